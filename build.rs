@@ -0,0 +1,133 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Computes the values baked into `abi::PluginAbi::current()`: the rustc
+//! version, a hash over this crate's sources and manifest (which pins
+//! the `isabelle-dm` revision) and the locked versions of the dependencies
+//! whose types cross the plugin boundary.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn collect_files(dir: &Path, out: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, out);
+        } else {
+            out.push(path);
+        }
+    }
+}
+
+/// Dependencies whose types plugins and host pass to each other.
+const BOUNDARY_CRATES: &[&str] = &["actix-web", "cookie", "isabelle-dm", "serde_json", "tokio"];
+
+/// `Cargo.lock` this build was resolved with: the one of the workspace
+/// whose target directory holds `OUT_DIR`, or else one above this crate.
+fn find_lockfile(root: &Path) -> Option<PathBuf> {
+    let out = PathBuf::from(std::env::var("OUT_DIR").ok()?);
+    out.ancestors()
+        .chain(root.ancestors())
+        .map(|dir| dir.join("Cargo.lock"))
+        .find(|lock| lock.is_file())
+}
+
+/// `name version (source)` of every locked package in [`BOUNDARY_CRATES`].
+fn locked_versions(lock: &str) -> Vec<String> {
+    let mut locked: Vec<String> = lock
+        .split("[[package]]")
+        .skip(1)
+        .filter_map(|package| {
+            let field = |key: &str| {
+                package.lines().find_map(|line| {
+                    let value = line.strip_prefix(key)?.strip_prefix(" = ")?;
+                    Some(value.trim_matches('"'))
+                })
+            };
+            let name = field("name")?;
+            if !BOUNDARY_CRATES.contains(&name) {
+                return None;
+            }
+            let version = field("version").unwrap_or("?");
+            Some(match field("source") {
+                Some(source) => format!("{} {} ({})", name, version, source),
+                None => format!("{} {}", name, version),
+            })
+        })
+        .collect();
+    locked.sort();
+    locked
+}
+
+/// 64-bit FNV-1a; stable across toolchains, unlike `DefaultHasher`.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+fn main() {
+    let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+    let rustc_version = Command::new(rustc)
+        .arg("--version")
+        .output()
+        .ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+    let mut files = vec![root.join("Cargo.toml")];
+    collect_files(&root.join("src"), &mut files);
+    files.sort();
+
+    let mut hash = 0xcbf2_9ce4_8422_2325;
+    for file in &files {
+        let rel = file.strip_prefix(&root).unwrap_or(file);
+        hash = fnv1a(hash, rel.to_string_lossy().as_bytes());
+        hash = fnv1a(hash, &fs::read(file).unwrap_or_default());
+    }
+
+    let lockfile = find_lockfile(&root);
+    let dependencies = match lockfile.as_ref().and_then(|l| fs::read_to_string(l).ok()) {
+        Some(lock) => locked_versions(&lock).join(", "),
+        None => {
+            println!("cargo:warning=Cargo.lock not found; plugin ABI descriptors won't pin dependency versions");
+            "unlocked".to_string()
+        }
+    };
+
+    println!(
+        "cargo:rustc-env=ISABELLE_PLUGIN_RUSTC_VERSION={}",
+        rustc_version
+    );
+    println!("cargo:rustc-env=ISABELLE_PLUGIN_API_HASH={:016x}", hash);
+    println!(
+        "cargo:rustc-env=ISABELLE_PLUGIN_DEPENDENCIES={}",
+        dependencies
+    );
+    if let Some(lockfile) = lockfile {
+        println!("cargo:rerun-if-changed={}", lockfile.display());
+    }
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-env-changed=RUSTC");
+}
@@ -0,0 +1,32 @@
+//! Minimal actor plugin library for `PluginRegistry::load_dir`. Build with
+//! `cargo build --example actor_plugin` and install the result as
+//! `libisabelle_actor_plugin_<name>.so`. The hot-reload tests load it too.
+//!
+//! The plugin answers on a thread of its own: the library carries its own
+//! copy of tokio, which doesn't see the host's runtime.
+
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::manifest::PluginManifest;
+use tokio::sync::mpsc;
+
+isabelle_plugin_api::export_plugin_abi!();
+
+#[no_mangle]
+pub fn register(reg: &mut PluginRegistry, _core: CoreHandle) {
+    let (tx, mut rx) = mpsc::channel(8);
+    std::thread::spawn(move || {
+        while let Some(msg) = rx.blocking_recv() {
+            match msg {
+                PluginHookMessage::Ping { reply } => {
+                    let _ = reply.send(());
+                }
+                PluginHookMessage::Shutdown => break,
+                _ => {}
+            }
+        }
+    });
+    reg.add_with_manifest(
+        PluginManifest::new("actor-example", env!("CARGO_PKG_VERSION")),
+        tx,
+    );
+}
@@ -0,0 +1,118 @@
+//! Minimal legacy plugin library for `PluginPool::load_plugins`. Build with
+//! `cargo build --example legacy_plugin` and install the result as
+//! `libisabelle_plugin_<name>.so`. The hot-reload tests load it too.
+
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::process_result::ProcessResult;
+use isabelle_plugin_api::api::*;
+use isabelle_plugin_api::manifest::PluginManifest;
+use std::collections::HashMap;
+
+isabelle_plugin_api::export_plugin_abi!();
+
+struct ExamplePlugin;
+
+impl Plugin for ExamplePlugin {
+    fn ping_test(&mut self) {}
+
+    fn item_pre_edit_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _user: &Option<Item>,
+        _collection: &str,
+        _old_itm: Option<Item>,
+        _itm: &mut Item,
+        _action: DataObjectAction,
+        _merge: bool,
+    ) -> ProcessResult {
+        ProcessResult {
+            succeeded: true,
+            data: HashMap::new(),
+            error: "".to_string(),
+        }
+    }
+
+    fn item_post_edit_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _user: &Option<Item>,
+        _collection: &str,
+        _old_itm: Option<Item>,
+        _new_itm: Option<Item>,
+        _id: u64,
+        _action: DataObjectAction,
+        _merge: bool,
+    ) {
+    }
+
+    fn item_auth_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _user: &Option<Item>,
+        _collection: &str,
+        _id: u64,
+        _new_item: Option<Item>,
+        _del: bool,
+    ) -> bool {
+        true
+    }
+
+    fn item_list_filter_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _user: &Option<Item>,
+        _collection: &str,
+        _context: &str,
+        _map: &mut HashMap<u64, Item>,
+    ) {
+    }
+
+    fn route_url_hook(&mut self, _api: &Box<dyn PluginApi>, _ctx: &RequestContext) -> WebResponse {
+        WebResponse::NotImplemented
+    }
+
+    fn route_unprotected_url_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _ctx: &RequestContext,
+    ) -> WebResponse {
+        WebResponse::NotImplemented
+    }
+
+    fn route_unprotected_url_post_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _ctx: &RequestContext,
+        _itm: &Item,
+        _parts: &[UploadPart],
+    ) -> WebResponse {
+        WebResponse::NotImplemented
+    }
+
+    fn collection_read_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _collection: &str,
+        _itm: &mut Item,
+    ) -> bool {
+        false
+    }
+
+    fn call_otp_hook(&mut self, _api: &Box<dyn PluginApi>, _hndl: &str, _itm: &Item) {}
+
+    fn manifest(&self) -> PluginManifest {
+        PluginManifest::new("legacy-example", env!("CARGO_PKG_VERSION"))
+    }
+}
+
+#[no_mangle]
+#[allow(improper_ctypes_definitions)]
+pub extern "C" fn register(pool: &mut dyn PluginPoolApi) {
+    pool.register(Box::new(ExamplePlugin));
+}
@@ -0,0 +1,220 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! ABI handshake for dynamically loaded plugins.
+//!
+//! `register` is called through a Rust-ABI function pointer with Rust
+//! types (`&mut dyn PluginPoolApi`, `&mut PluginRegistry`, `CoreHandle`),
+//! which is only sound when plugin and host were built with the same
+//! compiler, the same revision of this crate and the same versions of the
+//! dependencies whose types cross the boundary (tokio channels,
+//! `isabelle-dm` items, `serde_json` values, actix cookies). Every plugin
+//! therefore exports a [`PluginAbi`] descriptor, and both loaders refuse
+//! to call `register` unless it matches the host's.
+//!
+//! ```ignore
+//! // In plugin crate, next to `register`:
+//! isabelle_plugin_api::export_plugin_abi!();
+//! ```
+
+use actix_web::cookie::Cookie;
+use isabelle_dm::data_model::item::Item;
+use libloading::{Library, Symbol};
+use std::any::type_name;
+use std::ffi::{c_char, CStr};
+use std::mem::{align_of, size_of};
+use tokio::sync::{mpsc, oneshot};
+
+use crate::actor::{CoreHandle, CoreMessage, PluginHookMessage, PluginRegistry};
+use crate::api::{CustomResponse, Plugin, PluginApi, WebResponse};
+use crate::manifest::PluginManifest;
+use crate::request::RequestContext;
+use crate::upload::UploadPart;
+
+/// Name of the symbol exported by [`export_plugin_abi!`].
+pub const PLUGIN_ABI_SYMBOL: &[u8] = b"isabelle_plugin_abi";
+
+/// Layout revision of [`PluginAbi`] itself.
+pub const PLUGIN_ABI_DESCRIPTOR_VERSION: u32 = 1;
+
+/// Signature of the [`PLUGIN_ABI_SYMBOL`] function.
+pub type PluginAbiFn = extern "C" fn() -> PluginAbi;
+
+/// Build fingerprint of a plugin (or host). `#[repr(C)]` with C strings so
+/// it can be read safely even from a plugin built with another compiler.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct PluginAbi {
+    /// Always [`PLUGIN_ABI_DESCRIPTOR_VERSION`] of the build that made it;
+    /// the remaining fields are only read when it matches.
+    pub descriptor_version: u32,
+    /// `isabelle-plugin-api` crate version.
+    pub crate_version: *const c_char,
+    /// Output of `rustc --version`.
+    pub rustc_version: *const c_char,
+    /// Hash over this crate's sources and manifest, which covers the
+    /// `isabelle-dm` revision it is pinned to.
+    pub api_hash: u64,
+    /// Versions of the boundary dependencies in the `Cargo.lock` the build
+    /// was resolved with, e.g. "serde_json 1.0.128, tokio 1.40.0".
+    pub dependencies: *const c_char,
+    /// Hash over the name, size and alignment of the types passed between
+    /// plugin and host, which also depend on enabled crate features.
+    pub layout_hash: u64,
+}
+
+/// 64-bit FNV-1a, as in `build.rs`.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+macro_rules! layouts {
+    ($($t:ty),* $(,)?) => {
+        [$((type_name::<$t>(), size_of::<$t>(), align_of::<$t>())),*]
+    };
+}
+
+fn layout_hash() -> u64 {
+    let layouts = layouts![
+        CoreHandle,
+        CoreMessage,
+        PluginHookMessage,
+        PluginRegistry,
+        PluginManifest,
+        RequestContext,
+        UploadPart,
+        WebResponse,
+        CustomResponse,
+        Box<dyn Plugin>,
+        Box<dyn PluginApi>,
+        Item,
+        serde_json::Value,
+        Cookie<'static>,
+        mpsc::Sender<PluginHookMessage>,
+        mpsc::Sender<CoreMessage>,
+        oneshot::Sender<WebResponse>,
+    ];
+    layouts
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, (name, size, align)| {
+            let hash = fnv1a(hash, name.as_bytes());
+            let hash = fnv1a(hash, &size.to_le_bytes());
+            fnv1a(hash, &align.to_le_bytes())
+        })
+}
+
+impl PluginAbi {
+    /// Fingerprint of the build this code is compiled into.
+    pub fn current() -> Self {
+        Self {
+            descriptor_version: PLUGIN_ABI_DESCRIPTOR_VERSION,
+            crate_version: concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char,
+            rustc_version: concat!(env!("ISABELLE_PLUGIN_RUSTC_VERSION"), "\0").as_ptr()
+                as *const c_char,
+            api_hash: u64::from_str_radix(env!("ISABELLE_PLUGIN_API_HASH"), 16).unwrap_or(0),
+            dependencies: concat!(env!("ISABELLE_PLUGIN_DEPENDENCIES"), "\0").as_ptr()
+                as *const c_char,
+            layout_hash: layout_hash(),
+        }
+    }
+
+    /// Compare this (plugin) descriptor against `host`. The error lists
+    /// every mismatching field.
+    ///
+    /// # Safety
+    ///
+    /// The string pointers of both descriptors must be valid NUL-terminated
+    /// strings, as produced by [`PluginAbi::current`].
+    pub unsafe fn check_against(&self, host: &PluginAbi) -> Result<(), String> {
+        if self.descriptor_version != host.descriptor_version {
+            return Err(format!(
+                "ABI descriptor version {} does not match host version {}",
+                self.descriptor_version, host.descriptor_version
+            ));
+        }
+
+        let mut mismatches = Vec::new();
+        let fields = [
+            (
+                "isabelle-plugin-api",
+                self.crate_version,
+                host.crate_version,
+            ),
+            ("rustc", self.rustc_version, host.rustc_version),
+            ("dependencies", self.dependencies, host.dependencies),
+        ];
+        for (what, plugin, host) in fields {
+            let plugin = CStr::from_ptr(plugin).to_string_lossy();
+            let host = CStr::from_ptr(host).to_string_lossy();
+            if plugin != host {
+                mismatches.push(format!("{} {} (host: {})", what, plugin, host));
+            }
+        }
+        if self.api_hash != host.api_hash {
+            mismatches.push(format!(
+                "API revision {:016x} (host: {:016x})",
+                self.api_hash, host.api_hash
+            ));
+        }
+        if self.layout_hash != host.layout_hash {
+            mismatches.push(format!(
+                "type layouts {:016x} (host: {:016x})",
+                self.layout_hash, host.layout_hash
+            ));
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("built with {}", mismatches.join(", ")))
+        }
+    }
+}
+
+/// Check the ABI descriptor exported by `lib` against this build. Called by
+/// the loaders before `register`.
+///
+/// # Safety
+///
+/// `lib` must be a trusted plugin library; its descriptor function is
+/// called.
+pub(crate) unsafe fn verify_plugin_abi(lib: &Library, path: &str) -> Result<(), String> {
+    let func = lib
+        .get::<Symbol<PluginAbiFn>>(PLUGIN_ABI_SYMBOL)
+        .map_err(|e| {
+            format!(
+                "Plugin {} does not export an ABI descriptor (add `export_plugin_abi!()`): {}",
+                path, e
+            )
+        })?;
+    func()
+        .check_against(&PluginAbi::current())
+        .map_err(|e| format!("Plugin {} is ABI-incompatible: {}", path, e))
+}
+
+/// Export the [`PluginAbi`] descriptor of the plugin crate it is invoked in.
+/// Every dynamically loaded plugin must call this once at its crate root.
+#[macro_export]
+macro_rules! export_plugin_abi {
+    () => {
+        #[no_mangle]
+        pub extern "C" fn isabelle_plugin_abi() -> $crate::abi::PluginAbi {
+            $crate::abi::PluginAbi::current()
+        }
+    };
+}
@@ -41,11 +41,26 @@
 //!
 //! ### Migration shape
 //!
+//! A plugin library links its own copy of tokio, which doesn't see the
+//! host's runtime, so `register` must not call `tokio::spawn`. It starts a
+//! thread of its own instead, with a runtime of its own if the plugin is
+//! async:
+//!
 //! ```ignore
-//! // In plugin crate:
+//! // In plugin crate, built as `libisabelle_actor_plugin_<name>` for
+//! // `PluginRegistry::load_dir`:
+//! isabelle_plugin_api::export_plugin_abi!();
+//!
+//! #[no_mangle]
 //! pub fn register(reg: &mut PluginRegistry, core: CoreHandle) {
 //!     let (tx, rx) = tokio::sync::mpsc::channel(64);
-//!     tokio::spawn(run_my_plugin(rx, core));
+//!     std::thread::spawn(move || {
+//!         tokio::runtime::Builder::new_current_thread()
+//!             .enable_all()
+//!             .build()
+//!             .expect("failed to start the plugin runtime")
+//!             .block_on(run_my_plugin(rx, core))
+//!     });
 //!     reg.add("my-plugin", tx);
 //! }
 //!
@@ -65,14 +80,38 @@
 //! }
 //! ```
 
+use futures_core::Stream;
 use isabelle_dm::data_model::data_object_action::DataObjectAction;
 use isabelle_dm::data_model::item::Item;
 use isabelle_dm::data_model::list_result::ListResult;
 use isabelle_dm::data_model::process_result::ProcessResult;
-use std::collections::HashMap;
+use log::{info, warn};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::runtime::{Builder, Handle};
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
 
 use crate::api::WebResponse;
+use crate::live::{
+    next_connection_id, CloseNotice, ConnectionId, SseConn, SseSession, WebSocketConn,
+    WebSocketSession, CONNECTION_CAPACITY,
+};
+use crate::manifest::{HookKind, PluginManifest};
+use crate::plugin_pool::{
+    canonical_plugin_path, find_plugin_libraries, log_load_result, missing_register_symbol,
+    open_plugin_library, PluginFileChange, PluginLoadFailure, PluginLoadResult,
+};
+use crate::query::{Filter, Sort};
+use crate::request::RequestContext;
+use crate::route::{match_routes, RouteMatch};
+use crate::upload::UploadPart;
+use libloading::Library;
 
 // ---------------------------------------------------------------------------
 // Replies
@@ -145,12 +184,17 @@ pub enum PluginHookMessage {
     },
 
     /// Notification after an item was written. Fire-and-forget (no reply).
+    /// `new_item` is the item as persisted (`None` after a delete), `user`
+    /// the user who made the edit.
     ItemPostEdit {
         hndl: String,
+        user: Option<Item>,
         collection: String,
         old_item: Option<Item>,
+        new_item: Option<Item>,
         id: u64,
         action: DataObjectAction,
+        merge: bool,
     },
 
     /// Authorization check. Plugin returns true to allow, false to deny.
@@ -210,48 +254,63 @@ pub enum PluginHookMessage {
 
     /// Authenticated GET-style route.
     RouteUrl {
-        hndl: String,
-        user: Option<Item>,
-        query: String,
+        ctx: RequestContext,
         reply: oneshot::Sender<WebResponse>,
     },
 
-    /// Authenticated POST-style route with a parsed multipart item.
+    /// Authenticated POST-style route with a parsed multipart item and
+    /// any uploaded files.
     RouteUrlPost {
-        hndl: String,
-        user: Option<Item>,
-        query: String,
+        ctx: RequestContext,
         item: Item,
+        /// Shared by every plugin asked, so fanning out doesn't copy them.
+        parts: Arc<[UploadPart]>,
         reply: oneshot::Sender<WebResponse>,
     },
 
     /// Public (unauthenticated) GET-style route.
     RouteUnprotectedUrl {
-        hndl: String,
-        user: Option<Item>,
-        query: String,
+        ctx: RequestContext,
         reply: oneshot::Sender<WebResponse>,
     },
 
     /// Public POST route.
     RouteUnprotectedUrlPost {
-        hndl: String,
-        user: Option<Item>,
-        query: String,
+        ctx: RequestContext,
         item: Item,
+        parts: Arc<[UploadPart]>,
         reply: oneshot::Sender<WebResponse>,
     },
 
-    /// REST-style route with method + raw body.
+    /// REST-style route with raw body; the method is `ctx.method`.
     RouteRest {
-        hndl: String,
-        method: String,
-        user: Option<Item>,
-        query: String,
+        ctx: RequestContext,
         payload: String,
         reply: oneshot::Sender<WebResponse>,
     },
 
+    /// WebSocket opened on a route; see [`crate::live`]. Reply
+    /// `WebResponse::Ok` to accept `conn`.
+    WebSocketConnect {
+        ctx: RequestContext,
+        conn: WebSocketConn,
+        reply: oneshot::Sender<WebResponse>,
+    },
+
+    /// Server-sent event stream opened on a route; see [`crate::live`].
+    /// Reply `WebResponse::Ok` to accept `conn`.
+    SseConnect {
+        ctx: RequestContext,
+        conn: SseConn,
+        reply: oneshot::Sender<WebResponse>,
+    },
+
+    /// A connection the plugin accepted was closed by the client or core.
+    /// Always delivered to that plugin.
+    ConnectionClosed {
+        id: ConnectionId,
+    },
+
     /// Liveness probe at startup. Replaces the old `ping_test`.
     Ping {
         reply: oneshot::Sender<()>,
@@ -274,6 +333,17 @@ pub struct CollectionReadReply {
 // plugin -> core messages
 // ---------------------------------------------------------------------------
 
+/// `u64s` key under which core keeps an item's revision. Core bumps it on
+/// every write to the item (a value sent by the plugin is ignored), so
+/// items returned by `DbGetItem`/`DbGetItems` carry the revision to pass
+/// to [`CoreMessage::DbSetItemIf`].
+pub const REVISION_KEY: &str = "_rev";
+
+/// Revision of a stored item; 0 if it has none (never stored).
+pub fn item_revision(item: &Item) -> u64 {
+    item.u64s.get(REVISION_KEY).copied().unwrap_or(0)
+}
+
 /// Requests sent from a plugin back into core. Core's main processing task
 /// owns the database/auth/secrets state and answers via oneshot replies.
 #[non_exhaustive]
@@ -311,6 +381,34 @@ pub enum CoreMessage {
         id: u64,
         reply: oneshot::Sender<bool>,
     },
+    /// `DbSetItem` that only goes through if the stored item is still at
+    /// `expected_revision` (0: the item must not exist yet). `item.id`
+    /// must be set. Replies with the new revision.
+    DbSetItemIf {
+        collection: String,
+        item: Item,
+        merge: bool,
+        expected_revision: u64,
+        reply: oneshot::Sender<Result<u64, ConditionalWriteError>>,
+    },
+    /// Start a change feed: core sends a [`ChangeEvent`] on `events` for
+    /// every write to `collection` whose old or new item matches `filter`
+    /// (same syntax as `DbGetItems`), whoever made it. The feed ends when
+    /// the receiver is dropped, or when the subscriber falls so far behind
+    /// that `events` is full; core then drops its sender.
+    DbSubscribe {
+        collection: String,
+        filter: String,
+        events: mpsc::Sender<ChangeEvent>,
+        reply: oneshot::Sender<Result<(), String>>,
+    },
+    /// Apply several writes atomically: either every op succeeds and the
+    /// reply holds one id per op, in order, or none is applied and the
+    /// first failing op is reported.
+    DbBatch {
+        ops: Vec<BatchOp>,
+        reply: oneshot::Sender<Result<Vec<u64>, BatchError>>,
+    },
 
     // --- Globals ---
     GlobalsGetPublicUrl {
@@ -386,60 +484,308 @@ pub enum CoreMessage {
         id: u64,
         reply: oneshot::Sender<Option<Item>>,
     },
+    SecretGetByName {
+        name: String,
+        reply: oneshot::Sender<Option<Item>>,
+    },
+    /// (id, name) pairs of all secrets, sorted by name.
+    SecretList {
+        reply: oneshot::Sender<Vec<(u64, String)>>,
+    },
+    /// Insert or update a secret; same semantics as `PluginApi::secret_set`,
+    /// including the `"<hidden>"` keep-existing rule.
+    SecretSet {
+        item: Item,
+        merge: bool,
+        reply: oneshot::Sender<Result<u64, String>>,
+    },
+    SecretDel {
+        id: u64,
+        reply: oneshot::Sender<bool>,
+    },
+}
+
+/// Why a [`CoreMessage::DbSetItemIf`] was not applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConditionalWriteError {
+    /// The stored item is at revision `current` (0: it doesn't exist).
+    Conflict { current: u64 },
+    /// Core refused the write for another reason.
+    Rejected(String),
+}
+
+/// What a write did to an item, see [`ChangeEvent`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Created,
+    Updated,
+    Deleted,
+}
+
+/// A write reported on a [`CoreMessage::DbSubscribe`] feed.
+#[derive(Debug, Clone)]
+pub struct ChangeEvent {
+    pub collection: String,
+    pub id: u64,
+    pub kind: ChangeKind,
+    /// Item before the write; `None` for `Created`.
+    pub old_item: Option<Item>,
+    /// Item after the write; `None` for `Deleted`.
+    pub new_item: Option<Item>,
+}
+
+/// One write in a [`CoreMessage::DbBatch`].
+#[derive(Debug, Clone)]
+pub enum BatchOp {
+    /// Same as `DbSetItem`; its result is the stored item's id.
+    Set {
+        collection: String,
+        item: Item,
+        merge: bool,
+    },
+    /// Same as `DbDelItem`, except that a missing item fails the batch.
+    /// Its result is the removed id.
+    Del { collection: String, id: u64 },
+}
+
+impl BatchOp {
+    pub fn set(collection: &str, item: &Item, merge: bool) -> Self {
+        BatchOp::Set {
+            collection: collection.to_string(),
+            item: item.clone(),
+            merge,
+        }
+    }
+
+    pub fn del(collection: &str, id: u64) -> Self {
+        BatchOp::Del {
+            collection: collection.to_string(),
+            id,
+        }
+    }
+
+    pub fn collection(&self) -> &str {
+        match self {
+            BatchOp::Set { collection, .. } | BatchOp::Del { collection, .. } => collection,
+        }
+    }
+}
+
+/// Why a [`CoreMessage::DbBatch`] was not applied.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchError {
+    /// Position of the failing op in the batch.
+    pub index: usize,
+    pub reason: String,
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "batch op {} failed: {}", self.index, self.reason)
+    }
 }
 
+impl std::error::Error for BatchError {}
+
 // ---------------------------------------------------------------------------
 // CoreHandle: ergonomic API plugins use to talk to core
 // ---------------------------------------------------------------------------
 
+/// Why a [`CoreHandle`] request didn't produce an answer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoreError {
+    /// Core's processing task is gone (the channel is closed).
+    CoreShutdown,
+    /// Core accepted the request but dropped the reply sender.
+    ReplyDropped,
+    /// No reply within the handle's timeout (see [`CoreHandle::with_timeout`]).
+    Timeout,
+    /// Core answered, but refused the operation.
+    Denied(String),
+    /// Core refused a batch; nothing in it was applied.
+    BatchFailed(BatchError),
+    /// A conditional write lost the race: the item is now at revision
+    /// `current` (0: it doesn't exist).
+    Conflict { current: u64 },
+}
+
+impl fmt::Display for CoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CoreError::CoreShutdown => write!(f, "core unavailable"),
+            CoreError::ReplyDropped => write!(f, "core dropped the reply"),
+            CoreError::Timeout => write!(f, "core did not reply in time"),
+            CoreError::Denied(reason) => write!(f, "denied by core: {}", reason),
+            CoreError::BatchFailed(e) => write!(f, "denied by core: {}", e),
+            CoreError::Conflict { current } => {
+                write!(f, "item changed concurrently (now at revision {})", current)
+            }
+        }
+    }
+}
+
+impl std::error::Error for CoreError {}
+
+/// Events a [`CoreHandle::subscribe`] feed buffers before core ends it.
+pub const CHANGE_FEED_CAPACITY: usize = 256;
+
+/// Runtime, on a thread of its own, that drives the requests of the
+/// blocking `CoreHandle` methods, so callers outside tokio need none.
+fn blocking_runtime() -> &'static Handle {
+    static RUNTIME: OnceLock<Handle> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        let runtime = Builder::new_current_thread()
+            .enable_time()
+            .build()
+            .expect("failed to build the blocking request runtime");
+        let handle = runtime.handle().clone();
+        std::thread::Builder::new()
+            .name("core-blocking-requests".to_string())
+            .spawn(move || runtime.block_on(std::future::pending::<()>()))
+            .expect("failed to start the blocking request runtime");
+        handle
+    })
+}
+
 /// Cloneable handle plugins use to call back into core. Wraps an mpsc
 /// sender and exposes async methods that match the operations the old
 /// `PluginApi` trait offered. Each method packages a [`CoreMessage`],
 /// sends it, and awaits the oneshot reply.
+///
+/// Every method has a `try_*` counterpart returning `Result<T, CoreError>`;
+/// the plain methods map errors to the same sentinels the old `PluginApi`
+/// impl returned on failure.
 #[derive(Clone)]
 pub struct CoreHandle {
     tx: mpsc::Sender<CoreMessage>,
+    timeout: Option<Duration>,
 }
 
 impl CoreHandle {
     pub fn new(tx: mpsc::Sender<CoreMessage>) -> Self {
-        Self { tx }
+        Self { tx, timeout: None }
+    }
+
+    /// Bound every request (send + reply) to `timeout`. Without it,
+    /// requests wait for core indefinitely.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    async fn bounded<T>(
+        &self,
+        fut: impl std::future::Future<Output = Result<T, CoreError>>,
+    ) -> Result<T, CoreError> {
+        match self.timeout {
+            Some(t) => timeout(t, fut).await.unwrap_or(Err(CoreError::Timeout)),
+            None => fut.await,
+        }
+    }
+
+    async fn try_request<T>(
+        &self,
+        build: impl FnOnce(oneshot::Sender<T>) -> CoreMessage,
+    ) -> Result<T, CoreError> {
+        let (rtx, rrx) = oneshot::channel();
+        self.bounded(async {
+            self.tx
+                .send(build(rtx))
+                .await
+                .map_err(|_| CoreError::CoreShutdown)?;
+            rrx.await.map_err(|_| CoreError::ReplyDropped)
+        })
+        .await
+    }
+
+    async fn try_notify(&self, msg: CoreMessage) -> Result<(), CoreError> {
+        self.bounded(async { self.tx.send(msg).await.map_err(|_| CoreError::CoreShutdown) })
+            .await
+    }
+
+    /// Blocking counterpart of `bounded`: runs `fut` on the
+    /// [`blocking_runtime`] and waits on the calling thread for its result.
+    fn blocking_bounded<T: Send + 'static>(
+        &self,
+        fut: impl Future<Output = Result<T, CoreError>> + Send + 'static,
+    ) -> Result<T, CoreError> {
+        let (tx, rx) = std::sync::mpsc::sync_channel(1);
+        let core = self.clone();
+        blocking_runtime().spawn(async move {
+            let _ = tx.send(core.bounded(fut).await);
+        });
+        rx.recv().unwrap_or(Err(CoreError::CoreShutdown))
     }
 
-    /// Errors are intentionally swallowed: if core is shutting down, the
-    /// channel is closed, and plugins should treat that as a no-op rather
-    /// than blowing up mid-request. Same sentinel choice as the old
-    /// `PluginApi` impl (which returned defaults on failure).
-    async fn request<T>(
+    /// Blocking counterpart of `try_request` for callers running outside
+    /// the tokio runtime (e.g. legacy plugin threads), bounded by the
+    /// handle's timeout like the async methods. Blocks the calling thread;
+    /// called from a runtime worker, it blocks that worker too, for up to
+    /// the timeout (or indefinitely without one).
+    pub(crate) fn blocking_request<T: Send + 'static>(
         &self,
         build: impl FnOnce(oneshot::Sender<T>) -> CoreMessage,
-    ) -> Option<T> {
+    ) -> Result<T, CoreError> {
         let (rtx, rrx) = oneshot::channel();
-        self.tx.send(build(rtx)).await.ok()?;
-        rrx.await.ok()
+        let msg = build(rtx);
+        let tx = self.tx.clone();
+        self.blocking_bounded(async move {
+            tx.send(msg).await.map_err(|_| CoreError::CoreShutdown)?;
+            rrx.await.map_err(|_| CoreError::ReplyDropped)
+        })
+    }
+
+    pub(crate) fn blocking_notify(&self, msg: CoreMessage) -> Result<(), CoreError> {
+        let tx = self.tx.clone();
+        self.blocking_bounded(
+            async move { tx.send(msg).await.map_err(|_| CoreError::CoreShutdown) },
+        )
+    }
+
+    pub(crate) fn unavailable() -> ProcessResult {
+        ProcessResult {
+            succeeded: false,
+            error: "core unavailable".into(),
+            data: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn empty_list() -> ListResult {
+        ListResult {
+            map: HashMap::new(),
+            total_count: 0,
+        }
     }
 
     // --- Database ---
-    pub async fn db_get_all_items(
+    pub async fn try_db_get_all_items(
         &self,
         collection: &str,
         sort_key: &str,
         filter: &str,
-    ) -> ListResult {
-        self.request(|reply| CoreMessage::DbGetAllItems {
+    ) -> Result<ListResult, CoreError> {
+        self.try_request(|reply| CoreMessage::DbGetAllItems {
             collection: collection.into(),
             sort_key: sort_key.into(),
             filter: filter.into(),
             reply,
         })
         .await
-        .unwrap_or(ListResult {
-            map: HashMap::new(),
-            total_count: 0,
-        })
     }
 
-    pub async fn db_get_items(
+    pub async fn db_get_all_items(
+        &self,
+        collection: &str,
+        sort_key: &str,
+        filter: &str,
+    ) -> ListResult {
+        self.try_db_get_all_items(collection, sort_key, filter)
+            .await
+            .unwrap_or_else(|_| Self::empty_list())
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_db_get_items(
         &self,
         collection: &str,
         id_min: u64,
@@ -448,8 +794,8 @@ impl CoreHandle {
         filter: &str,
         skip: u64,
         limit: u64,
-    ) -> ListResult {
-        self.request(|reply| CoreMessage::DbGetItems {
+    ) -> Result<ListResult, CoreError> {
+        self.try_request(|reply| CoreMessage::DbGetItems {
             collection: collection.into(),
             id_min,
             id_max,
@@ -460,194 +806,620 @@ impl CoreHandle {
             reply,
         })
         .await
-        .unwrap_or(ListResult {
-            map: HashMap::new(),
-            total_count: 0,
-        })
     }
 
-    pub async fn db_get_item(&self, collection: &str, id: u64) -> Option<Item> {
-        self.request(|reply| CoreMessage::DbGetItem {
+    pub async fn db_get_items(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort_key: &str,
+        filter: &str,
+        skip: u64,
+        limit: u64,
+    ) -> ListResult {
+        self.try_db_get_items(collection, id_min, id_max, sort_key, filter, skip, limit)
+            .await
+            .unwrap_or_else(|_| Self::empty_list())
+    }
+
+    /// [`CoreHandle::try_db_get_all_items`] with a typed sort and filter.
+    pub async fn try_db_find_all_items(
+        &self,
+        collection: &str,
+        sort: &Sort,
+        filter: &Filter,
+    ) -> Result<ListResult, CoreError> {
+        self.try_db_get_all_items(collection, sort.as_str(), &filter.to_string())
+            .await
+    }
+
+    pub async fn db_find_all_items(
+        &self,
+        collection: &str,
+        sort: &Sort,
+        filter: &Filter,
+    ) -> ListResult {
+        self.db_get_all_items(collection, sort.as_str(), &filter.to_string())
+            .await
+    }
+
+    /// [`CoreHandle::try_db_get_items`] with a typed sort and filter.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn try_db_find_items(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort: &Sort,
+        filter: &Filter,
+        skip: u64,
+        limit: u64,
+    ) -> Result<ListResult, CoreError> {
+        let filter = filter.to_string();
+        self.try_db_get_items(
+            collection,
+            id_min,
+            id_max,
+            sort.as_str(),
+            &filter,
+            skip,
+            limit,
+        )
+        .await
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn db_find_items(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort: &Sort,
+        filter: &Filter,
+        skip: u64,
+        limit: u64,
+    ) -> ListResult {
+        let filter = filter.to_string();
+        self.db_get_items(
+            collection,
+            id_min,
+            id_max,
+            sort.as_str(),
+            &filter,
+            skip,
+            limit,
+        )
+        .await
+    }
+
+    pub async fn try_db_get_item(
+        &self,
+        collection: &str,
+        id: u64,
+    ) -> Result<Option<Item>, CoreError> {
+        self.try_request(|reply| CoreMessage::DbGetItem {
             collection: collection.into(),
             id,
             reply,
         })
         .await
-        .flatten()
+    }
+
+    /// Iterate over the items of `collection` matching `filter` in
+    /// ascending id order, fetching `page_size` items at a time. Only one
+    /// page is held in memory; see [`ItemStream`].
+    pub fn db_stream_items(&self, collection: &str, filter: &Filter, page_size: u64) -> ItemStream {
+        ItemStream {
+            core: self.clone(),
+            collection: collection.to_string(),
+            filter: filter.to_string(),
+            page_size: page_size.max(1),
+            cursor: Some(0),
+            buffer: VecDeque::new(),
+            pending: None,
+        }
+    }
+
+    pub async fn db_get_item(&self, collection: &str, id: u64) -> Option<Item> {
+        self.try_db_get_item(collection, id).await.ok().flatten()
+    }
+
+    /// Core signals a rejected write with `u64::MAX`; that is reported as
+    /// [`CoreError::Denied`].
+    pub async fn try_db_set_item(
+        &self,
+        collection: &str,
+        item: &Item,
+        merge: bool,
+    ) -> Result<u64, CoreError> {
+        let id = self
+            .try_request(|reply| CoreMessage::DbSetItem {
+                collection: collection.into(),
+                item: item.clone(),
+                merge,
+                reply,
+            })
+            .await?;
+        if id == u64::MAX {
+            return Err(CoreError::Denied(format!(
+                "write to {} was rejected",
+                collection
+            )));
+        }
+        Ok(id)
     }
 
     pub async fn db_set_item(&self, collection: &str, item: &Item, merge: bool) -> u64 {
-        self.request(|reply| CoreMessage::DbSetItem {
+        self.try_db_set_item(collection, item, merge)
+            .await
+            .unwrap_or(u64::MAX)
+    }
+
+    /// Write `item` only if its stored revision is still
+    /// `expected_revision`, typically [`item_revision`] of the copy it was
+    /// edited from. Losing the race gives [`CoreError::Conflict`]; re-read
+    /// and retry. Returns the new revision.
+    pub async fn try_db_set_item_if(
+        &self,
+        collection: &str,
+        item: &Item,
+        merge: bool,
+        expected_revision: u64,
+    ) -> Result<u64, CoreError> {
+        self.try_request(|reply| CoreMessage::DbSetItemIf {
             collection: collection.into(),
             item: item.clone(),
             merge,
+            expected_revision,
             reply,
         })
-        .await
-        .unwrap_or(u64::MAX)
+        .await?
+        .map_err(|e| match e {
+            ConditionalWriteError::Conflict { current } => CoreError::Conflict { current },
+            ConditionalWriteError::Rejected(reason) => CoreError::Denied(reason),
+        })
     }
 
-    pub async fn db_del_item(&self, collection: &str, id: u64) -> bool {
-        self.request(|reply| CoreMessage::DbDelItem {
+    /// `None` if the item was not written.
+    pub async fn db_set_item_if(
+        &self,
+        collection: &str,
+        item: &Item,
+        merge: bool,
+        expected_revision: u64,
+    ) -> Option<u64> {
+        self.try_db_set_item_if(collection, item, merge, expected_revision)
+            .await
+            .ok()
+    }
+
+    pub async fn try_db_del_item(&self, collection: &str, id: u64) -> Result<bool, CoreError> {
+        self.try_request(|reply| CoreMessage::DbDelItem {
             collection: collection.into(),
             id,
             reply,
         })
         .await
-        .unwrap_or(false)
+    }
+
+    pub async fn db_del_item(&self, collection: &str, id: u64) -> bool {
+        self.try_db_del_item(collection, id).await.unwrap_or(false)
+    }
+
+    /// Apply `ops` all-or-nothing (see [`CoreMessage::DbBatch`]). A
+    /// rejected op is reported as [`CoreError::BatchFailed`].
+    pub async fn try_db_batch(&self, ops: Vec<BatchOp>) -> Result<Vec<u64>, CoreError> {
+        self.try_request(|reply| CoreMessage::DbBatch { ops, reply })
+            .await?
+            .map_err(CoreError::BatchFailed)
+    }
+
+    /// `None` if the batch was not applied.
+    pub async fn db_batch(&self, ops: Vec<BatchOp>) -> Option<Vec<u64>> {
+        self.try_db_batch(ops).await.ok()
+    }
+
+    /// Receive a [`ChangeEvent`] for every write to `collection` matching
+    /// `filter`, including those made by other plugins. Once the receiver
+    /// yields `None` the feed is over (see [`CoreMessage::DbSubscribe`]);
+    /// subscribe again and re-read to catch up.
+    pub async fn try_subscribe(
+        &self,
+        collection: &str,
+        filter: &Filter,
+    ) -> Result<mpsc::Receiver<ChangeEvent>, CoreError> {
+        let (events, rx) = mpsc::channel(CHANGE_FEED_CAPACITY);
+        self.try_request(|reply| CoreMessage::DbSubscribe {
+            collection: collection.into(),
+            filter: filter.to_string(),
+            events,
+            reply,
+        })
+        .await?
+        .map_err(CoreError::Denied)?;
+        Ok(rx)
+    }
+
+    /// On failure the returned receiver is already closed.
+    pub async fn subscribe(
+        &self,
+        collection: &str,
+        filter: &Filter,
+    ) -> mpsc::Receiver<ChangeEvent> {
+        match self.try_subscribe(collection, filter).await {
+            Ok(rx) => rx,
+            Err(_) => mpsc::channel(1).1,
+        }
     }
 
     // --- Globals ---
+    pub async fn try_globals_get_public_url(&self) -> Result<String, CoreError> {
+        self.try_request(|reply| CoreMessage::GlobalsGetPublicUrl { reply })
+            .await
+    }
+
     pub async fn globals_get_public_url(&self) -> String {
-        self.request(|reply| CoreMessage::GlobalsGetPublicUrl { reply })
+        self.try_globals_get_public_url().await.unwrap_or_default()
+    }
+
+    pub async fn try_globals_get_data_path(&self) -> Result<String, CoreError> {
+        self.try_request(|reply| CoreMessage::GlobalsGetDataPath { reply })
             .await
-            .unwrap_or_default()
     }
 
     pub async fn globals_get_data_path(&self) -> String {
-        self.request(|reply| CoreMessage::GlobalsGetDataPath { reply })
+        self.try_globals_get_data_path().await.unwrap_or_default()
+    }
+
+    pub async fn try_globals_get_settings(&self) -> Result<Item, CoreError> {
+        self.try_request(|reply| CoreMessage::GlobalsGetSettings { reply })
             .await
-            .unwrap_or_default()
     }
 
     pub async fn globals_get_settings(&self) -> Item {
-        self.request(|reply| CoreMessage::GlobalsGetSettings { reply })
+        self.try_globals_get_settings()
+            .await
+            .unwrap_or_else(|_| Item::new())
+    }
+
+    pub async fn try_globals_set_settings(&self, item: &Item) -> Result<(), CoreError> {
+        self.try_notify(CoreMessage::GlobalsSetSettings { item: item.clone() })
             .await
-            .unwrap_or_else(Item::new)
     }
 
     pub async fn globals_set_settings(&self, item: &Item) {
-        let _ = self
-            .tx
-            .send(CoreMessage::GlobalsSetSettings { item: item.clone() })
-            .await;
+        let _ = self.try_globals_set_settings(item).await;
     }
 
     // --- Auth ---
-    pub async fn auth_check_role(&self, item: &Option<Item>, role: &str) -> bool {
-        self.request(|reply| CoreMessage::AuthCheckRole {
+    pub async fn try_auth_check_role(
+        &self,
+        item: &Option<Item>,
+        role: &str,
+    ) -> Result<bool, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthCheckRole {
             item: item.clone(),
             role: role.into(),
             reply,
         })
         .await
-        .unwrap_or(false)
     }
 
-    pub async fn auth_get_new_salt(&self) -> String {
-        self.request(|reply| CoreMessage::AuthGetNewSalt { reply })
+    pub async fn auth_check_role(&self, item: &Option<Item>, role: &str) -> bool {
+        self.try_auth_check_role(item, role).await.unwrap_or(false)
+    }
+
+    pub async fn try_auth_get_new_salt(&self) -> Result<String, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthGetNewSalt { reply })
             .await
-            .unwrap_or_default()
     }
 
-    pub async fn auth_get_password_hash(&self, pw: &str, salt: &str) -> String {
-        self.request(|reply| CoreMessage::AuthGetPasswordHash {
+    pub async fn auth_get_new_salt(&self) -> String {
+        self.try_auth_get_new_salt().await.unwrap_or_default()
+    }
+
+    pub async fn try_auth_get_password_hash(
+        &self,
+        pw: &str,
+        salt: &str,
+    ) -> Result<String, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthGetPasswordHash {
             password: pw.into(),
             salt: salt.into(),
             reply,
         })
         .await
-        .unwrap_or_default()
     }
 
-    pub async fn auth_verify_password(&self, pw: &str, hash: &str) -> bool {
-        self.request(|reply| CoreMessage::AuthVerifyPassword {
+    pub async fn auth_get_password_hash(&self, pw: &str, salt: &str) -> String {
+        self.try_auth_get_password_hash(pw, salt)
+            .await
+            .unwrap_or_default()
+    }
+
+    pub async fn try_auth_verify_password(&self, pw: &str, hash: &str) -> Result<bool, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthVerifyPassword {
             password: pw.into(),
             hash: hash.into(),
             reply,
         })
         .await
-        .unwrap_or(false)
     }
 
-    pub async fn auth_login(&self, login: &str, password: &str) -> ProcessResult {
-        self.request(|reply| CoreMessage::AuthLogin {
+    pub async fn auth_verify_password(&self, pw: &str, hash: &str) -> bool {
+        self.try_auth_verify_password(pw, hash)
+            .await
+            .unwrap_or(false)
+    }
+
+    pub async fn try_auth_login(
+        &self,
+        login: &str,
+        password: &str,
+    ) -> Result<ProcessResult, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthLogin {
             login: login.into(),
             password: password.into(),
             reply,
         })
         .await
-        .unwrap_or_else(|| ProcessResult {
-            succeeded: false,
-            error: "core unavailable".into(),
-            data: HashMap::new(),
-        })
     }
 
-    pub async fn auth_logout(&self, login: &str) -> ProcessResult {
-        self.request(|reply| CoreMessage::AuthLogout {
+    pub async fn auth_login(&self, login: &str, password: &str) -> ProcessResult {
+        self.try_auth_login(login, password)
+            .await
+            .unwrap_or_else(|_| Self::unavailable())
+    }
+
+    pub async fn try_auth_logout(&self, login: &str) -> Result<ProcessResult, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthLogout {
             login: login.into(),
             reply,
         })
         .await
-        .unwrap_or_else(|| ProcessResult {
-            succeeded: false,
-            error: "core unavailable".into(),
-            data: HashMap::new(),
-        })
     }
 
-    pub async fn auth_register(&self, login: &str, email: &str) -> ProcessResult {
-        self.request(|reply| CoreMessage::AuthRegister {
+    pub async fn auth_logout(&self, login: &str) -> ProcessResult {
+        self.try_auth_logout(login)
+            .await
+            .unwrap_or_else(|_| Self::unavailable())
+    }
+
+    pub async fn try_auth_register(
+        &self,
+        login: &str,
+        email: &str,
+    ) -> Result<ProcessResult, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthRegister {
             login: login.into(),
             email: email.into(),
             reply,
         })
         .await
-        .unwrap_or_else(|| ProcessResult {
-            succeeded: false,
-            error: "core unavailable".into(),
-            data: HashMap::new(),
-        })
     }
 
-    pub async fn auth_gen_otp(&self, login: &str) -> ProcessResult {
-        self.request(|reply| CoreMessage::AuthGenOtp {
+    pub async fn auth_register(&self, login: &str, email: &str) -> ProcessResult {
+        self.try_auth_register(login, email)
+            .await
+            .unwrap_or_else(|_| Self::unavailable())
+    }
+
+    pub async fn try_auth_gen_otp(&self, login: &str) -> Result<ProcessResult, CoreError> {
+        self.try_request(|reply| CoreMessage::AuthGenOtp {
             login: login.into(),
             reply,
         })
         .await
-        .unwrap_or_else(|| ProcessResult {
-            succeeded: false,
-            error: "core unavailable".into(),
-            data: HashMap::new(),
-        })
+    }
+
+    pub async fn auth_gen_otp(&self, login: &str) -> ProcessResult {
+        self.try_auth_gen_otp(login)
+            .await
+            .unwrap_or_else(|_| Self::unavailable())
     }
 
     // --- Notifications ---
+    pub async fn try_send_email(
+        &self,
+        to: &str,
+        subject: &str,
+        body: &str,
+    ) -> Result<(), CoreError> {
+        self.try_notify(CoreMessage::SendEmail {
+            to: to.into(),
+            subject: subject.into(),
+            body: body.into(),
+        })
+        .await
+    }
+
     pub async fn send_email(&self, to: &str, subject: &str, body: &str) {
-        let _ = self
-            .tx
-            .send(CoreMessage::SendEmail {
-                to: to.into(),
-                subject: subject.into(),
-                body: body.into(),
-            })
-            .await;
+        let _ = self.try_send_email(to, subject, body).await;
     }
 
-    pub async fn init_google(&self) -> String {
-        self.request(|reply| CoreMessage::InitGoogle { reply })
+    pub async fn try_init_google(&self) -> Result<String, CoreError> {
+        self.try_request(|reply| CoreMessage::InitGoogle { reply })
             .await
-            .unwrap_or_default()
+    }
+
+    pub async fn init_google(&self) -> String {
+        self.try_init_google().await.unwrap_or_default()
+    }
+
+    pub async fn try_sync_with_google(
+        &self,
+        add: bool,
+        name: String,
+        date_time: String,
+    ) -> Result<(), CoreError> {
+        self.try_notify(CoreMessage::SyncWithGoogle {
+            add,
+            name,
+            date_time,
+        })
+        .await
     }
 
     pub async fn sync_with_google(&self, add: bool, name: String, date_time: String) {
-        let _ = self
-            .tx
-            .send(CoreMessage::SyncWithGoogle {
-                add,
-                name,
-                date_time,
-            })
-            .await;
+        let _ = self.try_sync_with_google(add, name, date_time).await;
     }
 
     // --- Secrets ---
+    pub async fn try_secret_get(&self, id: u64) -> Result<Option<Item>, CoreError> {
+        self.try_request(|reply| CoreMessage::SecretGet { id, reply })
+            .await
+    }
+
     pub async fn secret_get(&self, id: u64) -> Option<Item> {
-        self.request(|reply| CoreMessage::SecretGet { id, reply })
+        self.try_secret_get(id).await.ok().flatten()
+    }
+
+    pub async fn try_secret_get_by_name(&self, name: &str) -> Result<Option<Item>, CoreError> {
+        self.try_request(|reply| CoreMessage::SecretGetByName {
+            name: name.into(),
+            reply,
+        })
+        .await
+    }
+
+    pub async fn secret_get_by_name(&self, name: &str) -> Option<Item> {
+        self.try_secret_get_by_name(name).await.ok().flatten()
+    }
+
+    pub async fn try_secret_list(&self) -> Result<Vec<(u64, String)>, CoreError> {
+        self.try_request(|reply| CoreMessage::SecretList { reply })
+            .await
+    }
+
+    pub async fn secret_list(&self) -> Vec<(u64, String)> {
+        self.try_secret_list().await.unwrap_or_default()
+    }
+
+    /// Core's refusal message is reported as [`CoreError::Denied`].
+    pub async fn try_secret_set(&self, item: &Item, merge: bool) -> Result<u64, CoreError> {
+        self.try_request(|reply| CoreMessage::SecretSet {
+            item: item.clone(),
+            merge,
+            reply,
+        })
+        .await?
+        .map_err(CoreError::Denied)
+    }
+
+    pub async fn secret_set(&self, item: &Item, merge: bool) -> Result<u64, String> {
+        self.try_secret_set(item, merge).await.map_err(|e| match e {
+            CoreError::Denied(reason) => reason,
+            e => e.to_string(),
+        })
+    }
+
+    pub async fn try_secret_del(&self, id: u64) -> Result<bool, CoreError> {
+        self.try_request(|reply| CoreMessage::SecretDel { id, reply })
             .await
-            .flatten()
+    }
+
+    pub async fn secret_del(&self, id: u64) -> bool {
+        self.try_secret_del(id).await.unwrap_or(false)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// ItemStream: paged iteration over a collection
+// ---------------------------------------------------------------------------
+
+/// Sort key the [`ItemStream`] pages by. The cursor only works if every
+/// page holds the lowest matching ids above it.
+const STREAM_SORT_KEY: &str = "_id";
+
+type PageFuture = Pin<Box<dyn Future<Output = Result<ListResult, CoreError>> + Send>>;
+
+/// Items of a collection, yielded in ascending id order. Returned by
+/// [`CoreHandle::db_stream_items`].
+///
+/// Each page is requested with `id_min` just above the last id seen, so
+/// items added or removed while streaming don't shift later pages (unlike
+/// `skip`). The stream ends with the first empty page, so it doesn't
+/// matter if core returns fewer items than asked for. If core fails, the
+/// error is yielded as the last element.
+pub struct ItemStream {
+    core: CoreHandle,
+    collection: String,
+    filter: String,
+    page_size: u64,
+    /// `id_min` of the next page; `None` once the last page was fetched.
+    cursor: Option<u64>,
+    buffer: VecDeque<Item>,
+    pending: Option<PageFuture>,
+}
+
+impl ItemStream {
+    /// Next item, or `None` at the end. Same as `StreamExt::next`.
+    pub async fn next(&mut self) -> Option<Result<Item, CoreError>> {
+        std::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await
+    }
+
+    fn fetch(&self, id_min: u64) -> PageFuture {
+        let core = self.core.clone();
+        let collection = self.collection.clone();
+        let filter = self.filter.clone();
+        let limit = self.page_size;
+        Box::pin(async move {
+            core.try_db_get_items(
+                &collection,
+                id_min,
+                u64::MAX,
+                STREAM_SORT_KEY,
+                &filter,
+                0,
+                limit,
+            )
+            .await
+        })
+    }
+}
+
+impl Stream for ItemStream {
+    type Item = Result<Item, CoreError>;
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Item, CoreError>>> {
+        loop {
+            if let Some(item) = self.buffer.pop_front() {
+                return Poll::Ready(Some(Ok(item)));
+            }
+            if self.pending.is_none() {
+                match self.cursor {
+                    Some(id_min) => self.pending = Some(self.fetch(id_min)),
+                    None => return Poll::Ready(None),
+                }
+            }
+
+            let page = match self.pending.as_mut().unwrap().as_mut().poll(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(page) => page,
+            };
+            self.pending = None;
+            match page {
+                Ok(page) => {
+                    let mut items: Vec<Item> = page.map.into_values().collect();
+                    items.sort_by_key(|item| item.id);
+                    let last = items.last().map(|item| item.id);
+                    self.cursor = match last {
+                        Some(id) if id < u64::MAX => Some(id + 1),
+                        _ => None,
+                    };
+                    self.buffer.extend(items);
+                }
+                Err(e) => {
+                    warn!("Streaming {} stopped: {}", self.collection, e);
+                    self.cursor = None;
+                    return Poll::Ready(Some(Err(e)));
+                }
+            }
+        }
     }
 }
 
@@ -664,25 +1436,119 @@ impl CoreHandle {
 /// the same way as before — this registry just owns the senders.
 pub struct PluginRegistry {
     plugins: Vec<RegisteredPlugin>,
+    /// Capabilities core offers; `None` doesn't restrict plugins.
+    capabilities: Option<Vec<String>>,
+    /// Why `add_with_manifest` refused plugins; collected by `load_file`.
+    refused: Vec<String>,
+}
+
+/// File name prefix of shared libraries holding actor plugins.
+pub const ACTOR_PLUGIN_PREFIX: &str = "libisabelle_actor_plugin_";
+
+/// Signature of the `register` symbol exported by actor plugin libraries.
+/// Rust ABI: both types are plain Rust structs, so the plugin must be built
+/// with the same toolchain and crate versions as the host. `register` runs
+/// its plugins on threads of its own (see the module docs).
+pub type ActorRegisterFn = unsafe fn(&mut PluginRegistry, CoreHandle);
+
+/// Liveness of a registered plugin as observed by the [`HookDispatcher`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PluginHealth {
+    /// Answered its last request (or hasn't been asked anything yet).
+    Healthy,
+    /// Missed the deadline of its last request. Still receives hooks and
+    /// becomes `Healthy` again as soon as it answers in time.
+    Unresponsive,
+    /// The plugin task is gone (its receiver was dropped, e.g. because the
+    /// task panicked). Dead plugins are no longer sent any hooks.
+    Dead,
 }
 
 struct RegisteredPlugin {
-    #[allow(dead_code)]
-    name: String,
+    manifest: PluginManifest,
     sender: mpsc::Sender<PluginHookMessage>,
+    health: Mutex<PluginHealth>,
+    /// Library the plugin was registered from (path, handle). Never
+    /// released, see [`PluginRegistry::unload`].
+    library: Option<(String, Arc<Library>)>,
+}
+
+impl Drop for RegisteredPlugin {
+    fn drop(&mut self) {
+        if let Some((_, lib)) = self.library.take() {
+            std::mem::forget(lib);
+        }
+    }
+}
+
+impl RegisteredPlugin {
+    fn health(&self) -> PluginHealth {
+        *self.health.lock().unwrap()
+    }
+
+    fn set_health(&self, health: PluginHealth) {
+        let mut current = self.health.lock().unwrap();
+        if *current != health {
+            info!(
+                "Plugin {} health changed: {:?} -> {:?}",
+                self.manifest.name, *current, health
+            );
+            *current = health;
+        }
+    }
 }
 
 impl PluginRegistry {
     pub fn new() -> Self {
         Self {
             plugins: Vec::new(),
+            capabilities: None,
+            refused: Vec::new(),
         }
     }
 
+    /// Refuse plugins whose manifest requires a capability not listed
+    /// here. By default every capability is assumed available.
+    pub fn with_capabilities<S: Into<String>>(
+        mut self,
+        capabilities: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.capabilities = Some(capabilities.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Register a plugin that subscribes to every hook. Shorthand for
+    /// [`PluginRegistry::add_with_manifest`] with a bare manifest.
     pub fn add(&mut self, name: impl Into<String>, sender: mpsc::Sender<PluginHookMessage>) {
+        self.add_with_manifest(PluginManifest::new(name, ""), sender);
+    }
+
+    /// Register a plugin described by `manifest`; the dispatcher only sends
+    /// it the hooks (and collections) the manifest accepts.
+    ///
+    /// A manifest failing [`PluginManifest::validate`] against the
+    /// registry's capabilities is refused: the error is logged (and
+    /// reported as a load failure when a library registers the plugin) and
+    /// `sender` is dropped, which ends the plugin's receive loop.
+    pub fn add_with_manifest(
+        &mut self,
+        manifest: PluginManifest,
+        sender: mpsc::Sender<PluginHookMessage>,
+    ) {
+        let available: Option<Vec<&str>> = self
+            .capabilities
+            .as_ref()
+            .map(|c| c.iter().map(String::as_str).collect());
+        if let Err(e) = manifest.validate(available.as_deref()) {
+            warn!("Refusing to register {}", e);
+            self.refused.push(e);
+            return;
+        }
         self.plugins.push(RegisteredPlugin {
-            name: name.into(),
+            manifest,
             sender,
+            health: Mutex::new(PluginHealth::Healthy),
+            library: None,
         });
     }
 
@@ -700,6 +1566,229 @@ impl PluginRegistry {
         self.plugins.iter().map(|p| &p.sender)
     }
 
+    /// Manifests of the registered plugins, in registration order.
+    pub fn manifests(&self) -> impl Iterator<Item = &PluginManifest> {
+        self.plugins.iter().map(|p| &p.manifest)
+    }
+
+    /// Health of the first plugin registered under `name`, if any.
+    pub fn health(&self, name: &str) -> Option<PluginHealth> {
+        self.plugins
+            .iter()
+            .find(|p| p.manifest.name == name)
+            .map(|p| p.health())
+    }
+
+    /// (name, health) of every registered plugin, in registration order.
+    pub fn health_report(&self) -> Vec<(String, PluginHealth)> {
+        self.plugins
+            .iter()
+            .map(|p| (p.manifest.name.clone(), p.health()))
+            .collect()
+    }
+
+    /// Load actor plugins from the shared libraries in `path`.
+    ///
+    /// Files named `libisabelle_actor_plugin_*` are opened and their
+    /// `register` symbol (see the module docs) is called with this registry
+    /// and a clone of `core`. Legacy `libisabelle_plugin_*` libraries are
+    /// left to `PluginPool::load_plugins`. As there, libraries without a
+    /// matching ABI descriptor (see [`crate::abi`]) are rejected, and
+    /// outcome reporting is the same. Libraries plugins were already
+    /// loaded from are skipped, so a directory can be rescanned.
+    ///
+    /// `register` starts its plugins on threads of its own rather than on
+    /// the host's runtime (see the module docs), so this may be called
+    /// from any thread.
+    pub fn load_dir(&mut self, path: &str, core: &CoreHandle) -> PluginLoadResult {
+        let mut result = PluginLoadResult::default();
+
+        for full in find_plugin_libraries(path, ACTOR_PLUGIN_PREFIX, &mut result) {
+            if self.libraries().any(|l| l == full) {
+                info!("Plugin library {} is already loaded, skipping", full);
+                result.considered -= 1;
+                continue;
+            }
+            self.load_file(&full, false, core, &mut result);
+        }
+
+        log_load_result(path, &result);
+        result
+    }
+
+    /// Load the single actor plugin library at `path`, as [`load_dir`]
+    /// does. A library that is already loaded is refused; use
+    /// [`PluginRegistry::reload_library`] to replace it. Like
+    /// `PluginLibraries::load_library`, this loads a private copy of the
+    /// file.
+    ///
+    /// [`load_dir`]: PluginRegistry::load_dir
+    pub fn load_library(&mut self, path: &str, core: &CoreHandle) -> PluginLoadResult {
+        let mut result = PluginLoadResult {
+            considered: 1,
+            ..Default::default()
+        };
+        let full = canonical_plugin_path(path);
+        if self.libraries().any(|l| l == full) {
+            let msg = format!("Plugin library {} is already loaded", full);
+            warn!("{}", msg);
+            result.failures.push(PluginLoadFailure {
+                path: full,
+                error: msg,
+            });
+            return result;
+        }
+
+        self.load_file(&full, true, core, &mut result);
+        log_load_result(path, &result);
+        result
+    }
+
+    fn load_file(
+        &mut self,
+        full: &str,
+        private_copy: bool,
+        core: &CoreHandle,
+        result: &mut PluginLoadResult,
+    ) {
+        unsafe {
+            let Some(lib) = open_plugin_library(full, private_copy, result) else {
+                return;
+            };
+            let func = match lib.get::<ActorRegisterFn>(b"register") {
+                Ok(func) => *func,
+                Err(e) => {
+                    missing_register_symbol(full, e, result);
+                    return;
+                }
+            };
+            let before = self.plugins.len();
+            self.refused.clear();
+            func(self, core.clone());
+            for p in &mut self.plugins[before..] {
+                p.library = Some((full.to_string(), lib.clone()));
+            }
+            for error in self.refused.drain(..) {
+                result.failures.push(PluginLoadFailure {
+                    path: full.to_string(),
+                    error,
+                });
+            }
+            result.loaded += 1;
+            info!(
+                "Actor plugin library {} registered {} plugin(s)",
+                full,
+                self.plugins.len() - before
+            );
+        }
+    }
+
+    /// Paths of the libraries plugins were loaded from, in load order.
+    pub fn libraries(&self) -> impl Iterator<Item = &str> {
+        let mut seen = Vec::new();
+        self.plugins.iter().filter_map(move |p| {
+            let path = p.library.as_ref()?.0.as_str();
+            if seen.contains(&path) {
+                return None;
+            }
+            seen.push(path);
+            Some(path)
+        })
+    }
+
+    /// Remove the plugins matching `pred` and stop them: each is sent
+    /// `Shutdown` after its already queued hooks and given `drain` to drop
+    /// its receiver.
+    async fn remove_where(
+        &mut self,
+        drain: Duration,
+        pred: impl Fn(&RegisteredPlugin) -> bool,
+    ) -> usize {
+        let (removed, kept) = std::mem::take(&mut self.plugins)
+            .into_iter()
+            .partition::<Vec<_>, _>(|p| pred(p));
+        self.plugins = kept;
+
+        let count = removed.len();
+        for p in removed {
+            let stopped = timeout(drain, async {
+                let _ = p.sender.send(PluginHookMessage::Shutdown).await;
+                p.sender.closed().await;
+            })
+            .await
+            .is_ok();
+
+            if stopped {
+                info!("Plugin {} stopped", p.manifest.name);
+            } else {
+                warn!("Plugin {} did not stop within {:?}", p.manifest.name, drain);
+            }
+        }
+        count
+    }
+
+    /// Stop and remove every plugin registered under `name`. Returns the
+    /// number of plugins removed.
+    ///
+    /// The libraries of removed plugins are never unmapped. A dropped
+    /// receiver doesn't mean the plugin's thread has left library code (it
+    /// still runs its epilogue and teardown there), and the host can't
+    /// tell when it has, so each unload or reload leaks one mapping of the
+    /// library; reloads load a private copy. Dropping the registry keeps
+    /// its libraries mapped as well.
+    pub async fn unload(&mut self, name: &str, drain: Duration) -> usize {
+        self.remove_where(drain, |p| p.manifest.name == name).await
+    }
+
+    /// Stop and remove every plugin registered by the library at `path`.
+    /// Hooks already queued to the plugins are answered first; `drain`
+    /// bounds the wait for each plugin. The library itself stays mapped
+    /// (see [`PluginRegistry::unload`]).
+    ///
+    /// Takes `&mut self`, so no [`HookDispatcher`] borrowing this registry
+    /// can be in flight.
+    pub async fn unload_library(&mut self, path: &str, drain: Duration) -> usize {
+        let full = canonical_plugin_path(path);
+        let removed = self
+            .remove_where(drain, |p| {
+                p.library.as_ref().is_some_and(|(l, _)| *l == full)
+            })
+            .await;
+        info!("Unloaded {} plugin(s) of {}", removed, full);
+        removed
+    }
+
+    /// Unload the library at `path` (if loaded) and load the file found
+    /// there now. Replace plugin files by renaming a new file over the old
+    /// one rather than rewriting it in place.
+    pub async fn reload_library(
+        &mut self,
+        path: &str,
+        core: &CoreHandle,
+        drain: Duration,
+    ) -> PluginLoadResult {
+        self.unload_library(path, drain).await;
+        self.load_library(path, core)
+    }
+
+    /// Apply a change reported by a
+    /// [`PluginDirWatcher`](crate::plugin_pool::PluginDirWatcher).
+    pub async fn apply_change(
+        &mut self,
+        change: &PluginFileChange,
+        core: &CoreHandle,
+        drain: Duration,
+    ) -> PluginLoadResult {
+        match change {
+            PluginFileChange::Added(path) => self.load_library(path, core),
+            PluginFileChange::Modified(path) => self.reload_library(path, core, drain).await,
+            PluginFileChange::Removed(path) => {
+                self.unload_library(path, drain).await;
+                PluginLoadResult::default()
+            }
+        }
+    }
+
     /// Broadcast `Shutdown` to all plugin tasks. Best-effort; doesn't wait
     /// for them to terminate (use the join handles from `tokio::spawn` for
     /// that on the caller side).
@@ -716,6 +1805,708 @@ impl Default for PluginRegistry {
     }
 }
 
+// ---------------------------------------------------------------------------
+// HookDispatcher: fan-out with the documented aggregation semantics
+// ---------------------------------------------------------------------------
+
+/// Deadlines the [`HookDispatcher`] applies to a single plugin's reply
+/// (including the time spent waiting for room in its queue).
+#[derive(Debug, Clone)]
+pub struct HookTimeouts {
+    pub pre_edit: Duration,
+    pub auth: Duration,
+    pub list_filter: Duration,
+    pub db_filter: Duration,
+    pub collection_read: Duration,
+    pub route: Duration,
+    pub ping: Duration,
+    /// Fire-and-forget hooks: bounds only the wait for queue space.
+    pub notify: Duration,
+}
+
+impl Default for HookTimeouts {
+    fn default() -> Self {
+        Self {
+            pre_edit: Duration::from_secs(5),
+            auth: Duration::from_secs(5),
+            list_filter: Duration::from_secs(5),
+            db_filter: Duration::from_secs(5),
+            collection_read: Duration::from_secs(5),
+            route: Duration::from_secs(30),
+            ping: Duration::from_secs(5),
+            notify: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Database filter used in place of the clause of a plugin that failed to
+/// answer `ItemListDbFilter`. Matches no document.
+pub const DENY_ALL_DB_FILTER: &str = r#"{"_id": {"$in": []}}"#;
+
+/// Fans out [`PluginHookMessage`]s to every plugin in a [`PluginRegistry`]
+/// and aggregates the replies, so core and test harnesses don't have to
+/// re-implement the semantics described on each hook variant:
+///
+/// * `ItemPreEdit` — plugins are asked in registration order; a rejection
+///   short-circuits, and a `modified_item` replaces the item passed to the
+///   next plugin.
+/// * `ItemAuth` — any plugin denying short-circuits with `false`.
+/// * `ItemListFilter` — the page returned by one plugin is fed to the next.
+/// * `ItemListDbFilter` — non-empty clauses are AND-combined.
+/// * `CollectionRead` — the item is threaded through all plugins; it should
+///   be saved if any plugin asked for it.
+/// * Routes — the first reply other than `WebResponse::NotImplemented` wins.
+///   Requests matching a route declared in a manifest only go to the
+///   plugins declaring it, most specific pattern first, after the route's
+///   role is checked with [`HookDispatcher::with_role_check`]; see
+///   [`crate::route`].
+///
+/// Plugins whose [`PluginManifest`] doesn't accept a hook (or, for item
+/// hooks, the collection) are not asked at all.
+///
+/// A plugin that is dead, drops the reply sender or misses its
+/// [`HookTimeouts`] deadline fails closed:
+///
+/// * `ItemPreEdit` is rejected and `ItemAuth` denied;
+/// * `ItemListFilter` yields an empty page and `ItemListDbFilter` yields
+///   [`DENY_ALL_DB_FILTER`] (as it does for a clause that isn't valid
+///   JSON);
+/// * `CollectionRead` leaves the item untouched;
+/// * routes answer `WebResponse::ServiceUnavailable` if no other plugin
+///   handled the request.
+///
+/// Failures update the plugin's [`PluginHealth`] in the registry.
+pub struct HookDispatcher<'a> {
+    registry: &'a PluginRegistry,
+    timeouts: HookTimeouts,
+    role_check: Option<&'a RoleCheck<'a>>,
+}
+
+/// Whether a user has a role, as answered by core for `AuthCheckRole`.
+pub type RoleCheck<'a> = dyn Fn(&Item, &str) -> bool + Sync + 'a;
+
+/// Why a plugin didn't produce a reply.
+enum HookFailure {
+    Dead,
+    ReplyDropped,
+    TimedOut,
+}
+
+impl<'a> HookDispatcher<'a> {
+    pub fn new(registry: &'a PluginRegistry) -> Self {
+        Self {
+            registry,
+            timeouts: HookTimeouts::default(),
+            role_check: None,
+        }
+    }
+
+    pub fn with_timeouts(mut self, timeouts: HookTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Check used for routes declaring a role. Without one, such routes
+    /// are refused.
+    pub fn with_role_check(mut self, check: &'a RoleCheck<'a>) -> Self {
+        self.role_check = Some(check);
+        self
+    }
+
+    /// Send a message built around a fresh oneshot to one plugin and await
+    /// the reply within `deadline`, keeping the plugin's health up to date.
+    async fn ask<T>(
+        plugin: &RegisteredPlugin,
+        hook: &str,
+        deadline: Duration,
+        build: impl FnOnce(oneshot::Sender<T>) -> PluginHookMessage,
+    ) -> Result<T, HookFailure> {
+        if plugin.health() == PluginHealth::Dead {
+            return Err(HookFailure::Dead);
+        }
+        let (rtx, rrx) = oneshot::channel();
+        let exchange = async {
+            plugin
+                .sender
+                .send(build(rtx))
+                .await
+                .map_err(|_| HookFailure::Dead)?;
+            rrx.await.map_err(|_| HookFailure::ReplyDropped)
+        };
+        let res = match timeout(deadline, exchange).await {
+            Ok(res) => res,
+            Err(_) => Err(HookFailure::TimedOut),
+        };
+        match &res {
+            Ok(_) => plugin.set_health(PluginHealth::Healthy),
+            Err(HookFailure::TimedOut) => {
+                warn!(
+                    "Plugin {} did not answer {} within {:?}",
+                    plugin.manifest.name, hook, deadline
+                );
+                plugin.set_health(PluginHealth::Unresponsive);
+            }
+            Err(_) if plugin.sender.is_closed() => {
+                warn!(
+                    "Plugin {} is not running, {} failed",
+                    plugin.manifest.name, hook
+                );
+                plugin.set_health(PluginHealth::Dead);
+            }
+            Err(_) => warn!("Plugin {} dropped the {} reply", plugin.manifest.name, hook),
+        }
+        res
+    }
+
+    /// Plugins whose manifest accepts `kind` (for `collection`, if given),
+    /// in registration order.
+    fn targets(
+        &self,
+        kind: HookKind,
+        collection: Option<&'a str>,
+    ) -> impl Iterator<Item = &'a RegisteredPlugin> {
+        self.registry
+            .plugins
+            .iter()
+            .filter(move |p| p.manifest.accepts(kind, collection))
+    }
+
+    /// Send a fire-and-forget message to every live plugin subscribed to it.
+    async fn notify_all(
+        &self,
+        kind: HookKind,
+        collection: Option<&str>,
+        hook: &str,
+        build: impl Fn() -> PluginHookMessage,
+    ) {
+        for p in self.targets(kind, collection) {
+            if p.health() == PluginHealth::Dead {
+                continue;
+            }
+            match timeout(self.timeouts.notify, p.sender.send(build())).await {
+                Ok(Ok(())) => {}
+                Ok(Err(_)) => {
+                    warn!(
+                        "Plugin {} is not running, skipping {}",
+                        p.manifest.name, hook
+                    );
+                    p.set_health(PluginHealth::Dead);
+                }
+                Err(_) => {
+                    warn!(
+                        "Plugin {} queue is full, dropping {}",
+                        p.manifest.name, hook
+                    );
+                    p.set_health(PluginHealth::Unresponsive);
+                }
+            }
+        }
+    }
+
+    /// Run the pre-edit chain. On success `item` holds the item as modified
+    /// by the plugins; on rejection the first rejecting plugin's result is
+    /// returned and `item` holds the state it was rejected in.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn item_pre_edit(
+        &self,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        old_item: &Option<Item>,
+        item: &mut Item,
+        action: DataObjectAction,
+        merge: bool,
+    ) -> ProcessResult {
+        for p in self.targets(HookKind::ItemPreEdit, Some(collection)) {
+            let reply = Self::ask(p, "ItemPreEdit", self.timeouts.pre_edit, |reply| {
+                PluginHookMessage::ItemPreEdit {
+                    hndl: hndl.into(),
+                    user: user.clone(),
+                    collection: collection.into(),
+                    old_item: old_item.clone(),
+                    item: item.clone(),
+                    action: action.clone(),
+                    merge,
+                    reply,
+                }
+            })
+            .await
+            .unwrap_or_else(|_| {
+                PreEditReply::rejected(format!("plugin {} is unavailable", p.manifest.name))
+            });
+            if let Some(modified) = reply.modified_item {
+                *item = modified;
+            }
+            if !reply.result.succeeded {
+                return reply.result;
+            }
+        }
+        PreEditReply::ok_unchanged().result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn item_post_edit(
+        &self,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        old_item: &Option<Item>,
+        new_item: &Option<Item>,
+        id: u64,
+        action: DataObjectAction,
+        merge: bool,
+    ) {
+        self.notify_all(
+            HookKind::ItemPostEdit,
+            Some(collection),
+            "ItemPostEdit",
+            || PluginHookMessage::ItemPostEdit {
+                hndl: hndl.into(),
+                user: user.clone(),
+                collection: collection.into(),
+                old_item: old_item.clone(),
+                new_item: new_item.clone(),
+                id,
+                action: action.clone(),
+                merge,
+            },
+        )
+        .await;
+    }
+
+    /// `true` unless some plugin denies access.
+    pub async fn item_auth(
+        &self,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        id: u64,
+        new_item: &Option<Item>,
+        del: bool,
+    ) -> bool {
+        for p in self.targets(HookKind::ItemAuth, Some(collection)) {
+            let allowed = Self::ask(p, "ItemAuth", self.timeouts.auth, |reply| {
+                PluginHookMessage::ItemAuth {
+                    hndl: hndl.into(),
+                    user: user.clone(),
+                    collection: collection.into(),
+                    id,
+                    new_item: new_item.clone(),
+                    del,
+                    reply,
+                }
+            })
+            .await
+            .unwrap_or(false);
+            if !allowed {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Pass the page through every plugin in turn, updating `items` in place.
+    pub async fn item_list_filter(
+        &self,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        context: &str,
+        items: &mut HashMap<u64, Item>,
+    ) {
+        for p in self.targets(HookKind::ItemListFilter, Some(collection)) {
+            let reply = Self::ask(p, "ItemListFilter", self.timeouts.list_filter, |reply| {
+                PluginHookMessage::ItemListFilter {
+                    hndl: hndl.into(),
+                    user: user.clone(),
+                    collection: collection.into(),
+                    context: context.into(),
+                    items: items.clone(),
+                    reply,
+                }
+            })
+            .await
+            .unwrap_or_default();
+            *items = reply.items;
+        }
+    }
+
+    /// Collect filter clauses from all plugins. Returns an empty string when
+    /// no plugin filters, the clause itself when exactly one does, and a
+    /// `{"$and": [...]}` object otherwise. A clause that isn't valid JSON
+    /// is logged and replaced by [`DENY_ALL_DB_FILTER`], like a missed
+    /// deadline.
+    pub async fn item_list_db_filter(
+        &self,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        context: &str,
+        filter_type: &str,
+    ) -> String {
+        let mut clauses = Vec::new();
+        for p in self.targets(HookKind::ItemListDbFilter, Some(collection)) {
+            let clause = Self::ask(p, "ItemListDbFilter", self.timeouts.db_filter, |reply| {
+                PluginHookMessage::ItemListDbFilter {
+                    hndl: hndl.into(),
+                    user: user.clone(),
+                    collection: collection.into(),
+                    context: context.into(),
+                    filter_type: filter_type.into(),
+                    reply,
+                }
+            })
+            .await
+            .unwrap_or_else(|_| DENY_ALL_DB_FILTER.to_string());
+            if clause.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<serde_json::Value>(&clause) {
+                Ok(v) => clauses.push(v),
+                Err(e) => {
+                    warn!(
+                        "Plugin {} returned an invalid db filter: {}",
+                        p.manifest.name, e
+                    );
+                    clauses.push(
+                        serde_json::from_str(DENY_ALL_DB_FILTER)
+                            .expect("DENY_ALL_DB_FILTER is valid JSON"),
+                    );
+                }
+            }
+        }
+        match clauses.len() {
+            0 => String::new(),
+            1 => clauses.remove(0).to_string(),
+            _ => serde_json::json!({ "$and": clauses }).to_string(),
+        }
+    }
+
+    /// Thread `item` through all plugins; returns whether it should be saved.
+    pub async fn collection_read(&self, hndl: &str, collection: &str, item: &mut Item) -> bool {
+        let mut should_save = false;
+        for p in self.targets(HookKind::CollectionRead, Some(collection)) {
+            let reply = Self::ask(
+                p,
+                "CollectionRead",
+                self.timeouts.collection_read,
+                |reply| PluginHookMessage::CollectionRead {
+                    hndl: hndl.into(),
+                    collection: collection.into(),
+                    item: item.clone(),
+                    reply,
+                },
+            )
+            .await;
+            let Ok(reply) = reply else { continue };
+            if let Some(modified) = reply.item {
+                *item = modified;
+            }
+            should_save |= reply.should_save;
+        }
+        should_save
+    }
+
+    pub async fn otp(&self, hndl: &str, item: &Item) {
+        self.notify_all(HookKind::Otp, None, "Otp", || PluginHookMessage::Otp {
+            hndl: hndl.into(),
+            item: item.clone(),
+        })
+        .await;
+    }
+
+    pub async fn periodic_job(&self, timing: &str) {
+        self.notify_all(HookKind::PeriodicJob, None, "PeriodicJob", || {
+            PluginHookMessage::PeriodicJob {
+                timing: timing.into(),
+            }
+        })
+        .await;
+    }
+
+    /// Ask plugins in turn until one answers with something other than
+    /// `WebResponse::NotImplemented`. If `ctx` matches routes declared for
+    /// `protected` requests by plugins subscribed to `kind`, only those
+    /// plugins are asked, each with its route's path parameters; otherwise
+    /// plugins declaring no routes are.
+    async fn route_response(
+        &self,
+        kind: HookKind,
+        hook: &str,
+        ctx: &RequestContext,
+        protected: bool,
+        build: impl Fn(RequestContext, oneshot::Sender<WebResponse>) -> PluginHookMessage,
+    ) -> WebResponse {
+        self.route_response_from(kind, hook, ctx, protected, |_, ctx, reply| {
+            build(ctx, reply)
+        })
+        .await
+    }
+
+    /// [`HookDispatcher::route_response`], telling `build` which plugin
+    /// the message is for.
+    async fn route_response_from(
+        &self,
+        kind: HookKind,
+        hook: &str,
+        ctx: &RequestContext,
+        protected: bool,
+        build: impl Fn(
+            &RegisteredPlugin,
+            RequestContext,
+            oneshot::Sender<WebResponse>,
+        ) -> PluginHookMessage,
+    ) -> WebResponse {
+        let routes = self.targets(kind, None).flat_map(|p| {
+            p.manifest
+                .routes
+                .iter()
+                .filter(|r| r.protected == protected)
+                .map(move |r| (r, p))
+        });
+        let claimed = match match_routes(routes, &ctx.method, &ctx.path) {
+            RouteMatch::Unrouted => None,
+            RouteMatch::MethodNotAllowed(resp) => return resp,
+            RouteMatch::Matched(claimed) => Some(claimed),
+        };
+
+        let mut any_failed = false;
+        let Some(claimed) = claimed else {
+            let unrouted = self
+                .targets(kind, None)
+                .filter(|p| p.manifest.routes.is_empty());
+            for p in unrouted {
+                match Self::ask(p, hook, self.timeouts.route, |reply| {
+                    build(p, ctx.clone(), reply)
+                })
+                .await
+                {
+                    Ok(WebResponse::NotImplemented) => {}
+                    Ok(resp) => return resp,
+                    Err(_) => any_failed = true,
+                }
+            }
+            return if any_failed {
+                WebResponse::ServiceUnavailable
+            } else {
+                WebResponse::NotImplemented
+            };
+        };
+
+        for (route, p, params) in claimed {
+            // Routes come most specific first, so a refusal here must not
+            // fall through to a catch-all behind it.
+            if let Some(role) = &route.role {
+                if !self.has_role(&ctx.user, role) {
+                    return match ctx.user {
+                        Some(_) => WebResponse::Forbidden,
+                        None => WebResponse::Unauthorized,
+                    };
+                }
+            }
+            let mut ctx = ctx.clone();
+            ctx.path_params = params;
+            match Self::ask(p, hook, self.timeouts.route, |reply| build(p, ctx, reply)).await {
+                Ok(WebResponse::NotImplemented) => {}
+                Ok(resp) => return resp,
+                Err(_) => any_failed = true,
+            }
+        }
+        if any_failed {
+            WebResponse::ServiceUnavailable
+        } else {
+            WebResponse::NotFound
+        }
+    }
+
+    fn has_role(&self, user: &Option<Item>, role: &str) -> bool {
+        match (user, self.role_check) {
+            (Some(user), Some(check)) => check(user, role),
+            _ => false,
+        }
+    }
+
+    pub async fn route_url(&self, ctx: &RequestContext) -> WebResponse {
+        self.route_response(HookKind::RouteUrl, "RouteUrl", ctx, true, |ctx, reply| {
+            PluginHookMessage::RouteUrl { ctx, reply }
+        })
+        .await
+    }
+
+    pub async fn route_url_post(
+        &self,
+        ctx: &RequestContext,
+        item: &Item,
+        parts: impl Into<Arc<[UploadPart]>>,
+    ) -> WebResponse {
+        let parts = parts.into();
+        self.route_response(
+            HookKind::RouteUrlPost,
+            "RouteUrlPost",
+            ctx,
+            true,
+            |ctx, reply| PluginHookMessage::RouteUrlPost {
+                ctx,
+                item: item.clone(),
+                parts: parts.clone(),
+                reply,
+            },
+        )
+        .await
+    }
+
+    pub async fn route_unprotected_url(&self, ctx: &RequestContext) -> WebResponse {
+        self.route_response(
+            HookKind::RouteUnprotectedUrl,
+            "RouteUnprotectedUrl",
+            ctx,
+            false,
+            |ctx, reply| PluginHookMessage::RouteUnprotectedUrl { ctx, reply },
+        )
+        .await
+    }
+
+    pub async fn route_unprotected_url_post(
+        &self,
+        ctx: &RequestContext,
+        item: &Item,
+        parts: impl Into<Arc<[UploadPart]>>,
+    ) -> WebResponse {
+        let parts = parts.into();
+        self.route_response(
+            HookKind::RouteUnprotectedUrlPost,
+            "RouteUnprotectedUrlPost",
+            ctx,
+            false,
+            |ctx, reply| PluginHookMessage::RouteUnprotectedUrlPost {
+                ctx,
+                item: item.clone(),
+                parts: parts.clone(),
+                reply,
+            },
+        )
+        .await
+    }
+
+    pub async fn route_rest(&self, ctx: &RequestContext, payload: &str) -> WebResponse {
+        self.route_response(HookKind::RouteRest, "RouteRest", ctx, true, |ctx, reply| {
+            PluginHookMessage::RouteRest {
+                ctx,
+                payload: payload.into(),
+                reply,
+            }
+        })
+        .await
+    }
+
+    /// Offer a WebSocket on `ctx` to the plugins serving its route, as
+    /// for `RouteUrl`, or `RouteUnprotectedUrl` unless `protected`. `Err`
+    /// holds the response to send instead of upgrading.
+    pub async fn websocket_connect(
+        &self,
+        ctx: &RequestContext,
+        protected: bool,
+    ) -> Result<WebSocketSession, WebResponse> {
+        let (closed, (to_plugin, from_plugin)) = self
+            .connect(
+                HookKind::WebSocketConnect,
+                "WebSocketConnect",
+                ctx,
+                protected,
+                |id, ctx, reply| {
+                    let (to_plugin, incoming) = mpsc::channel(CONNECTION_CAPACITY);
+                    let (outgoing, from_plugin) = mpsc::channel(CONNECTION_CAPACITY);
+                    let conn = WebSocketConn {
+                        id,
+                        outgoing,
+                        incoming,
+                    };
+                    (
+                        PluginHookMessage::WebSocketConnect { ctx, conn, reply },
+                        (to_plugin, from_plugin),
+                    )
+                },
+            )
+            .await?;
+        Ok(WebSocketSession {
+            to_plugin,
+            from_plugin,
+            closed,
+        })
+    }
+
+    /// Offer a server-sent event stream on `ctx`, like
+    /// [`HookDispatcher::websocket_connect`].
+    pub async fn sse_connect(
+        &self,
+        ctx: &RequestContext,
+        protected: bool,
+    ) -> Result<SseSession, WebResponse> {
+        let (closed, events) = self
+            .connect(
+                HookKind::SseConnect,
+                "SseConnect",
+                ctx,
+                protected,
+                |id, ctx, reply| {
+                    let (tx, events) = mpsc::channel(CONNECTION_CAPACITY);
+                    let conn = SseConn { id, events: tx };
+                    (PluginHookMessage::SseConnect { ctx, conn, reply }, events)
+                },
+            )
+            .await?;
+        Ok(SseSession { events, closed })
+    }
+
+    /// Route a connection request. `open` builds the message for one
+    /// plugin along with core's end of the connection; the end built for
+    /// the plugin that accepted is returned.
+    async fn connect<T>(
+        &self,
+        kind: HookKind,
+        hook: &str,
+        ctx: &RequestContext,
+        protected: bool,
+        open: impl Fn(
+            ConnectionId,
+            RequestContext,
+            oneshot::Sender<WebResponse>,
+        ) -> (PluginHookMessage, T),
+    ) -> Result<(CloseNotice, T), WebResponse> {
+        let id = next_connection_id();
+        let offered = Mutex::new(None);
+        let resp = self
+            .route_response_from(kind, hook, ctx, protected, |p, ctx, reply| {
+                let (msg, end) = open(id, ctx, reply);
+                *offered.lock().unwrap() = Some((p.manifest.name.clone(), p.sender.clone(), end));
+                msg
+            })
+            .await;
+        match (resp, offered.into_inner().unwrap()) {
+            (WebResponse::Ok, Some((plugin, sender, end))) => {
+                Ok((CloseNotice { id, plugin, sender }, end))
+            }
+            (resp, _) => Err(resp),
+        }
+    }
+
+    /// Ping every plugin; returns how many answered.
+    pub async fn ping(&self) -> usize {
+        let mut alive = 0;
+        for p in &self.registry.plugins {
+            if Self::ask(p, "Ping", self.timeouts.ping, |reply| {
+                PluginHookMessage::Ping { reply }
+            })
+            .await
+            .is_ok()
+            {
+                alive += 1;
+            }
+        }
+        alive
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
@@ -21,12 +21,30 @@
  * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
  * DEALINGS IN THE SOFTWARE.
  */
+use crate::manifest::PluginManifest;
+use crate::query::{Filter, Sort};
+pub use crate::request::RequestContext;
+pub use crate::upload::UploadPart;
+use actix_files::NamedFile;
+use actix_web::body::{BoxBody, SizedStream};
+use actix_web::cookie::Cookie;
+use actix_web::http::header::{self, ContentDisposition, HeaderName, HeaderValue};
+use actix_web::http::StatusCode;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use futures_core::Stream;
 use isabelle_dm::data_model::data_object_action::DataObjectAction;
 use isabelle_dm::data_model::item::Item;
 use isabelle_dm::data_model::list_result::ListResult;
 use isabelle_dm::data_model::process_result::ProcessResult;
+use log::warn;
 use std::any::Any;
 use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+use tokio::task::{spawn_blocking, JoinHandle};
 
 #[repr(C)]
 /// Canonical web responses
@@ -40,8 +58,333 @@ pub enum WebResponse {
     BadRequest,
     Forbidden,
     NotImplemented,
+    ServiceUnavailable,
     Login(String),
     Logout,
+    /// 200 with a JSON body.
+    Json(serde_json::Value),
+    /// 302 to the given URL.
+    Redirect(String),
+    Conflict,
+    TooManyRequests,
+    /// Anything else: see [`CustomResponse`].
+    Custom(CustomResponse),
+    /// 200 with a body produced while it is sent: see [`StreamResponse`].
+    Stream(StreamResponse),
+}
+
+/// A response with a chosen status, headers, cookies and body.
+///
+/// ```ignore
+/// CustomResponse::new(201)
+///     .content_type("text/csv")
+///     .header("Cache-Control", "no-store")
+///     .cookie(Cookie::new("seen", "1"))
+///     .body(csv)
+///     .into()
+/// ```
+#[derive(Debug, Clone)]
+pub struct CustomResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub cookies: Vec<Cookie<'static>>,
+    pub body: Vec<u8>,
+}
+
+impl CustomResponse {
+    pub fn new(status: u16) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            cookies: Vec::new(),
+            body: Vec::new(),
+        }
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn content_type(self, content_type: &str) -> Self {
+        self.header("Content-Type", content_type)
+    }
+
+    pub fn cookie(mut self, cookie: Cookie<'static>) -> Self {
+        self.cookies.push(cookie);
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = body.into();
+        self
+    }
+}
+
+impl From<CustomResponse> for WebResponse {
+    fn from(resp: CustomResponse) -> Self {
+        WebResponse::Custom(resp)
+    }
+}
+
+/// One piece of a [`StreamResponse`] body. An `Err` aborts the response,
+/// so the client sees a failed transfer instead of a truncated file.
+pub type Chunk = Result<Vec<u8>, String>;
+
+/// A body sent as it is produced, for files too large to hold in memory.
+///
+/// ```ignore
+/// let (tx, stream) = StreamResponse::channel("application/pdf", 4);
+/// tokio::spawn(async move {
+///     while let Some(chunk) = archive.next_chunk().await {
+///         if tx.send(chunk).await.is_err() {
+///             break; // client went away
+///         }
+///     }
+/// });
+/// let _ = reply.send(WebResponse::Stream(stream.length(size).attachment("doc.pdf")));
+/// ```
+///
+/// Legacy plugins can fill the channel from a thread with `blocking_send`.
+pub struct StreamResponse {
+    pub content_type: String,
+    /// Sent as `Content-Length`; otherwise the body is chunked.
+    pub length: Option<u64>,
+    /// Offer the body as a download with this file name.
+    pub filename: Option<String>,
+    pub body: Pin<Box<dyn Stream<Item = Chunk> + Send>>,
+}
+
+impl StreamResponse {
+    pub fn from_stream(
+        content_type: &str,
+        body: impl Stream<Item = Chunk> + Send + 'static,
+    ) -> Self {
+        Self {
+            content_type: content_type.to_string(),
+            length: None,
+            filename: None,
+            body: Box::pin(body),
+        }
+    }
+
+    /// A response whose chunks are sent on the returned channel, which
+    /// buffers up to `capacity` of them. The body ends when the sender is
+    /// dropped.
+    pub fn channel(content_type: &str, capacity: usize) -> (mpsc::Sender<Chunk>, Self) {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        (tx, Self::from_stream(content_type, ChunkReceiver(rx)))
+    }
+
+    pub fn length(mut self, length: u64) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    pub fn attachment(mut self, filename: &str) -> Self {
+        self.filename = Some(filename.to_string());
+        self
+    }
+}
+
+struct ChunkReceiver(mpsc::Receiver<Chunk>);
+
+impl Stream for ChunkReceiver {
+    type Item = Chunk;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Chunk>> {
+        self.0.poll_recv(cx)
+    }
+}
+
+/// [`StreamResponse::body`] as actix wants it.
+struct StreamBody(Pin<Box<dyn Stream<Item = Chunk> + Send>>);
+
+impl Stream for StreamBody {
+    type Item = Result<Bytes, String>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.0
+            .as_mut()
+            .poll_next(cx)
+            .map(|chunk| chunk.map(|chunk| chunk.map(Bytes::from)))
+    }
+}
+
+/// What core sends to the client. `NotImplemented` means no plugin
+/// handled the request and becomes 404. `Login` and `Logout` become a
+/// bare 200: core sets up or clears the session before converting.
+/// `OkFilePath` is streamed as a plain download (404 if it can't be
+/// opened); the [`Responder`] impl adds range and conditional request
+/// support. An invalid `Custom` status or header becomes 500.
+impl From<WebResponse> for HttpResponse {
+    fn from(resp: WebResponse) -> Self {
+        match resp {
+            WebResponse::Ok | WebResponse::Login(_) | WebResponse::Logout => {
+                HttpResponse::Ok().finish()
+            }
+            WebResponse::OkData(data) => HttpResponse::Ok().body(data),
+            WebResponse::OkFile(name, data) => file_response(&name, data),
+            WebResponse::OkFilePath(name, path) => match file_stream(&path) {
+                Ok(stream) => stream_response(stream.attachment(&name)),
+                Err(e) => {
+                    warn!("Failed to open {}: {}", path, e);
+                    HttpResponse::NotFound().finish()
+                }
+            },
+            WebResponse::NotFound | WebResponse::NotImplemented => {
+                HttpResponse::NotFound().finish()
+            }
+            WebResponse::Unauthorized => HttpResponse::Unauthorized().finish(),
+            WebResponse::BadRequest => HttpResponse::BadRequest().finish(),
+            WebResponse::Forbidden => HttpResponse::Forbidden().finish(),
+            WebResponse::ServiceUnavailable => HttpResponse::ServiceUnavailable().finish(),
+            WebResponse::Json(value) => HttpResponse::Ok().json(value),
+            WebResponse::Redirect(url) => HttpResponse::Found()
+                .insert_header((header::LOCATION, url))
+                .finish(),
+            WebResponse::Conflict => HttpResponse::Conflict().finish(),
+            WebResponse::TooManyRequests => HttpResponse::TooManyRequests().finish(),
+            WebResponse::Custom(custom) => custom_response(custom),
+            WebResponse::Stream(stream) => stream_response(stream),
+        }
+    }
+}
+
+/// Like the `From` conversion, except that `OkFilePath` is streamed from
+/// disk with `Range`, `ETag`/`Last-Modified` and conditional request
+/// support. Route handlers in core can return a [`WebResponse`] directly.
+impl Responder for WebResponse {
+    type Body = BoxBody;
+
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        match self {
+            WebResponse::OkFilePath(name, path) => match NamedFile::open(&path) {
+                Ok(file) => file
+                    .set_content_disposition(ContentDisposition::attachment(name))
+                    .into_response(req),
+                Err(e) => {
+                    warn!("Failed to open {}: {}", path, e);
+                    HttpResponse::NotFound().finish()
+                }
+            },
+            other => other.into(),
+        }
+    }
+}
+
+fn file_response(name: &str, data: Vec<u8>) -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("application/octet-stream")
+        .insert_header(ContentDisposition::attachment(name))
+        .body(data)
+}
+
+/// Bytes read per chunk by [`file_stream`].
+const FILE_CHUNK_SIZE: usize = 64 << 10;
+
+/// The file at `path` as a [`StreamResponse`]. Chunks are read on tokio's
+/// blocking pool as the body is polled, so converting doesn't need a
+/// request or a runtime.
+fn file_stream(path: &str) -> std::io::Result<StreamResponse> {
+    let file = std::fs::File::open(path)?;
+    let length = file.metadata()?.len();
+    let chunks = FileChunks {
+        path: path.to_string(),
+        state: FileState::Idle(file),
+    };
+    Ok(StreamResponse::from_stream("application/octet-stream", chunks).length(length))
+}
+
+struct FileChunks {
+    path: String,
+    state: FileState,
+}
+
+enum FileState {
+    Idle(std::fs::File),
+    Reading(JoinHandle<(std::fs::File, std::io::Result<Vec<u8>>)>),
+    Done,
+}
+
+impl Stream for FileChunks {
+    type Item = Chunk;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Chunk>> {
+        loop {
+            match std::mem::replace(&mut self.state, FileState::Done) {
+                FileState::Done => return Poll::Ready(None),
+                FileState::Idle(mut file) => {
+                    self.state = FileState::Reading(spawn_blocking(move || {
+                        let mut buf = vec![0; FILE_CHUNK_SIZE];
+                        let read = std::io::Read::read(&mut file, &mut buf).map(|n| {
+                            buf.truncate(n);
+                            buf
+                        });
+                        (file, read)
+                    }));
+                }
+                FileState::Reading(mut reading) => {
+                    let read = match Pin::new(&mut reading).poll(cx) {
+                        Poll::Pending => {
+                            self.state = FileState::Reading(reading);
+                            return Poll::Pending;
+                        }
+                        Poll::Ready(Ok((file, Ok(buf)))) if !buf.is_empty() => {
+                            self.state = FileState::Idle(file);
+                            Ok(buf)
+                        }
+                        Poll::Ready(Ok((_, Ok(_)))) => return Poll::Ready(None),
+                        Poll::Ready(Ok((_, Err(e)))) => Err(e.to_string()),
+                        Poll::Ready(Err(e)) => Err(e.to_string()),
+                    };
+                    let path = &self.path;
+                    return Poll::Ready(Some(
+                        read.map_err(|e| format!("failed to read {}: {}", path, e)),
+                    ));
+                }
+            }
+        }
+    }
+}
+
+fn stream_response(stream: StreamResponse) -> HttpResponse {
+    let mut builder = HttpResponse::Ok();
+    builder.content_type(stream.content_type);
+    if let Some(name) = stream.filename {
+        builder.insert_header(ContentDisposition::attachment(name));
+    }
+    let body = StreamBody(stream.body);
+    match stream.length {
+        Some(length) => builder.body(SizedStream::new(length, body)),
+        None => builder.streaming(body),
+    }
+}
+
+fn custom_response(custom: CustomResponse) -> HttpResponse {
+    let Ok(status) = StatusCode::from_u16(custom.status) else {
+        warn!("Invalid response status {}", custom.status);
+        return HttpResponse::InternalServerError().finish();
+    };
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in &custom.headers {
+        match (
+            HeaderName::try_from(name.as_str()),
+            HeaderValue::try_from(value.as_str()),
+        ) {
+            (Ok(name), Ok(value)) => {
+                builder.append_header((name, value));
+            }
+            _ => {
+                warn!("Invalid response header {}: {}", name, value);
+                return HttpResponse::InternalServerError().finish();
+            }
+        }
+    }
+    for cookie in custom.cookies {
+        builder.cookie(cookie);
+    }
+    builder.body(custom.body)
 }
 
 pub trait Plugin: Send {
@@ -57,14 +400,19 @@ pub trait Plugin: Send {
         action: DataObjectAction,
         merge: bool,
     ) -> ProcessResult;
+    /// `new_itm` is the item as persisted (`None` after a delete).
+    #[allow(clippy::too_many_arguments)]
     fn item_post_edit_hook(
         &mut self,
         api: &Box<dyn PluginApi>,
         hndl: &str,
+        user: &Option<Item>,
         collection: &str,
         old_itm: Option<Item>,
+        new_itm: Option<Item>,
         id: u64,
         action: DataObjectAction,
+        merge: bool,
     );
     fn item_auth_hook(
         &mut self,
@@ -97,46 +445,34 @@ pub trait Plugin: Send {
         return "".to_string();
     }
 
-    fn route_url_hook(
-        &mut self,
-        api: &Box<dyn PluginApi>,
-        hndl: &str,
-        user: &Option<Item>,
-        query: &str,
-    ) -> WebResponse;
+    fn route_url_hook(&mut self, api: &Box<dyn PluginApi>, ctx: &RequestContext) -> WebResponse;
     fn route_url_post_hook(
         &mut self,
         _api: &Box<dyn PluginApi>,
-        _hndl: &str,
-        _user: &Option<Item>,
-        _query: &str,
+        _ctx: &RequestContext,
         _itm: &Item,
+        _parts: &[UploadPart],
     ) -> WebResponse {
         return WebResponse::NotImplemented;
     }
     fn route_unprotected_url_hook(
         &mut self,
         api: &Box<dyn PluginApi>,
-        hndl: &str,
-        user: &Option<Item>,
-        query: &str,
+        ctx: &RequestContext,
     ) -> WebResponse;
     fn route_unprotected_url_post_hook(
         &mut self,
         api: &Box<dyn PluginApi>,
-        hndl: &str,
-        user: &Option<Item>,
-        query: &str,
+        ctx: &RequestContext,
         itm: &Item,
+        parts: &[UploadPart],
     ) -> WebResponse;
 
+    /// `ctx.method` is the HTTP method, `payload` the raw body.
     fn route_rest_hook(
         &mut self,
         _api: &Box<dyn PluginApi>,
-        _hndl: &str,
-        _method: &str,
-        _user: &Option<Item>,
-        _query: &str,
+        _ctx: &RequestContext,
         _payload: &str,
     ) -> WebResponse {
         return WebResponse::NotImplemented;
@@ -151,6 +487,12 @@ pub trait Plugin: Send {
     ) -> bool;
     fn call_otp_hook(&mut self, api: &Box<dyn PluginApi>, hndl: &str, itm: &Item);
     fn call_periodic_job_hook(&mut self, _api: &Box<dyn PluginApi>, _timing: &str) {}
+
+    /// Self-description used for listing and hook filtering. The default is
+    /// an anonymous manifest subscribed to every hook.
+    fn manifest(&self) -> PluginManifest {
+        PluginManifest::default()
+    }
 }
 
 pub trait PluginApi: Send {
@@ -165,6 +507,35 @@ pub trait PluginApi: Send {
         skip: u64,
         limit: u64,
     ) -> ListResult;
+
+    /// [`PluginApi::db_get_all_items`] with a typed sort and filter.
+    fn db_find_all_items(&self, collection: &str, sort: &Sort, filter: &Filter) -> ListResult {
+        self.db_get_all_items(collection, sort.as_str(), &filter.to_string())
+    }
+
+    /// [`PluginApi::db_get_items`] with a typed sort and filter.
+    #[allow(clippy::too_many_arguments)]
+    fn db_find_items(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort: &Sort,
+        filter: &Filter,
+        skip: u64,
+        limit: u64,
+    ) -> ListResult {
+        self.db_get_items(
+            collection,
+            id_min,
+            id_max,
+            sort.as_str(),
+            &filter.to_string(),
+            skip,
+            limit,
+        )
+    }
+
     fn db_get_item(&self, collection: &str, id: u64) -> Option<Item>;
     fn db_set_item(&self, collection: &str, itm: &Item, merge: bool) -> u64;
     fn db_del_item(&self, collection: &str, id: u64) -> bool;
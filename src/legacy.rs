@@ -0,0 +1,604 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Bridge between the synchronous `Plugin`/`PluginApi` traits and the
+//! actor model, so legacy plugins can live in a [`PluginRegistry`] next to
+//! actor plugins while they are being migrated.
+//!
+//! A legacy plugin runs on its own OS thread: its hooks are blocking calls,
+//! and every `PluginApi` call it makes is a blocking round-trip to core
+//! through a [`CoreHandle`]. Running it on a runtime worker would stall
+//! every other task scheduled there.
+//!
+//! ```ignore
+//! pub fn register(reg: &mut PluginRegistry, core: CoreHandle) {
+//!     reg.add_legacy("my-legacy-plugin", Box::new(MyPlugin::new()), core);
+//! }
+//! ```
+//!
+//! The other way round, [`CoreHandleApi`] lets a host that still drives
+//! plugins through `PluginPool` pass them a `PluginApi` served by core's
+//! message loop:
+//!
+//! ```ignore
+//! let api = CoreHandleApi::new(core.clone());
+//! let borrows = api.state_borrows();
+//! let api: Box<dyn PluginApi> = Box::new(api);
+//! tokio::task::spawn_blocking(move || {
+//!     for plugin in &mut pool.plugins {
+//!         plugin.call_periodic_job_hook(&api, "min");
+//!         // SAFETY: the hook has returned, taking its state borrows along.
+//!         unsafe { borrows.release() };
+//!     }
+//! });
+//! ```
+
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::list_result::ListResult;
+use isabelle_dm::data_model::process_result::ProcessResult;
+use log::{error, info};
+use std::any::Any;
+use std::cell::UnsafeCell;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tokio::sync::mpsc;
+
+use crate::actor::*;
+use crate::api::{Plugin, PluginApi, WebResponse};
+
+/// Queue size of the hook channel created for a legacy plugin.
+const LEGACY_QUEUE_SIZE: usize = 64;
+
+// ---------------------------------------------------------------------------
+// PluginApi on top of a CoreHandle
+// ---------------------------------------------------------------------------
+
+type StateSlot = Option<Box<dyn Any + Send>>;
+
+/// `PluginApi` implementation that forwards every call to core as a
+/// [`CoreMessage`] and blocks until the reply arrives. Failures map to the
+/// same sentinels as the non-`try_*` [`CoreHandle`] methods.
+///
+/// Hosts still driving plugins through `PluginPool` can hand this to the
+/// legacy hooks, so both plugin flavours are served by the same core
+/// processing task. Calls block the calling thread until core answers or
+/// the handle's timeout expires. Made from a runtime worker, they block
+/// that worker, possibly for the full timeout (or indefinitely without
+/// one): invoke the hooks from a dedicated thread or
+/// `tokio::task::spawn_blocking`.
+///
+/// Plugin state (`fn_get_state`/`fn_set_state`) isn't core data; it is
+/// kept here, per `hndl`, for the lifetime of the API object. Since
+/// `fn_get_state` hands out `&mut` from `&self`, a `hndl`'s state can be
+/// borrowed only once until the host ends the borrows with
+/// [`StateBorrows::release`] after the hook returns; a second
+/// `fn_get_state` or a `fn_set_state` for that `hndl` before then panics.
+/// [`spawn_legacy_plugin`] releases them after every hook.
+pub struct CoreHandleApi {
+    core: CoreHandle,
+    // Slots are boxed so references handed out by `fn_get_state` stay
+    // valid when the map grows.
+    states: UnsafeCell<HashMap<String, Box<StateSlot>>>,
+    lent: StateBorrows,
+}
+
+impl CoreHandleApi {
+    pub fn new(core: CoreHandle) -> Self {
+        Self {
+            core,
+            states: UnsafeCell::new(HashMap::new()),
+            lent: StateBorrows::default(),
+        }
+    }
+
+    /// The handle requests are issued through.
+    pub fn core(&self) -> &CoreHandle {
+        &self.core
+    }
+
+    /// Handle for ending the state borrows of this API object; keep it
+    /// before boxing the object for the hooks.
+    pub fn state_borrows(&self) -> StateBorrows {
+        self.lent.clone()
+    }
+}
+
+/// The `hndl`s whose state a [`CoreHandleApi`] lent out through
+/// `fn_get_state`.
+#[derive(Clone, Default)]
+pub struct StateBorrows(Arc<Mutex<HashSet<String>>>);
+
+impl StateBorrows {
+    /// End every state borrow handed out so far.
+    ///
+    /// # Safety
+    ///
+    /// No reference returned by `fn_get_state` may still be live. That
+    /// holds between hook calls: hooks get the API by reference and can't
+    /// keep what it lends them.
+    pub unsafe fn release(&self) {
+        self.0.lock().unwrap().clear();
+    }
+
+    fn lend(&self, hndl: &str) {
+        let fresh = self.0.lock().unwrap().insert(hndl.to_string());
+        assert!(fresh, "state of {} is already borrowed", hndl);
+    }
+
+    fn check_free(&self, hndl: &str) {
+        let lent = self.0.lock().unwrap().contains(hndl);
+        assert!(!lent, "state of {} is borrowed", hndl);
+    }
+}
+
+impl PluginApi for CoreHandleApi {
+    fn db_get_all_items(&self, collection: &str, sort_key: &str, filter: &str) -> ListResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::DbGetAllItems {
+                collection: collection.into(),
+                sort_key: sort_key.into(),
+                filter: filter.into(),
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::empty_list())
+    }
+
+    fn db_get_items(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort_key: &str,
+        filter: &str,
+        skip: u64,
+        limit: u64,
+    ) -> ListResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::DbGetItems {
+                collection: collection.into(),
+                id_min,
+                id_max,
+                sort_key: sort_key.into(),
+                filter: filter.into(),
+                skip,
+                limit,
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::empty_list())
+    }
+
+    fn db_get_item(&self, collection: &str, id: u64) -> Option<Item> {
+        self.core
+            .blocking_request(|reply| CoreMessage::DbGetItem {
+                collection: collection.into(),
+                id,
+                reply,
+            })
+            .ok()
+            .flatten()
+    }
+
+    fn db_set_item(&self, collection: &str, itm: &Item, merge: bool) -> u64 {
+        self.core
+            .blocking_request(|reply| CoreMessage::DbSetItem {
+                collection: collection.into(),
+                item: itm.clone(),
+                merge,
+                reply,
+            })
+            .unwrap_or(u64::MAX)
+    }
+
+    fn db_del_item(&self, collection: &str, id: u64) -> bool {
+        self.core
+            .blocking_request(|reply| CoreMessage::DbDelItem {
+                collection: collection.into(),
+                id,
+                reply,
+            })
+            .unwrap_or(false)
+    }
+
+    fn globals_get_public_url(&self) -> String {
+        self.core
+            .blocking_request(|reply| CoreMessage::GlobalsGetPublicUrl { reply })
+            .unwrap_or_default()
+    }
+
+    fn globals_get_settings(&self) -> Item {
+        self.core
+            .blocking_request(|reply| CoreMessage::GlobalsGetSettings { reply })
+            .unwrap_or_else(|_| Item::new())
+    }
+
+    fn globals_set_settings(&self, itm: &Item) {
+        let _ = self
+            .core
+            .blocking_notify(CoreMessage::GlobalsSetSettings { item: itm.clone() });
+    }
+
+    fn auth_check_role(&self, itm: &Option<Item>, role: &str) -> bool {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthCheckRole {
+                item: itm.clone(),
+                role: role.into(),
+                reply,
+            })
+            .unwrap_or(false)
+    }
+
+    fn auth_get_new_salt(&self) -> String {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthGetNewSalt { reply })
+            .unwrap_or_default()
+    }
+
+    fn auth_get_password_hash(&self, pw: &str, salt: &str) -> String {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthGetPasswordHash {
+                password: pw.into(),
+                salt: salt.into(),
+                reply,
+            })
+            .unwrap_or_default()
+    }
+
+    fn auth_verify_password(&self, pw: &str, pw_hash: &str) -> bool {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthVerifyPassword {
+                password: pw.into(),
+                hash: pw_hash.into(),
+                reply,
+            })
+            .unwrap_or(false)
+    }
+
+    fn auth_login(&self, login: &str, password: &str) -> ProcessResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthLogin {
+                login: login.into(),
+                password: password.into(),
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::unavailable())
+    }
+
+    fn auth_logout(&self, login: &str) -> ProcessResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthLogout {
+                login: login.into(),
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::unavailable())
+    }
+
+    fn auth_register(&self, login: &str, email: &str) -> ProcessResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthRegister {
+                login: login.into(),
+                email: email.into(),
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::unavailable())
+    }
+
+    fn auth_gen_otp(&self, login: &str) -> ProcessResult {
+        self.core
+            .blocking_request(|reply| CoreMessage::AuthGenOtp {
+                login: login.into(),
+                reply,
+            })
+            .unwrap_or_else(|_| CoreHandle::unavailable())
+    }
+
+    fn fn_send_email(&self, to: &str, subject: &str, body: &str) {
+        let _ = self.core.blocking_notify(CoreMessage::SendEmail {
+            to: to.into(),
+            subject: subject.into(),
+            body: body.into(),
+        });
+    }
+
+    fn fn_init_google(&self) -> String {
+        self.core
+            .blocking_request(|reply| CoreMessage::InitGoogle { reply })
+            .unwrap_or_default()
+    }
+
+    fn fn_sync_with_google(&self, add: bool, name: String, date_time: String) {
+        let _ = self.core.blocking_notify(CoreMessage::SyncWithGoogle {
+            add,
+            name,
+            date_time,
+        });
+    }
+
+    #[allow(clippy::mut_from_ref)]
+    fn fn_get_state(&self, hndl: &str) -> &mut Option<Box<dyn Any + Send>> {
+        self.lent.lend(hndl);
+        // SAFETY: `lend` panics if the slot was lent since the borrows were
+        // last released, so no other reference to it is live. The object
+        // isn't `Sync`, so the map is only touched from this thread, and
+        // boxed slots are never removed, so the slot outlives the
+        // returned reference.
+        unsafe {
+            let states = &mut *self.states.get();
+            states.entry(hndl.to_string()).or_default().as_mut()
+        }
+    }
+
+    fn fn_set_state(&self, hndl: &str, value: Option<Box<dyn Any + Send>>) {
+        self.lent.check_free(hndl);
+        // SAFETY: as in `fn_get_state`; `check_free` panics if a reference
+        // to the slot may be live.
+        unsafe {
+            let states = &mut *self.states.get();
+            **states.entry(hndl.to_string()).or_default() = value;
+        }
+    }
+
+    fn secret_get(&self, id: u64) -> Option<Item> {
+        self.core
+            .blocking_request(|reply| CoreMessage::SecretGet { id, reply })
+            .ok()
+            .flatten()
+    }
+
+    fn secret_get_by_name(&self, name: &str) -> Option<Item> {
+        self.core
+            .blocking_request(|reply| CoreMessage::SecretGetByName {
+                name: name.into(),
+                reply,
+            })
+            .ok()
+            .flatten()
+    }
+
+    fn secret_list(&self) -> Vec<(u64, String)> {
+        self.core
+            .blocking_request(|reply| CoreMessage::SecretList { reply })
+            .unwrap_or_default()
+    }
+
+    fn secret_set(&self, item: &Item, merge: bool) -> Result<u64, String> {
+        self.core
+            .blocking_request(|reply| CoreMessage::SecretSet {
+                item: item.clone(),
+                merge,
+                reply,
+            })
+            .unwrap_or_else(|e| Err(e.to_string()))
+    }
+
+    fn secret_del(&self, id: u64) -> bool {
+        self.core
+            .blocking_request(|reply| CoreMessage::SecretDel { id, reply })
+            .unwrap_or(false)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Legacy plugin actor
+// ---------------------------------------------------------------------------
+
+/// Run `plugin` as an actor on a dedicated thread and return the sender
+/// of its hook channel. The thread exits on `Shutdown` or when every
+/// sender has been dropped.
+pub fn spawn_legacy_plugin(
+    name: &str,
+    plugin: Box<dyn Plugin>,
+    core: CoreHandle,
+) -> mpsc::Sender<PluginHookMessage> {
+    let (tx, rx) = mpsc::channel(LEGACY_QUEUE_SIZE);
+    let thread_name = format!("plugin-{}", name);
+    let res = thread::Builder::new()
+        .name(thread_name.clone())
+        .spawn(move || run_legacy_plugin(plugin, rx, core));
+    if let Err(e) = res {
+        // The receiver is gone with the closure, so the dispatcher will see
+        // this plugin as dead.
+        error!("Failed to start thread {}: {}", thread_name, e);
+    }
+    tx
+}
+
+fn run_legacy_plugin(
+    mut plugin: Box<dyn Plugin>,
+    mut rx: mpsc::Receiver<PluginHookMessage>,
+    core: CoreHandle,
+) {
+    let api = CoreHandleApi::new(core);
+    let borrows = api.state_borrows();
+    let api: Box<dyn PluginApi> = Box::new(api);
+    while let Some(msg) = rx.blocking_recv() {
+        match msg {
+            PluginHookMessage::ItemPreEdit {
+                hndl,
+                user,
+                collection,
+                old_item,
+                mut item,
+                action,
+                merge,
+                reply,
+            } => {
+                let result = plugin.item_pre_edit_hook(
+                    &api,
+                    &hndl,
+                    &user,
+                    &collection,
+                    old_item,
+                    &mut item,
+                    action,
+                    merge,
+                );
+                let _ = reply.send(PreEditReply {
+                    result,
+                    modified_item: Some(item),
+                });
+            }
+            PluginHookMessage::ItemPostEdit {
+                hndl,
+                user,
+                collection,
+                old_item,
+                new_item,
+                id,
+                action,
+                merge,
+            } => {
+                plugin.item_post_edit_hook(
+                    &api,
+                    &hndl,
+                    &user,
+                    &collection,
+                    old_item,
+                    new_item,
+                    id,
+                    action,
+                    merge,
+                );
+            }
+            PluginHookMessage::ItemAuth {
+                hndl,
+                user,
+                collection,
+                id,
+                new_item,
+                del,
+                reply,
+            } => {
+                let allowed =
+                    plugin.item_auth_hook(&api, &hndl, &user, &collection, id, new_item, del);
+                let _ = reply.send(allowed);
+            }
+            PluginHookMessage::ItemListFilter {
+                hndl,
+                user,
+                collection,
+                context,
+                mut items,
+                reply,
+            } => {
+                plugin.item_list_filter_hook(&api, &hndl, &user, &collection, &context, &mut items);
+                let _ = reply.send(ListFilterReply { items });
+            }
+            PluginHookMessage::ItemListDbFilter {
+                hndl,
+                user,
+                collection,
+                context,
+                filter_type,
+                reply,
+            } => {
+                let filter = plugin.item_list_db_filter_hook(
+                    &api,
+                    &hndl,
+                    &user,
+                    &collection,
+                    &context,
+                    &filter_type,
+                );
+                let _ = reply.send(filter);
+            }
+            PluginHookMessage::CollectionRead {
+                hndl,
+                collection,
+                mut item,
+                reply,
+            } => {
+                let should_save = plugin.collection_read_hook(&api, &hndl, &collection, &mut item);
+                let _ = reply.send(CollectionReadReply {
+                    should_save,
+                    item: Some(item),
+                });
+            }
+            PluginHookMessage::Otp { hndl, item } => {
+                plugin.call_otp_hook(&api, &hndl, &item);
+            }
+            PluginHookMessage::PeriodicJob { timing } => {
+                plugin.call_periodic_job_hook(&api, &timing);
+            }
+            PluginHookMessage::RouteUrl { ctx, reply } => {
+                let _ = reply.send(plugin.route_url_hook(&api, &ctx));
+            }
+            PluginHookMessage::RouteUrlPost {
+                ctx,
+                item,
+                parts,
+                reply,
+            } => {
+                let _ = reply.send(plugin.route_url_post_hook(&api, &ctx, &item, &parts));
+            }
+            PluginHookMessage::RouteUnprotectedUrl { ctx, reply } => {
+                let _ = reply.send(plugin.route_unprotected_url_hook(&api, &ctx));
+            }
+            PluginHookMessage::RouteUnprotectedUrlPost {
+                ctx,
+                item,
+                parts,
+                reply,
+            } => {
+                let _ =
+                    reply.send(plugin.route_unprotected_url_post_hook(&api, &ctx, &item, &parts));
+            }
+            PluginHookMessage::RouteRest {
+                ctx,
+                payload,
+                reply,
+            } => {
+                let _ = reply.send(plugin.route_rest_hook(&api, &ctx, &payload));
+            }
+            // Legacy plugins have no way to hold a connection open.
+            PluginHookMessage::WebSocketConnect { reply, .. }
+            | PluginHookMessage::SseConnect { reply, .. } => {
+                let _ = reply.send(WebResponse::NotImplemented);
+            }
+            PluginHookMessage::ConnectionClosed { .. } => {}
+            PluginHookMessage::Ping { reply } => {
+                plugin.ping_test();
+                let _ = reply.send(());
+            }
+            PluginHookMessage::Shutdown => break,
+        }
+        // SAFETY: the hook has returned, taking its state borrows along.
+        unsafe { borrows.release() };
+    }
+    info!(
+        "Legacy plugin thread {} stopped",
+        thread::current().name().unwrap_or("?")
+    );
+}
+
+impl PluginRegistry {
+    /// Register a legacy `Plugin` implementation: spawns it as an actor via
+    /// [`spawn_legacy_plugin`] and adds its sender with the plugin's
+    /// manifest. `name` is used when the manifest doesn't name the plugin.
+    pub fn add_legacy(
+        &mut self,
+        name: impl Into<String>,
+        plugin: Box<dyn Plugin>,
+        core: CoreHandle,
+    ) {
+        let mut manifest = plugin.manifest();
+        if manifest.name.is_empty() {
+            manifest.name = name.into();
+        }
+        let sender = spawn_legacy_plugin(&manifest.name, plugin, core);
+        self.add_with_manifest(manifest, sender);
+    }
+}
@@ -21,6 +21,17 @@
  * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
  * DEALINGS IN THE SOFTWARE.
  */
+pub mod abi;
 pub mod actor;
 pub mod api;
+pub mod legacy;
+pub mod live;
+pub mod manifest;
 pub mod plugin_pool;
+pub mod query;
+pub mod request;
+pub mod rest;
+pub mod route;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod upload;
@@ -0,0 +1,233 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Long-lived connections: WebSockets and server-sent events.
+//!
+//! Core upgrades the request and asks plugins with
+//! `PluginHookMessage::WebSocketConnect` or `SseConnect`, routed like
+//! `RouteUrl` (declared routes, roles, 404/405). The message carries the
+//! plugin's end of the connection; replying `WebResponse::Ok` accepts it,
+//! `WebResponse::NotImplemented` passes it to the next plugin and anything
+//! else is sent to the client instead. When the client goes away, the
+//! plugin gets `PluginHookMessage::ConnectionClosed` with the connection's
+//! id.
+//!
+//! ```ignore
+//! PluginHookMessage::WebSocketConnect { ctx, conn, reply } => {
+//!     let _ = reply.send(WebResponse::Ok);
+//!     tokio::spawn(async move {
+//!         let WebSocketConn { id, outgoing, mut incoming } = conn;
+//!         while let Some(WsMessage::Text(text)) = incoming.recv().await {
+//!             let _ = outgoing.send(WsMessage::Text(format!("{}: {}", id, text))).await;
+//!         }
+//!     });
+//! }
+//! PluginHookMessage::SseConnect { conn, reply, .. } => {
+//!     let _ = reply.send(WebResponse::Ok);
+//!     dashboards.insert(conn.id, conn.events);
+//! }
+//! PluginHookMessage::ConnectionClosed { id } => {
+//!     dashboards.remove(&id);
+//! }
+//! ```
+
+use futures_core::Stream;
+use log::warn;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use tokio::runtime::Handle;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
+
+use crate::actor::PluginHookMessage;
+use crate::api::{Chunk, StreamResponse, WebResponse};
+
+/// Identifies a connection for its lifetime; unique within the process.
+pub type ConnectionId = u64;
+
+/// Messages either side of a connection buffers before sends wait.
+pub const CONNECTION_CAPACITY: usize = 64;
+
+pub(crate) fn next_connection_id() -> ConnectionId {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// A WebSocket message. Pings and pongs are answered by core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WsMessage {
+    Text(String),
+    Binary(Vec<u8>),
+    /// Close the connection, with an optional reason.
+    Close(Option<String>),
+}
+
+/// The plugin's end of a WebSocket.
+#[derive(Debug)]
+pub struct WebSocketConn {
+    pub id: ConnectionId,
+    /// Messages to the client. Dropping it closes the connection.
+    pub outgoing: mpsc::Sender<WsMessage>,
+    /// Messages from the client; ends when the connection closes.
+    pub incoming: mpsc::Receiver<WsMessage>,
+}
+
+/// One server-sent event.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SseEvent {
+    /// Event type; clients treat a missing one as "message".
+    pub event: Option<String>,
+    pub id: Option<String>,
+    pub data: String,
+}
+
+impl SseEvent {
+    pub fn new(data: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn event(mut self, event: impl Into<String>) -> Self {
+        self.event = Some(event.into());
+        self
+    }
+
+    pub fn id(mut self, id: impl Into<String>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// The event in `text/event-stream` format, one `data:` line per line
+    /// of `data` (split on `\r\n`, `\r` and `\n`). Line breaks in `event`
+    /// and `id` are removed so they can't start new fields.
+    pub fn to_frame(&self) -> String {
+        let mut frame = String::new();
+        if let Some(event) = &self.event {
+            frame.push_str(&format!("event: {}\n", single_line(event)));
+        }
+        if let Some(id) = &self.id {
+            frame.push_str(&format!("id: {}\n", single_line(id)));
+        }
+        for line in self.data.replace("\r\n", "\n").split(['\r', '\n']) {
+            frame.push_str(&format!("data: {}\n", line));
+        }
+        frame.push('\n');
+        frame
+    }
+}
+
+fn single_line(field: &str) -> String {
+    field.replace(['\r', '\n'], "")
+}
+
+/// The plugin's end of a server-sent event stream.
+#[derive(Debug)]
+pub struct SseConn {
+    pub id: ConnectionId,
+    /// Events to the client. Dropping it ends the stream.
+    pub events: mpsc::Sender<SseEvent>,
+}
+
+/// Sends `ConnectionClosed` to the owning plugin when dropped. If the
+/// plugin's queue is full the notice is sent from a task on the current
+/// Tokio runtime.
+pub(crate) struct CloseNotice {
+    pub(crate) id: ConnectionId,
+    pub(crate) plugin: String,
+    pub(crate) sender: mpsc::Sender<PluginHookMessage>,
+}
+
+impl Drop for CloseNotice {
+    fn drop(&mut self) {
+        let msg = PluginHookMessage::ConnectionClosed { id: self.id };
+        // A full queue must not lose the notice, or the plugin would keep
+        // the connection forever; wait for room on the runtime instead.
+        let Err(TrySendError::Full(msg)) = self.sender.try_send(msg) else {
+            return;
+        };
+        match Handle::try_current() {
+            Ok(runtime) => {
+                let sender = self.sender.clone();
+                runtime.spawn(async move {
+                    let _ = sender.send(msg).await;
+                });
+            }
+            Err(_) => warn!(
+                "Plugin {} missed ConnectionClosed for connection {}",
+                self.plugin, self.id
+            ),
+        }
+    }
+}
+
+/// Core's end of an accepted WebSocket. Dropping it tells the plugin the
+/// connection closed.
+pub struct WebSocketSession {
+    /// Messages from the client, for the plugin.
+    pub to_plugin: mpsc::Sender<WsMessage>,
+    /// Messages from the plugin, for the client.
+    pub from_plugin: mpsc::Receiver<WsMessage>,
+    pub(crate) closed: CloseNotice,
+}
+
+/// Core's end of an accepted event stream. Dropping it tells the plugin
+/// the connection closed.
+pub struct SseSession {
+    pub events: mpsc::Receiver<SseEvent>,
+    pub(crate) closed: CloseNotice,
+}
+
+impl WebSocketSession {
+    pub fn id(&self) -> ConnectionId {
+        self.closed.id
+    }
+
+    /// Name of the plugin that accepted the connection.
+    pub fn plugin(&self) -> &str {
+        &self.closed.plugin
+    }
+}
+
+impl SseSession {
+    pub fn id(&self) -> ConnectionId {
+        self.closed.id
+    }
+
+    /// Name of the plugin that accepted the connection.
+    pub fn plugin(&self) -> &str {
+        &self.closed.plugin
+    }
+
+    /// A `text/event-stream` response sending the plugin's events. The
+    /// session lives as long as the response body, so a client
+    /// disconnecting closes it.
+    pub fn into_response(self) -> WebResponse {
+        WebResponse::Stream(StreamResponse::from_stream("text/event-stream", self))
+    }
+}
+
+impl Stream for SseSession {
+    type Item = Chunk;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Chunk>> {
+        self.events
+            .poll_recv(cx)
+            .map(|event| event.map(|e| Ok(e.to_frame().into_bytes())))
+    }
+}
@@ -0,0 +1,174 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Plugin self-description. A [`PluginManifest`] is returned by legacy
+//! plugins through `Plugin::manifest` and passed along with the sender by
+//! actor plugins (`PluginRegistry::add_with_manifest`). Core uses it to
+//! list installed plugins and the dispatcher to skip hooks a plugin
+//! doesn't handle and to route requests by the plugin's [`RouteSpec`]s.
+
+use std::collections::HashSet;
+
+use crate::route::RouteSpec;
+
+/// Hooks a plugin can subscribe to. `Ping` and `Shutdown` are always
+/// delivered and therefore not listed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum HookKind {
+    ItemPreEdit,
+    ItemPostEdit,
+    ItemAuth,
+    ItemListFilter,
+    ItemListDbFilter,
+    CollectionRead,
+    Otp,
+    PeriodicJob,
+    RouteUrl,
+    RouteUrlPost,
+    RouteUnprotectedUrl,
+    RouteUnprotectedUrlPost,
+    RouteRest,
+    WebSocketConnect,
+    SseConnect,
+}
+
+/// Name, version and interests of a plugin.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PluginManifest {
+    pub name: String,
+    /// Semantic version, e.g. "1.2.0".
+    pub version: String,
+    pub author: String,
+    /// Hooks the plugin handles. `None` subscribes to every hook.
+    pub hooks: Option<HashSet<HookKind>>,
+    /// Collections whose item hooks the plugin wants. Empty means all.
+    pub collections: Vec<String>,
+    /// Core capabilities the plugin relies on (e.g. "email", "secrets").
+    pub capabilities: Vec<String>,
+    /// Routes the plugin serves. Empty means it is offered every request
+    /// no other plugin declared a route for.
+    pub routes: Vec<RouteSpec>,
+}
+
+impl PluginManifest {
+    /// Manifest subscribing to every hook on every collection.
+    pub fn new(name: impl Into<String>, version: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn author(mut self, author: impl Into<String>) -> Self {
+        self.author = author.into();
+        self
+    }
+
+    /// Restrict the subscription to `hooks`.
+    pub fn hooks(mut self, hooks: impl IntoIterator<Item = HookKind>) -> Self {
+        self.hooks = Some(hooks.into_iter().collect());
+        self
+    }
+
+    /// Restrict item hooks to `collections`.
+    pub fn collections<S: Into<String>>(
+        mut self,
+        collections: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.collections = collections.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn requires<S: Into<String>>(mut self, capabilities: impl IntoIterator<Item = S>) -> Self {
+        self.capabilities = capabilities.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn route(mut self, route: RouteSpec) -> Self {
+        self.routes.push(route);
+        self
+    }
+
+    pub fn routes(mut self, routes: impl IntoIterator<Item = RouteSpec>) -> Self {
+        self.routes.extend(routes);
+        self
+    }
+
+    pub fn handles(&self, hook: HookKind) -> bool {
+        self.hooks.as_ref().is_none_or(|h| h.contains(&hook))
+    }
+
+    pub fn interested_in(&self, collection: &str) -> bool {
+        self.collections.is_empty() || self.collections.iter().any(|c| c == collection)
+    }
+
+    /// Whether `hook` should be delivered; `collection` is given for item
+    /// hooks only.
+    pub fn accepts(&self, hook: HookKind, collection: Option<&str>) -> bool {
+        self.handles(hook) && collection.is_none_or(|c| self.interested_in(c))
+    }
+
+    /// Capabilities from this manifest that are not in `available`.
+    pub fn missing_capabilities<'a>(&'a self, available: &[&str]) -> Vec<&'a str> {
+        self.capabilities
+            .iter()
+            .map(String::as_str)
+            .filter(|c| !available.contains(c))
+            .collect()
+    }
+
+    /// Checked before the plugin is registered: `version`, if set, must be
+    /// a semantic version, and every required capability must be in
+    /// `available` (`None` when core doesn't restrict them).
+    pub fn validate(&self, available: Option<&[&str]>) -> Result<(), String> {
+        if !self.version.is_empty() && !is_semver(&self.version) {
+            return Err(format!(
+                "plugin {} has invalid version {:?}",
+                self.name, self.version
+            ));
+        }
+        let missing = available.map_or_else(Vec::new, |a| self.missing_capabilities(a));
+        if !missing.is_empty() {
+            return Err(format!(
+                "plugin {} requires unavailable capabilities: {}",
+                self.name,
+                missing.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// `MAJOR.MINOR.PATCH` with an optional `-pre-release` and `+build`.
+fn is_semver(version: &str) -> bool {
+    let (version, build) = match version.split_once('+') {
+        Some((v, b)) => (v, Some(b)),
+        None => (version, None),
+    };
+    let (core, pre) = match version.split_once('-') {
+        Some((c, p)) => (c, Some(p)),
+        None => (version, None),
+    };
+    let numbers: Vec<&str> = core.split('.').collect();
+    numbers.len() == 3
+        && numbers
+            .iter()
+            .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        && pre.is_none_or(|p| !p.is_empty())
+        && build.is_none_or(|b| !b.is_empty())
+}
@@ -21,10 +21,20 @@
  * FROM, OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER
  * DEALINGS IN THE SOFTWARE.
  */
+use crate::abi::verify_plugin_abi;
 use crate::api::*;
-use libloading::{Library, Symbol};
+use crate::manifest::PluginManifest;
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::process_result::ProcessResult;
+use libloading::Library;
 use log::{error, info, warn};
+use std::collections::HashMap;
 use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::SystemTime;
 
 #[repr(C)]
 /// Plugin pool structure
@@ -32,6 +42,22 @@ pub struct PluginPool {
     pub plugins: Vec<Box<dyn Plugin>>,
 }
 
+/// Shared libraries loaded into a [`PluginPool`], with the plugins each
+/// registered. Kept next to the pool by hosts that unload or reload
+/// libraries; every method takes the pool it manages.
+#[derive(Default)]
+pub struct PluginLibraries {
+    libraries: Vec<PoolLibrary>,
+    /// Capabilities core offers; `None` doesn't restrict plugins.
+    capabilities: Option<Vec<String>>,
+}
+
+struct PoolLibrary {
+    path: String,
+    /// [`plugin_key`]s of the plugins registered by the library.
+    plugins: Vec<usize>,
+}
+
 /// Description of a single plugin-loading failure.
 #[derive(Debug, Clone)]
 pub struct PluginLoadFailure {
@@ -72,136 +98,674 @@ impl PluginPoolApi for PluginPool {
     }
 }
 
+/// Scan `path` for files whose name starts with `prefix` and return their
+/// canonical paths. `result.considered` is bumped for every candidate and
+/// directory/path errors are recorded as failures.
+pub(crate) fn find_plugin_libraries(
+    path: &str,
+    prefix: &str,
+    result: &mut PluginLoadResult,
+) -> Vec<String> {
+    let mut found = Vec::new();
+    info!("Loading plugins from {}", path);
+
+    let paths = match fs::read_dir(path) {
+        Ok(p) => p,
+        Err(e) => {
+            let msg = format!("Failed to read plugin directory {}: {}", path, e);
+            error!("{}", msg);
+            result.failures.push(PluginLoadFailure {
+                path: path.to_string(),
+                error: msg,
+            });
+            return found;
+        }
+    };
+
+    for entry in paths {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                let msg = format!("Failed to read directory entry in {}: {}", path, e);
+                warn!("{}", msg);
+                result.failures.push(PluginLoadFailure {
+                    path: path.to_string(),
+                    error: msg,
+                });
+                continue;
+            }
+        };
+        let entry_path = entry.path();
+        let file_name = match entry_path.file_name() {
+            Some(n) => n.to_string_lossy().to_string(),
+            None => {
+                warn!(
+                    "Skipping entry without a file name in {}: {}",
+                    path,
+                    entry_path.display()
+                );
+                continue;
+            }
+        };
+
+        if !file_name.starts_with(prefix) {
+            continue;
+        }
+
+        result.considered += 1;
+        info!("Found plugin candidate: {}", file_name);
+
+        match entry_path.canonicalize() {
+            Ok(p) => found.push(p.to_string_lossy().to_string()),
+            Err(e) => {
+                let path_str = entry_path.to_string_lossy().to_string();
+                let msg = format!("Failed to canonicalize plugin path {}: {}", path_str, e);
+                error!("{}", msg);
+                result.failures.push(PluginLoadFailure {
+                    path: path_str,
+                    error: msg,
+                });
+            }
+        };
+    }
+    found
+}
+
+/// Load a temporary copy of `full`, removed again once it is open.
+///
+/// The dynamic loader hands out the already loaded image when a path is
+/// opened a second time, and glibc never unmaps a library that registered
+/// thread-local destructors (which most Rust plugins do). Loading through a
+/// unique path makes sure a replaced file is actually read again.
+unsafe fn load_private_copy(full: &str) -> Result<Library, String> {
+    static NEXT_COPY: AtomicUsize = AtomicUsize::new(0);
+
+    let name = Path::new(full)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let copy = std::env::temp_dir().join(format!(
+        "isabelle-plugin-{}-{}-{}",
+        std::process::id(),
+        NEXT_COPY.fetch_add(1, Ordering::Relaxed),
+        name
+    ));
+    fs::copy(full, &copy).map_err(|e| format!("Failed to copy plugin library {}: {}", full, e))?;
+    let lib =
+        Library::new(&copy).map_err(|e| format!("Failed to load plugin library {}: {}", full, e));
+    let _ = fs::remove_file(&copy);
+    lib
+}
+
+/// Open the shared library at `full` (through a private copy if
+/// `private_copy` is set, see [`load_private_copy`]) and check its ABI
+/// descriptor. The library stays loaded while a clone of the returned
+/// handle is alive. Failures are recorded in `result`; an incompatible
+/// library is unloaded again.
+///
+/// # Safety
+///
+/// Loading a library runs its initialisers; the file must be a trusted
+/// plugin.
+pub(crate) unsafe fn open_plugin_library(
+    full: &str,
+    private_copy: bool,
+    result: &mut PluginLoadResult,
+) -> Option<Arc<Library>> {
+    info!("Loading library {}", full);
+    let res = if private_copy {
+        load_private_copy(full)
+    } else {
+        Library::new(full).map_err(|e| format!("Failed to load plugin library {}: {}", full, e))
+    }
+    .and_then(|lib| verify_plugin_abi(&lib, full).map(|_| lib));
+    match res {
+        Ok(l) => Some(Arc::new(l)),
+        Err(msg) => {
+            error!("{}", msg);
+            result.failures.push(PluginLoadFailure {
+                path: full.to_string(),
+                error: msg,
+            });
+            None
+        }
+    }
+}
+
+/// Record that `lib` at `full` lacks the `register` symbol.
+pub(crate) fn missing_register_symbol(
+    full: &str,
+    e: libloading::Error,
+    result: &mut PluginLoadResult,
+) {
+    let msg = format!("Plugin {} is missing the `register` symbol: {}", full, e);
+    error!("{}", msg);
+    result.failures.push(PluginLoadFailure {
+        path: full.to_string(),
+        error: msg,
+    });
+}
+
+/// Log the outcome of loading plugins from `path`.
+pub(crate) fn log_load_result(path: &str, result: &PluginLoadResult) {
+    info!(
+        "Plugin loading from {} finished: {} candidate(s), {} loaded, {} failed",
+        path,
+        result.considered,
+        result.loaded,
+        result.failed()
+    );
+    if !result.is_ok() {
+        warn!("{} plugin(s) failed to load from {}", result.failed(), path);
+    }
+}
+
+/// Canonical form of `path` as recorded by the loaders, or `path` itself
+/// when it can't be resolved (e.g. the file was already removed).
+pub(crate) fn canonical_plugin_path(path: &str) -> String {
+    fs::canonicalize(path)
+        .map(|p| p.to_string_lossy().to_string())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+/// Identity of a boxed plugin, stable for as long as the box lives.
+fn plugin_key(plugin: &dyn Plugin) -> usize {
+    plugin as *const dyn Plugin as *const () as usize
+}
+
+/// Plugin registered by a shared library. Keeps the library loaded until
+/// the plugin itself has been dropped (fields drop in declaration order).
+struct LibraryPlugin {
+    plugin: Box<dyn Plugin>,
+    _library: Arc<Library>,
+}
+
+impl Plugin for LibraryPlugin {
+    fn ping_test(&mut self) {
+        self.plugin.ping_test()
+    }
+
+    fn item_pre_edit_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        old_itm: Option<Item>,
+        itm: &mut Item,
+        action: DataObjectAction,
+        merge: bool,
+    ) -> ProcessResult {
+        self.plugin
+            .item_pre_edit_hook(api, hndl, user, collection, old_itm, itm, action, merge)
+    }
+
+    fn item_post_edit_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        old_itm: Option<Item>,
+        new_itm: Option<Item>,
+        id: u64,
+        action: DataObjectAction,
+        merge: bool,
+    ) {
+        self.plugin.item_post_edit_hook(
+            api, hndl, user, collection, old_itm, new_itm, id, action, merge,
+        )
+    }
+
+    fn item_auth_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        id: u64,
+        new_item: Option<Item>,
+        del: bool,
+    ) -> bool {
+        self.plugin
+            .item_auth_hook(api, hndl, user, collection, id, new_item, del)
+    }
+
+    fn item_list_filter_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        context: &str,
+        map: &mut HashMap<u64, Item>,
+    ) {
+        self.plugin
+            .item_list_filter_hook(api, hndl, user, collection, context, map)
+    }
+
+    fn item_list_db_filter_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        context: &str,
+        filter_type: &str,
+    ) -> String {
+        self.plugin
+            .item_list_db_filter_hook(api, hndl, user, collection, context, filter_type)
+    }
+
+    fn route_url_hook(&mut self, api: &Box<dyn PluginApi>, ctx: &RequestContext) -> WebResponse {
+        self.plugin.route_url_hook(api, ctx)
+    }
+
+    fn route_url_post_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        ctx: &RequestContext,
+        itm: &Item,
+        parts: &[UploadPart],
+    ) -> WebResponse {
+        self.plugin.route_url_post_hook(api, ctx, itm, parts)
+    }
+
+    fn route_unprotected_url_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        ctx: &RequestContext,
+    ) -> WebResponse {
+        self.plugin.route_unprotected_url_hook(api, ctx)
+    }
+
+    fn route_unprotected_url_post_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        ctx: &RequestContext,
+        itm: &Item,
+        parts: &[UploadPart],
+    ) -> WebResponse {
+        self.plugin
+            .route_unprotected_url_post_hook(api, ctx, itm, parts)
+    }
+
+    fn route_rest_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        ctx: &RequestContext,
+        payload: &str,
+    ) -> WebResponse {
+        self.plugin.route_rest_hook(api, ctx, payload)
+    }
+
+    fn collection_read_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        collection: &str,
+        itm: &mut Item,
+    ) -> bool {
+        self.plugin.collection_read_hook(api, hndl, collection, itm)
+    }
+
+    fn call_otp_hook(&mut self, api: &Box<dyn PluginApi>, hndl: &str, itm: &Item) {
+        self.plugin.call_otp_hook(api, hndl, itm)
+    }
+
+    fn call_periodic_job_hook(&mut self, api: &Box<dyn PluginApi>, timing: &str) {
+        self.plugin.call_periodic_job_hook(api, timing)
+    }
+
+    fn manifest(&self) -> PluginManifest {
+        self.plugin.manifest()
+    }
+}
+
+/// [`PluginPoolApi`] handed to a library's `register`; records which
+/// plugins the library owns and refuses those whose manifest fails
+/// [`PluginManifest::validate`].
+struct LibraryRegistrar<'a> {
+    pool: &'a mut PluginPool,
+    library: Arc<Library>,
+    capabilities: Option<&'a [&'a str]>,
+    keys: Vec<usize>,
+    refused: Vec<String>,
+}
+
+impl PluginPoolApi for LibraryRegistrar<'_> {
+    fn register(&mut self, plugin: Box<dyn Plugin>) {
+        if let Err(e) = plugin.manifest().validate(self.capabilities) {
+            warn!("Refusing to register {}", e);
+            self.refused.push(e);
+            return;
+        }
+        let plugin: Box<dyn Plugin> = Box::new(LibraryPlugin {
+            plugin,
+            _library: self.library.clone(),
+        });
+        self.keys.push(plugin_key(plugin.as_ref()));
+        self.pool.plugins.push(plugin);
+    }
+}
+
 impl PluginPool {
+    pub fn new() -> Self {
+        Self {
+            plugins: Vec::new(),
+        }
+    }
+
     /// Load plugins from the given path, pass the API to them.
     ///
+    /// Each library must export a matching ABI descriptor (see
+    /// [`crate::abi`]); `register` is not called otherwise.
+    ///
     /// Returns a [`PluginLoadResult`] describing how many candidates were
     /// considered, how many were successfully registered, and the per-file
     /// failures encountered (if any). All failures are also logged.
+    ///
+    /// Plugins whose manifest fails [`PluginManifest::validate`] are
+    /// refused and reported as failures.
+    ///
+    /// The pool doesn't remember which libraries it loaded, so calling this
+    /// again registers their plugins again. Use
+    /// [`PluginLibraries::load_plugins`] to rescan a directory or to
+    /// restrict the capabilities plugins may require.
     pub fn load_plugins(&mut self, path: &str) -> PluginLoadResult {
         let mut result = PluginLoadResult::default();
-        info!("Loading plugins from {}", path);
 
-        let paths = match fs::read_dir(path) {
-            Ok(p) => p,
+        for full in find_plugin_libraries(path, "libisabelle_plugin_", &mut result) {
+            load_pool_library(self, &full, false, None, &mut result);
+        }
+
+        log_load_result(path, &result);
+        result
+    }
+
+    /// Manifests of the registered plugins, in registration order.
+    pub fn manifests(&self) -> Vec<PluginManifest> {
+        self.plugins.iter().map(|p| p.manifest()).collect()
+    }
+
+    pub fn ping_plugins(&mut self) {
+        for plugin in &mut self.plugins {
+            plugin.ping_test();
+        }
+    }
+}
+
+/// Open the library at `full`, call its `register` with `pool` and return
+/// the [`plugin_key`]s of the plugins it registered, or `None` if it failed
+/// to load. Failures, including refused plugins, are recorded in `result`.
+fn load_pool_library(
+    pool: &mut PluginPool,
+    full: &str,
+    private_copy: bool,
+    capabilities: Option<&[&str]>,
+    result: &mut PluginLoadResult,
+) -> Option<Vec<usize>> {
+    unsafe {
+        let lib = open_plugin_library(full, private_copy, result)?;
+        let func = match lib.get::<unsafe extern "C" fn(&mut dyn PluginPoolApi)>(b"register") {
+            Ok(func) => *func,
             Err(e) => {
-                let msg = format!("Failed to read plugin directory {}: {}", path, e);
-                error!("{}", msg);
-                result.failures.push(PluginLoadFailure {
-                    path: path.to_string(),
-                    error: msg,
-                });
-                return result;
+                missing_register_symbol(full, e, result);
+                return None;
             }
         };
+        let mut registrar = LibraryRegistrar {
+            pool,
+            library: lib,
+            capabilities,
+            keys: Vec::new(),
+            refused: Vec::new(),
+        };
+        func(&mut registrar);
+        for error in registrar.refused {
+            result.failures.push(PluginLoadFailure {
+                path: full.to_string(),
+                error,
+            });
+        }
+        result.loaded += 1;
+        info!("Plugin registered from {}", full);
+        Some(registrar.keys)
+    }
+}
 
-        for entry in paths {
-            let entry = match entry {
-                Ok(e) => e,
-                Err(e) => {
-                    let msg = format!("Failed to read directory entry in {}: {}", path, e);
-                    warn!("{}", msg);
-                    result.failures.push(PluginLoadFailure {
-                        path: path.to_string(),
-                        error: msg,
-                    });
-                    continue;
-                }
-            };
-            let entry_path = entry.path();
-            let file_name = match entry_path.file_name() {
-                Some(n) => n.to_string_lossy().to_string(),
-                None => {
-                    warn!(
-                        "Skipping entry without a file name in {}: {}",
-                        path,
-                        entry_path.display()
-                    );
-                    continue;
-                }
-            };
+impl PluginLibraries {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-            if !file_name.starts_with("libisabelle_plugin_") {
+    /// Refuse plugins whose manifest requires a capability not listed
+    /// here. By default every capability is assumed available.
+    pub fn with_capabilities<S: Into<String>>(
+        mut self,
+        capabilities: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.capabilities = Some(capabilities.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// [`PluginPool::load_plugins`], skipping libraries that are already
+    /// loaded. Calling it again picks up libraries added since.
+    pub fn load_plugins(&mut self, pool: &mut PluginPool, path: &str) -> PluginLoadResult {
+        let mut result = PluginLoadResult::default();
+
+        for full in find_plugin_libraries(path, "libisabelle_plugin_", &mut result) {
+            if self.is_loaded(&full) {
+                info!("Plugin library {} is already loaded, skipping", full);
+                result.considered -= 1;
                 continue;
             }
+            self.load_file(pool, &full, false, &mut result);
+        }
 
-            result.considered += 1;
-            info!("Found plugin candidate: {}", file_name);
-
-            let full = match entry_path.canonicalize() {
-                Ok(p) => p.to_string_lossy().to_string(),
-                Err(e) => {
-                    let path_str = entry_path.to_string_lossy().to_string();
-                    let msg =
-                        format!("Failed to canonicalize plugin path {}: {}", path_str, e);
-                    error!("{}", msg);
-                    result.failures.push(PluginLoadFailure {
-                        path: path_str,
-                        error: msg,
-                    });
-                    continue;
-                }
-            };
+        log_load_result(path, &result);
+        result
+    }
+
+    /// Load the single plugin library at `path` into `pool`. A library that
+    /// is already loaded is refused; use [`PluginLibraries::reload_library`]
+    /// to replace it.
+    ///
+    /// The file is loaded through a private copy, so this always picks up
+    /// its current contents even if an earlier version is still mapped.
+    pub fn load_library(&mut self, pool: &mut PluginPool, path: &str) -> PluginLoadResult {
+        let mut result = PluginLoadResult {
+            considered: 1,
+            ..Default::default()
+        };
+        let full = canonical_plugin_path(path);
+        if self.is_loaded(&full) {
+            let msg = format!("Plugin library {} is already loaded", full);
+            error!("{}", msg);
+            result.failures.push(PluginLoadFailure {
+                path: full,
+                error: msg,
+            });
+            return result;
+        }
+
+        self.load_file(pool, &full, true, &mut result);
+        log_load_result(path, &result);
+        result
+    }
+
+    fn is_loaded(&self, full: &str) -> bool {
+        self.libraries.iter().any(|l| l.path == full)
+    }
+
+    fn load_file(
+        &mut self,
+        pool: &mut PluginPool,
+        full: &str,
+        private_copy: bool,
+        result: &mut PluginLoadResult,
+    ) {
+        let available: Option<Vec<&str>> = self
+            .capabilities
+            .as_ref()
+            .map(|c| c.iter().map(String::as_str).collect());
+        if let Some(keys) =
+            load_pool_library(pool, full, private_copy, available.as_deref(), result)
+        {
+            self.libraries.push(PoolLibrary {
+                path: full.to_string(),
+                plugins: keys,
+            });
+        }
+    }
+
+    /// Remove every plugin registered by the library at `path` from `pool`
+    /// and release the library. Returns the number of plugins removed. The
+    /// platform may keep its code mapped (see
+    /// [`PluginLibraries::load_library`]).
+    ///
+    /// Hooks run synchronously on `&mut PluginPool`, so no call into the
+    /// library can be in flight here.
+    pub fn unload_library(&mut self, pool: &mut PluginPool, path: &str) -> usize {
+        let full = canonical_plugin_path(path);
+        let Some(pos) = self.libraries.iter().position(|l| l.path == full) else {
+            warn!("Plugin library {} is not loaded", full);
+            return 0;
+        };
+        let lib = self.libraries.remove(pos);
+        let before = pool.plugins.len();
+        pool.plugins
+            .retain(|p| !lib.plugins.contains(&plugin_key(p.as_ref())));
+        let removed = before - pool.plugins.len();
+        info!("Unloaded {} plugin(s) of {}", removed, full);
+        removed
+    }
+
+    /// Unload the library at `path` (if loaded) and load the file found
+    /// there now. Replace plugin files by renaming a new file over the old
+    /// one rather than rewriting it in place.
+    pub fn reload_library(&mut self, pool: &mut PluginPool, path: &str) -> PluginLoadResult {
+        self.unload_library(pool, path);
+        self.load_library(pool, path)
+    }
 
-            info!("Loading library {}", full);
-            unsafe {
-                match Library::new(&full) {
-                    Ok(l) => {
-                        let lib = Box::leak(Box::new(l));
-                        match lib
-                            .get::<Symbol<unsafe extern "C" fn(&mut dyn PluginPoolApi) -> ()>>(
-                                b"register",
-                            ) {
-                            Ok(func) => {
-                                func(self);
-                                result.loaded += 1;
-                                info!("Plugin registered from {}", full);
-                            }
-                            Err(e) => {
-                                let msg = format!(
-                                    "Plugin {} is missing the `register` symbol: {}",
-                                    full, e
-                                );
-                                error!("{}", msg);
-                                result.failures.push(PluginLoadFailure {
-                                    path: full.clone(),
-                                    error: msg,
-                                });
-                            }
-                        };
-                    }
-                    Err(e) => {
-                        let msg = format!("Failed to load plugin library {}: {}", full, e);
-                        error!("{}", msg);
-                        result.failures.push(PluginLoadFailure {
-                            path: full.clone(),
-                            error: msg,
-                        });
-                    }
-                }
+    /// Apply a change reported by a [`PluginDirWatcher`].
+    pub fn apply_change(
+        &mut self,
+        pool: &mut PluginPool,
+        change: &PluginFileChange,
+    ) -> PluginLoadResult {
+        match change {
+            PluginFileChange::Added(path) => self.load_library(pool, path),
+            PluginFileChange::Modified(path) => self.reload_library(pool, path),
+            PluginFileChange::Removed(path) => {
+                self.unload_library(pool, path);
+                PluginLoadResult::default()
             }
         }
+    }
+
+    /// Paths of the libraries currently loaded, in load order.
+    pub fn libraries(&self) -> impl Iterator<Item = &str> {
+        self.libraries.iter().map(|l| l.path.as_str())
+    }
+}
+
+impl Default for PluginPool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-        info!(
-            "Plugin loading from {} finished: {} candidate(s), {} loaded, {} failed",
-            path,
-            result.considered,
-            result.loaded,
-            result.failed()
-        );
-        if !result.is_ok() {
-            warn!(
-                "{} plugin(s) failed to load from {}",
-                result.failed(),
-                path
-            );
+/// Change to a plugin file observed by [`PluginDirWatcher::poll`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PluginFileChange {
+    Added(String),
+    Modified(String),
+    Removed(String),
+}
+
+/// Polls a plugin directory for added, replaced and removed libraries.
+/// Core calls [`PluginDirWatcher::poll`] periodically and hands the changes
+/// to `PluginLibraries::apply_change` or `PluginRegistry::apply_change`.
+pub struct PluginDirWatcher {
+    path: String,
+    prefix: String,
+    seen: HashMap<String, SystemTime>,
+}
+
+impl PluginDirWatcher {
+    /// Watch files in `path` whose name starts with `prefix`. Files present
+    /// now are assumed to be loaded already and are not reported.
+    pub fn new(path: impl Into<String>, prefix: impl Into<String>) -> Self {
+        let mut watcher = Self {
+            path: path.into(),
+            prefix: prefix.into(),
+            seen: HashMap::new(),
+        };
+        watcher.seen = watcher.scan().unwrap_or_default();
+        watcher
+    }
+
+    fn scan(&self) -> Option<HashMap<String, SystemTime>> {
+        let entries = match fs::read_dir(&self.path) {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("Failed to read plugin directory {}: {}", self.path, e);
+                return None;
+            }
+        };
+        let mut files = HashMap::new();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let matches = path
+                .file_name()
+                .is_some_and(|n| n.to_string_lossy().starts_with(&self.prefix));
+            if !matches {
+                continue;
+            }
+            let Ok(modified) = entry.metadata().and_then(|m| m.modified()) else {
+                continue;
+            };
+            let full = canonical_plugin_path(&path.to_string_lossy());
+            files.insert(full, modified);
         }
-        result
+        Some(files)
     }
 
-    pub fn ping_plugins(&mut self) {
-        for plugin in &mut self.plugins {
-            plugin.ping_test();
+    /// Changes since the previous call: removals first, then replacements,
+    /// then additions, each sorted by path. An unreadable directory yields
+    /// no changes.
+    pub fn poll(&mut self) -> Vec<PluginFileChange> {
+        let Some(current) = self.scan() else {
+            return Vec::new();
+        };
+
+        let mut removed: Vec<_> = self
+            .seen
+            .keys()
+            .filter(|p| !current.contains_key(*p))
+            .cloned()
+            .collect();
+        let mut modified = Vec::new();
+        let mut added = Vec::new();
+        for (path, time) in &current {
+            match self.seen.get(path) {
+                None => added.push(path.clone()),
+                Some(old) if old != time => modified.push(path.clone()),
+                Some(_) => {}
+            }
         }
+        removed.sort();
+        modified.sort();
+        added.sort();
+        self.seen = current;
+
+        removed
+            .into_iter()
+            .map(PluginFileChange::Removed)
+            .chain(modified.into_iter().map(PluginFileChange::Modified))
+            .chain(added.into_iter().map(PluginFileChange::Added))
+            .collect()
     }
 }
@@ -0,0 +1,256 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Typed builders for the `filter` and `sort_key` strings taken by
+//! `db_get_items`/`db_get_all_items`.
+//!
+//! ```ignore
+//! let filter = Filter::eq(Field::str("status"), "open")
+//!     .and(Filter::range(Field::u64("time"), since..))
+//!     .and(!Filter::regex(Field::str("title"), "^urgent"));
+//! let open = core.db_find_all_items("tasks", &Sort::by("time"), &filter).await;
+//! ```
+//!
+//! Filters render to the Mongo JSON documents core already accepts (see
+//! `PluginHookMessage::ItemListDbFilter`), with item fields addressed as
+//! stored: `strs.<key>`, `u64s.<key>`, `bools.<key>` and `_id`.
+
+use crate::actor::DENY_ALL_DB_FILTER;
+use serde_json::{json, Map, Value};
+use std::fmt;
+use std::ops::{Bound, Not, RangeBounds};
+
+/// An [`Item`](isabelle_dm::data_model::item::Item) field to filter on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Field {
+    Id,
+    Str(String),
+    U64(String),
+    Bool(String),
+}
+
+impl Field {
+    pub fn id() -> Self {
+        Field::Id
+    }
+
+    pub fn str(key: impl Into<String>) -> Self {
+        Field::Str(key.into())
+    }
+
+    pub fn u64(key: impl Into<String>) -> Self {
+        Field::U64(key.into())
+    }
+
+    pub fn bool(key: impl Into<String>) -> Self {
+        Field::Bool(key.into())
+    }
+
+    /// Document path of the field, e.g. "strs.name".
+    pub fn path(&self) -> String {
+        match self {
+            Field::Id => "_id".to_string(),
+            Field::Str(k) => format!("strs.{}", k),
+            Field::U64(k) => format!("u64s.{}", k),
+            Field::Bool(k) => format!("bools.{}", k),
+        }
+    }
+}
+
+/// A database filter. Combine with [`Filter::and`], [`Filter::or`] and
+/// `!`; `to_string()` gives the string `db_get_items` takes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Filter(Value);
+
+impl Filter {
+    /// Matches every item.
+    pub fn all() -> Self {
+        Filter(Value::Object(Map::new()))
+    }
+
+    /// Matches no item: [`DENY_ALL_DB_FILTER`], the filter core falls back
+    /// to when a plugin fails to answer.
+    pub fn none() -> Self {
+        Filter(serde_json::from_str(DENY_ALL_DB_FILTER).expect("DENY_ALL_DB_FILTER is valid JSON"))
+    }
+
+    fn op(field: Field, op: &str, value: Value) -> Self {
+        let mut cond = Map::new();
+        cond.insert(op.to_string(), value);
+        let mut doc = Map::new();
+        doc.insert(field.path(), Value::Object(cond));
+        Filter(Value::Object(doc))
+    }
+
+    pub fn eq(field: Field, value: impl Into<Value>) -> Self {
+        Self::op(field, "$eq", value.into())
+    }
+
+    pub fn ne(field: Field, value: impl Into<Value>) -> Self {
+        Self::op(field, "$ne", value.into())
+    }
+
+    /// `field` equals one of `values`.
+    pub fn is_in<V: Into<Value>>(field: Field, values: impl IntoIterator<Item = V>) -> Self {
+        Self::op(field, "$in", values.into_iter().map(Into::into).collect())
+    }
+
+    /// `field` equals none of `values`.
+    pub fn not_in<V: Into<Value>>(field: Field, values: impl IntoIterator<Item = V>) -> Self {
+        Self::op(field, "$nin", values.into_iter().map(Into::into).collect())
+    }
+
+    /// `field` lies within `range`, e.g. `10..20` or `"a".."n"`.
+    pub fn range<V: Into<Value> + Clone>(field: Field, range: impl RangeBounds<V>) -> Self {
+        let mut cond = Map::new();
+        match range.start_bound() {
+            Bound::Included(v) => {
+                cond.insert("$gte".to_string(), v.clone().into());
+            }
+            Bound::Excluded(v) => {
+                cond.insert("$gt".to_string(), v.clone().into());
+            }
+            Bound::Unbounded => {}
+        }
+        match range.end_bound() {
+            Bound::Included(v) => {
+                cond.insert("$lte".to_string(), v.clone().into());
+            }
+            Bound::Excluded(v) => {
+                cond.insert("$lt".to_string(), v.clone().into());
+            }
+            Bound::Unbounded => {}
+        }
+        if cond.is_empty() {
+            return Filter::all();
+        }
+        let mut doc = Map::new();
+        doc.insert(field.path(), Value::Object(cond));
+        Filter(Value::Object(doc))
+    }
+
+    /// `field` matches the regular expression `pattern`.
+    pub fn regex(field: Field, pattern: impl Into<String>) -> Self {
+        Self::op(field, "$regex", Value::String(pattern.into()))
+    }
+
+    /// Every filter in `filters` matches (all items if empty).
+    pub fn all_of(filters: impl IntoIterator<Item = Filter>) -> Self {
+        let clauses: Vec<Value> = filters
+            .into_iter()
+            .filter(|f| !f.is_all())
+            .flat_map(|f| f.into_clauses("$and"))
+            .collect();
+        match clauses.len() {
+            0 => Filter::all(),
+            1 => Filter(clauses.into_iter().next().unwrap()),
+            _ => Filter(json!({ "$and": clauses })),
+        }
+    }
+
+    /// Any filter in `filters` matches (no items if empty).
+    pub fn any_of(filters: impl IntoIterator<Item = Filter>) -> Self {
+        let clauses: Vec<Value> = filters
+            .into_iter()
+            .flat_map(|f| f.into_clauses("$or"))
+            .collect();
+        match clauses.len() {
+            0 => Filter::none(),
+            1 => Filter(clauses.into_iter().next().unwrap()),
+            _ => Filter(json!({ "$or": clauses })),
+        }
+    }
+
+    pub fn and(self, other: Filter) -> Self {
+        Filter::all_of([self, other])
+    }
+
+    pub fn or(self, other: Filter) -> Self {
+        Filter::any_of([self, other])
+    }
+
+    /// Wrap an already built filter document.
+    pub fn raw(doc: Value) -> Self {
+        Filter(doc)
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.0.as_object().is_some_and(|o| o.is_empty())
+    }
+
+    pub fn as_value(&self) -> &Value {
+        &self.0
+    }
+
+    /// Operands for a `$and`/`$or` (`op`) node, flattening nested ones.
+    fn into_clauses(self, op: &str) -> Vec<Value> {
+        match self.0 {
+            Value::Object(mut o) if o.len() == 1 && o.contains_key(op) => match o.remove(op) {
+                Some(Value::Array(clauses)) => clauses,
+                Some(other) => vec![other],
+                None => Vec::new(),
+            },
+            other => vec![other],
+        }
+    }
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Filter::all()
+    }
+}
+
+/// Items the filter does not match.
+impl Not for Filter {
+    type Output = Filter;
+
+    fn not(self) -> Filter {
+        Filter(json!({ "$nor": [self.0] }))
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Sort order for `db_get_items`: the item key to sort by, or none.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Sort(String);
+
+impl Sort {
+    /// Storage order.
+    pub fn none() -> Self {
+        Sort(String::new())
+    }
+
+    /// Ascending by the item key `key`.
+    pub fn by(key: impl Into<String>) -> Self {
+        Sort(key.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for Sort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
@@ -0,0 +1,187 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Per-request data handed to route hooks.
+//!
+//! Core builds a [`RequestContext`] from the incoming `actix_web` request
+//! (see [`RequestContext::from_http_request`]) and passes it to every
+//! route hook, actor or legacy:
+//!
+//! ```ignore
+//! PluginHookMessage::RouteUrl { ctx, reply } => {
+//!     let page = ctx.query_param("page").unwrap_or("1");
+//!     info!("[{}] {} {} from {:?}", ctx.request_id, ctx.method, ctx.path, ctx.remote_addr);
+//!     ...
+//! }
+//! ```
+
+use actix_web::HttpRequest;
+use isabelle_dm::data_model::item::Item;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header a client or proxy may use to pass its own correlation id.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// An HTTP request as seen by a route hook.
+#[derive(Debug, Clone, Default)]
+pub struct RequestContext {
+    /// Request handle, the same one item hooks get.
+    pub hndl: String,
+    /// Logged-in user, if any.
+    pub user: Option<Item>,
+    /// Upper-case HTTP method, e.g. "GET".
+    pub method: String,
+    /// Path without the query string, e.g. "/report".
+    pub path: String,
+    /// Raw query string without the leading '?'.
+    pub query_string: String,
+    /// Decoded query parameters, in order; names may repeat.
+    pub query_params: Vec<(String, String)>,
+    /// Parameters of the declared route the request matched, e.g. `id`
+    /// for `/invoice/{id}/pdf`. Set by the dispatcher.
+    pub path_params: Vec<(String, String)>,
+    /// Headers with lower-case names, in order; names may repeat.
+    pub headers: Vec<(String, String)>,
+    pub cookies: HashMap<String, String>,
+    /// Address of the connection's peer. Behind a proxy this is the
+    /// proxy; see the `x-forwarded-for` header.
+    pub remote_addr: Option<SocketAddr>,
+    /// Correlation id for logs: the client's `x-request-id` if it sent
+    /// one, otherwise one made up by [`RequestContext::new`].
+    pub request_id: String,
+}
+
+impl RequestContext {
+    /// Context for `method` on `url` (path plus optional query string)
+    /// with no headers, cookies or peer address.
+    pub fn new(hndl: &str, user: &Option<Item>, method: &str, url: &str) -> Self {
+        let (path, query_string) = url.split_once('?').unwrap_or((url, ""));
+        Self {
+            hndl: hndl.to_string(),
+            user: user.clone(),
+            method: method.to_ascii_uppercase(),
+            path: path.to_string(),
+            query_string: query_string.to_string(),
+            query_params: parse_query(query_string),
+            path_params: Vec::new(),
+            headers: Vec::new(),
+            cookies: HashMap::new(),
+            remote_addr: None,
+            request_id: next_request_id(),
+        }
+    }
+
+    /// Context for an `actix_web` request.
+    pub fn from_http_request(hndl: &str, user: &Option<Item>, req: &HttpRequest) -> Self {
+        let url = req
+            .uri()
+            .path_and_query()
+            .map_or(req.path(), |pq| pq.as_str());
+        let mut ctx = Self::new(hndl, user, req.method().as_str(), url);
+        for (name, value) in req.headers() {
+            if let Ok(value) = value.to_str() {
+                ctx = ctx.with_header(name.as_str(), value);
+            }
+        }
+        if let Ok(cookies) = req.cookies() {
+            for cookie in cookies.iter() {
+                ctx.cookies
+                    .insert(cookie.name().to_string(), cookie.value().to_string());
+            }
+        }
+        ctx.remote_addr = req.peer_addr();
+        ctx
+    }
+
+    /// Add a header; an `x-request-id` header also becomes the request id.
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        let name = name.to_ascii_lowercase();
+        if name == REQUEST_ID_HEADER && !value.is_empty() {
+            self.request_id = value.to_string();
+        }
+        self.headers.push((name, value.to_string()));
+        self
+    }
+
+    pub fn with_cookie(mut self, name: &str, value: &str) -> Self {
+        self.cookies.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn with_remote_addr(mut self, addr: SocketAddr) -> Self {
+        self.remote_addr = Some(addr);
+        self
+    }
+
+    /// Path plus query string, i.e. what route hooks used to get as
+    /// `query`.
+    pub fn url(&self) -> String {
+        if self.query_string.is_empty() {
+            self.path.clone()
+        } else {
+            format!("{}?{}", self.path, self.query_string)
+        }
+    }
+
+    /// Path parameter `name` of the matched route.
+    pub fn path_param(&self, name: &str) -> Option<&str> {
+        self.path_params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// First value of query parameter `name`.
+    pub fn query_param(&self, name: &str) -> Option<&str> {
+        self.query_params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// First value of header `name` (case-insensitive).
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        self.cookies.get(name).map(String::as_str)
+    }
+}
+
+fn parse_query(query_string: &str) -> Vec<(String, String)> {
+    serde_urlencoded::from_str(query_string).unwrap_or_default()
+}
+
+/// Process-unique id: the time of the first call plus a counter.
+fn next_request_id() -> String {
+    static START: OnceLock<u128> = OnceLock::new();
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    let start = START.get_or_init(|| {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis())
+            .unwrap_or(0)
+    });
+    format!("{:x}-{:x}", start, NEXT.fetch_add(1, Ordering::Relaxed))
+}
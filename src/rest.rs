@@ -0,0 +1,248 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Typed JSON handlers for `RouteRest`.
+//!
+//! Instead of parsing `payload` by hand, a plugin registers handlers
+//! taking a `Deserialize` request and returning a `Serialize` response or
+//! an [`ApiError`]. Malformed bodies get a 400 and errors a JSON envelope,
+//! the same for every plugin:
+//!
+//! ```ignore
+//! let router = Arc::new(
+//!     RestRouter::new()
+//!         .route(RouteSpec::post("/invoice"), create_invoice)
+//!         .route_with_context(RouteSpec::get("/invoice/{id}"), |ctx, (): ()| async move {
+//!             let id: u64 = ctx.path_param("id").unwrap_or("").parse()
+//!                 .map_err(|_| ApiError::bad_request("invalid id"))?;
+//!             load_invoice(id).await.ok_or_else(|| ApiError::not_found("no such invoice"))
+//!         }),
+//! );
+//! registry.add_with_manifest(PluginManifest::new("billing", "1.0.0").routes(router.specs()), tx);
+//!
+//! PluginHookMessage::RouteRest { ctx, payload, reply } => {
+//!     let _ = reply.send(router.handle(&ctx, &payload).await);
+//! }
+//! ```
+//!
+//! Legacy plugins, whose `route_rest_hook` is synchronous, use
+//! [`handle_json`].
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::api::{CustomResponse, WebResponse};
+use crate::request::RequestContext;
+use crate::route::{match_routes, RouteMatch, RouteSpec};
+
+/// Error returned by a typed handler. Sent as
+/// `{"error": code, "message": message, "details": details}` with
+/// `status`; `details` is left out when `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: u16,
+    /// Machine-readable code, e.g. "not_found".
+    pub code: String,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(status: u16, code: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: code.into(),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(400, "bad_request", message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(401, "unauthorized", message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(403, "forbidden", message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(404, "not_found", message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(409, "conflict", message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(500, "internal", message)
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.code, self.status, self.message)
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+impl From<ApiError> for WebResponse {
+    fn from(err: ApiError) -> Self {
+        let mut body = json!({ "error": err.code, "message": err.message });
+        if let Some(details) = err.details {
+            body["details"] = details;
+        }
+        CustomResponse::new(err.status)
+            .content_type("application/json")
+            .body(body.to_string())
+            .into()
+    }
+}
+
+/// Parse `payload` as `Req`; an empty payload parses as JSON `null`, so
+/// bodiless requests work with `()` and `Option<_>`.
+pub fn parse_body<Req: DeserializeOwned>(payload: &str) -> Result<Req, ApiError> {
+    let payload = match payload.trim() {
+        "" => "null",
+        p => p,
+    };
+    serde_json::from_str(payload)
+        .map_err(|e| ApiError::new(400, "invalid_body", format!("invalid request body: {}", e)))
+}
+
+/// JSON response for a handler's result.
+pub fn json_response<Resp: Serialize>(result: Result<Resp, ApiError>) -> WebResponse {
+    match result
+        .and_then(|resp| serde_json::to_value(resp).map_err(|e| ApiError::internal(e.to_string())))
+    {
+        Ok(value) => WebResponse::Json(value),
+        Err(err) => err.into(),
+    }
+}
+
+/// Run a synchronous typed handler on a raw `payload`.
+pub fn handle_json<Req, Resp>(
+    payload: &str,
+    handler: impl FnOnce(Req) -> Result<Resp, ApiError>,
+) -> WebResponse
+where
+    Req: DeserializeOwned,
+    Resp: Serialize,
+{
+    json_response(parse_body(payload).and_then(handler))
+}
+
+type BoxedResponse = Pin<Box<dyn Future<Output = WebResponse> + Send>>;
+type Handler = Box<dyn Fn(RequestContext, &str) -> BoxedResponse + Send + Sync>;
+
+/// Typed handlers keyed by [`RouteSpec`], for actor plugins.
+#[derive(Default)]
+pub struct RestRouter {
+    routes: Vec<(RouteSpec, Handler)>,
+}
+
+impl RestRouter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a handler for requests matching `spec`.
+    ///
+    /// Panics if `spec` is unprotected, as `RouteRest` only carries
+    /// authenticated requests, or restricted to a role, which the router
+    /// can't check; check roles in the handler with
+    /// [`CoreHandle::auth_check_role`] instead.
+    ///
+    /// [`CoreHandle::auth_check_role`]: crate::actor::CoreHandle::auth_check_role
+    pub fn route<Req, Resp, F, Fut>(self, spec: RouteSpec, handler: F) -> Self
+    where
+        Req: DeserializeOwned + 'static,
+        Resp: Serialize + 'static,
+        F: Fn(Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, ApiError>> + Send + 'static,
+    {
+        self.route_with_context(spec, move |_, req| handler(req))
+    }
+
+    /// Like [`RestRouter::route`], for handlers that also need the request,
+    /// e.g. for its path parameters or user. Panics on the same specs.
+    pub fn route_with_context<Req, Resp, F, Fut>(mut self, spec: RouteSpec, handler: F) -> Self
+    where
+        Req: DeserializeOwned + 'static,
+        Resp: Serialize + 'static,
+        F: Fn(RequestContext, Req) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<Resp, ApiError>> + Send + 'static,
+    {
+        assert!(
+            spec.protected,
+            "REST route {} {} must be protected",
+            spec.method,
+            spec.pattern.as_str()
+        );
+        assert!(
+            spec.role.is_none(),
+            "REST route {} {} can't be restricted to a role",
+            spec.method,
+            spec.pattern.as_str()
+        );
+        let handler: Handler = Box::new(move |ctx, payload| match parse_body::<Req>(payload) {
+            Ok(req) => {
+                let fut = handler(ctx, req);
+                Box::pin(async move { json_response(fut.await) })
+            }
+            Err(err) => {
+                let resp = WebResponse::from(err);
+                Box::pin(async move { resp })
+            }
+        });
+        self.routes.push((spec, handler));
+        self
+    }
+
+    /// Routes to declare in the plugin's manifest.
+    pub fn specs(&self) -> Vec<RouteSpec> {
+        self.routes.iter().map(|(spec, _)| spec.clone()).collect()
+    }
+
+    /// Answer a `RouteRest` request. The most specific matching route
+    /// wins, the first added among equally specific ones, as in the
+    /// dispatcher; a path routed only for other methods gets 405, and one
+    /// not routed at all `WebResponse::NotImplemented`.
+    pub async fn handle(&self, ctx: &RequestContext, payload: &str) -> WebResponse {
+        let routes = self.routes.iter().map(|(spec, handler)| (spec, handler));
+        let (_, handler, params) = match match_routes(routes, &ctx.method, &ctx.path) {
+            RouteMatch::Unrouted => return WebResponse::NotImplemented,
+            RouteMatch::MethodNotAllowed(resp) => return resp,
+            RouteMatch::Matched(mut matched) => matched.remove(0),
+        };
+        let mut ctx = ctx.clone();
+        ctx.path_params = params;
+        handler(ctx, payload).await
+    }
+}
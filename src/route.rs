@@ -0,0 +1,226 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Routes a plugin declares in its [`PluginManifest`].
+//!
+//! Once a plugin declares routes, the [`HookDispatcher`] only sends it
+//! requests matching one of them, with the pattern's parameters in
+//! `RequestContext::path_params`. A path no declared route matches is
+//! offered to plugins that declare no routes, as before; a declared path
+//! requested with another method gets 405.
+//!
+//! ```ignore
+//! PluginManifest::new("billing", "1.0.0")
+//!     .route(RouteSpec::get("/invoice/{id}/pdf").role("accountant"))
+//!     .route(RouteSpec::post("/webhook/{provider}").unprotected())
+//!     .route(RouteSpec::get("/files/{*path}"));
+//! ```
+//!
+//! [`PluginManifest`]: crate::manifest::PluginManifest
+//! [`HookDispatcher`]: crate::actor::HookDispatcher
+
+use percent_encoding::percent_decode_str;
+use std::cmp::Reverse;
+
+use crate::api::{CustomResponse, WebResponse};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Param(String),
+    Rest(String),
+}
+
+/// A path pattern: `/`-separated literal segments, `{name}` for a single
+/// segment and a trailing `{*name}` for the rest of the path. Empty
+/// segments are ignored, so `/a/` matches `/a`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RoutePattern {
+    pattern: String,
+    segments: Vec<Segment>,
+}
+
+impl RoutePattern {
+    pub fn new(pattern: &str) -> Self {
+        let segments = segments(pattern)
+            .map(
+                |s| match s.strip_prefix('{').and_then(|s| s.strip_suffix('}')) {
+                    Some(name) => match name.strip_prefix('*') {
+                        Some(name) => Segment::Rest(name.to_string()),
+                        None => Segment::Param(name.to_string()),
+                    },
+                    None => Segment::Literal(s.to_string()),
+                },
+            )
+            .collect();
+        Self {
+            pattern: pattern.to_string(),
+            segments,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Percent-decoded parameters if `path` matches, in pattern order.
+    pub fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let mut parts: Vec<&str> = segments(path).collect();
+        let mut params = Vec::new();
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                Segment::Rest(name) => {
+                    let rest = parts.split_off(i.min(parts.len())).join("/");
+                    params.push((name.clone(), decode(&rest)));
+                    return Some(params);
+                }
+                Segment::Literal(literal) => {
+                    if parts.get(i) != Some(&literal.as_str()) {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => params.push((name.clone(), decode(parts.get(i)?))),
+            }
+        }
+        (parts.len() == self.segments.len()).then_some(params)
+    }
+
+    /// Orders patterns matching the same path: at the first segment where
+    /// they differ, a literal beats `{name}`, which beats `{*name}`.
+    pub(crate) fn specificity(&self) -> Vec<u8> {
+        self.segments
+            .iter()
+            .map(|s| match s {
+                Segment::Literal(_) => 2,
+                Segment::Param(_) => 1,
+                Segment::Rest(_) => 0,
+            })
+            .collect()
+    }
+}
+
+fn segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|s| !s.is_empty())
+}
+
+fn decode(s: &str) -> String {
+    percent_decode_str(s).decode_utf8_lossy().into_owned()
+}
+
+/// One route served by a plugin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RouteSpec {
+    /// Upper-case HTTP method, or `*` for any.
+    pub method: String,
+    pub pattern: RoutePattern,
+    /// Served through the authenticated route hooks (`RouteUrl`,
+    /// `RouteUrlPost`, `RouteRest`) rather than the unprotected ones.
+    pub protected: bool,
+    /// Role the user must have; checked before the plugin is asked.
+    pub role: Option<String>,
+}
+
+impl RouteSpec {
+    /// Protected route for `method` on `pattern`, open to any user.
+    pub fn new(method: &str, pattern: &str) -> Self {
+        Self {
+            method: method.to_ascii_uppercase(),
+            pattern: RoutePattern::new(pattern),
+            protected: true,
+            role: None,
+        }
+    }
+
+    pub fn get(pattern: &str) -> Self {
+        Self::new("GET", pattern)
+    }
+
+    pub fn post(pattern: &str) -> Self {
+        Self::new("POST", pattern)
+    }
+
+    pub fn put(pattern: &str) -> Self {
+        Self::new("PUT", pattern)
+    }
+
+    pub fn delete(pattern: &str) -> Self {
+        Self::new("DELETE", pattern)
+    }
+
+    /// Route for every method.
+    pub fn any(pattern: &str) -> Self {
+        Self::new("*", pattern)
+    }
+
+    pub fn unprotected(mut self) -> Self {
+        self.protected = false;
+        self
+    }
+
+    pub fn role(mut self, role: impl Into<String>) -> Self {
+        self.role = Some(role.into());
+        self
+    }
+
+    pub fn allows(&self, method: &str) -> bool {
+        self.method == "*" || self.method.eq_ignore_ascii_case(method)
+    }
+}
+
+/// 405 listing the methods declared for the requested path.
+fn method_not_allowed(allowed: &[&str]) -> WebResponse {
+    CustomResponse::new(405)
+        .header("Allow", &allowed.join(", "))
+        .into()
+}
+
+/// Outcome of [`match_routes`].
+pub(crate) enum RouteMatch<'r, T> {
+    /// No route matches the path.
+    Unrouted,
+    /// Routes match the path, but none for the method; the 405 to send.
+    MethodNotAllowed(WebResponse),
+    /// Routes matching path and method with their path parameters, most
+    /// specific first and in the given order among equally specific ones.
+    /// Never empty.
+    Matched(Vec<(&'r RouteSpec, T, Vec<(String, String)>)>),
+}
+
+/// Match a request against `routes`, each carrying what serves it. Shared
+/// by the dispatcher and `RestRouter` so both pick the same route.
+pub(crate) fn match_routes<'r, T>(
+    routes: impl IntoIterator<Item = (&'r RouteSpec, T)>,
+    method: &str,
+    path: &str,
+) -> RouteMatch<'r, T> {
+    let mut matched: Vec<_> = routes
+        .into_iter()
+        .filter_map(|(route, target)| Some((route, target, route.pattern.matches(path)?)))
+        .collect();
+    if matched.is_empty() {
+        return RouteMatch::Unrouted;
+    }
+    if !matched.iter().any(|(route, _, _)| route.allows(method)) {
+        let mut allowed: Vec<&str> = matched.iter().map(|(r, _, _)| r.method.as_str()).collect();
+        allowed.sort_unstable();
+        allowed.dedup();
+        return RouteMatch::MethodNotAllowed(method_not_allowed(&allowed));
+    }
+    matched.retain(|(route, _, _)| route.allows(method));
+    // Stable, so declaration order breaks ties.
+    matched.sort_by_cached_key(|(route, _, _)| Reverse(route.pattern.specificity()));
+    RouteMatch::Matched(matched)
+}
@@ -0,0 +1,1303 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! In-memory stand-in for core, for unit-testing actor plugins. Enabled by
+//! the `testing` feature.
+//!
+//! ```ignore
+//! let core = MockCore::new();
+//! core.insert("users", user);
+//! core.grant_role(user.id, "admin");
+//!
+//! let handle = core.start();
+//! my_plugin_logic(&handle).await;
+//!
+//! core.assert_called("DbSetItem");
+//! assert_eq!(core.sent_emails()[0].to, "admin@example.com");
+//! ```
+//!
+//! [`PluginHarness`] goes one step further: it registers the plugin
+//! against a `MockCore` and drives its hooks directly.
+//!
+//! ```ignore
+//! let harness = PluginHarness::new(my_plugin::register).as_user(admin);
+//! assert!(harness.auth("users", 5).await);
+//! assert!(matches!(harness.get("/report").await, WebResponse::OkData(_)));
+//! ```
+//!
+//! Filters are the JSON documents core passes to its database, as built by
+//! [`crate::query::Filter`]: fields are `strs.<key>`, `u64s.<key>`,
+//! `bools.<key>` or a bare key looked up in all three (`_id`/`id` match
+//! the item id), with plain equality, `$eq`, `$ne`, `$in`, `$nin`, `$gt`,
+//! `$gte`, `$lt`, `$lte`, `$regex`, `$not`, `$and`, `$or` and `$nor`. An
+//! empty filter matches everything; a filter that doesn't parse matches
+//! nothing. Sorting is ascending by the `sort_key` field (`u64s`
+//! before `strs`, items lacking it last), then by id.
+//!
+//! Every write bumps the item's revision (`u64s["_rev"]`, see
+//! [`crate::actor::REVISION_KEY`]), starting at 1, so `DbSetItemIf`
+//! behaves as it does against core. `DbBatch` ops are applied in order;
+//! if one fails, the collections are restored to what they were before
+//! the batch. `DbSubscribe` feeds see every write, including
+//! [`MockCore::insert`]; a rolled back batch reports nothing.
+
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::list_result::ListResult;
+use isabelle_dm::data_model::process_result::ProcessResult;
+use log::warn;
+use regex_lite::Regex;
+use serde_json::Value;
+use std::cmp::Ordering;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::timeout;
+
+use crate::actor::{
+    item_revision, BatchError, BatchOp, ChangeEvent, ChangeKind, CollectionReadReply,
+    ConditionalWriteError, CoreHandle, CoreMessage, HookDispatcher, HookTimeouts,
+    PluginHookMessage, PluginRegistry, PreEditReply, REVISION_KEY,
+};
+use crate::api::WebResponse;
+use crate::live::{SseSession, WebSocketSession};
+use crate::request::RequestContext;
+use crate::upload::UploadPart;
+
+/// One-time password handed out by `AuthGenOtp`.
+pub const MOCK_OTP: &str = "000000";
+
+/// An email captured from `SendEmail`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentEmail {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+/// A [`CoreMessage`] received by the mock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedCall {
+    /// Name of the `CoreMessage` variant, e.g. "DbSetItem".
+    pub name: &'static str,
+    /// Main arguments, e.g. "users/5" for an item or the login for auth.
+    pub detail: String,
+}
+
+#[derive(Default)]
+struct MockState {
+    collections: HashMap<String, BTreeMap<u64, Item>>,
+    next_id: u64,
+    public_url: String,
+    data_path: String,
+    settings: Item,
+    secrets: BTreeMap<u64, Item>,
+    /// login -> password
+    users: HashMap<String, String>,
+    /// user id -> roles
+    roles: HashMap<u64, HashSet<String>>,
+    salts: u64,
+    outbox: Vec<SentEmail>,
+    calls: Vec<RecordedCall>,
+    subscribers: Vec<Subscriber>,
+    /// Writes of the message being handled, published once it is done.
+    changes: Vec<ChangeEvent>,
+}
+
+/// A `DbSubscribe` feed.
+struct Subscriber {
+    collection: String,
+    filter: Value,
+    events: mpsc::Sender<ChangeEvent>,
+}
+
+/// In-memory core. Cheap to clone; clones share state, so tests keep one
+/// to seed data and inspect the outcome while a [`CoreHandle`] from
+/// [`MockCore::start`] is used by the code under test.
+#[derive(Clone, Default)]
+pub struct MockCore {
+    state: Arc<Mutex<MockState>>,
+}
+
+fn process_result(succeeded: bool, error: &str) -> ProcessResult {
+    ProcessResult {
+        succeeded,
+        error: error.to_string(),
+        data: HashMap::new(),
+    }
+}
+
+impl MockCore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Serve [`CoreMessage`]s on a spawned task and return a handle to send
+    /// them. Call from within the tokio runtime.
+    pub fn start(&self) -> CoreHandle {
+        let (tx, mut rx) = mpsc::channel(64);
+        let core = self.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                core.handle(msg);
+            }
+        });
+        CoreHandle::new(tx)
+    }
+
+    /// Record and answer a single message. For tests running their own
+    /// receive loop.
+    pub fn handle(&self, msg: CoreMessage) {
+        let mut st = self.state();
+        match msg {
+            CoreMessage::DbGetAllItems {
+                collection,
+                sort_key,
+                filter,
+                reply,
+            } => {
+                st.record("DbGetAllItems", collection.clone());
+                let _ =
+                    reply.send(st.query(&collection, 0, u64::MAX, &sort_key, &filter, 0, u64::MAX));
+            }
+            CoreMessage::DbGetItems {
+                collection,
+                id_min,
+                id_max,
+                sort_key,
+                filter,
+                skip,
+                limit,
+                reply,
+            } => {
+                st.record("DbGetItems", collection.clone());
+                let _ = reply.send(st.query(
+                    &collection,
+                    id_min,
+                    id_max,
+                    &sort_key,
+                    &filter,
+                    skip,
+                    limit,
+                ));
+            }
+            CoreMessage::DbGetItem {
+                collection,
+                id,
+                reply,
+            } => {
+                st.record("DbGetItem", format!("{}/{}", collection, id));
+                let item = st
+                    .collections
+                    .get(&collection)
+                    .and_then(|c| c.get(&id))
+                    .cloned();
+                let _ = reply.send(item);
+            }
+            CoreMessage::DbSetItem {
+                collection,
+                item,
+                merge,
+                reply,
+            } => {
+                let id = st.set_item(&collection, item, merge);
+                st.record("DbSetItem", format!("{}/{}", collection, id));
+                let _ = reply.send(id);
+            }
+            CoreMessage::DbSetItemIf {
+                collection,
+                item,
+                merge,
+                expected_revision,
+                reply,
+            } => {
+                st.record("DbSetItemIf", format!("{}/{}", collection, item.id));
+                let _ = reply.send(st.set_item_if(&collection, item, merge, expected_revision));
+            }
+            CoreMessage::DbDelItem {
+                collection,
+                id,
+                reply,
+            } => {
+                st.record("DbDelItem", format!("{}/{}", collection, id));
+                let _ = reply.send(st.del_item(&collection, id));
+            }
+            CoreMessage::DbBatch { ops, reply } => {
+                let collections: Vec<&str> = ops.iter().map(BatchOp::collection).collect();
+                st.record("DbBatch", collections.join(", "));
+                let _ = reply.send(st.batch(ops));
+            }
+            CoreMessage::DbSubscribe {
+                collection,
+                filter,
+                events,
+                reply,
+            } => {
+                st.record("DbSubscribe", collection.clone());
+                let _ = reply.send(match parse_filter(&filter) {
+                    Ok(filter) => {
+                        st.subscribers.push(Subscriber {
+                            collection,
+                            filter,
+                            events,
+                        });
+                        Ok(())
+                    }
+                    Err(e) => Err(format!("invalid filter: {}", e)),
+                });
+            }
+            CoreMessage::GlobalsGetPublicUrl { reply } => {
+                st.record("GlobalsGetPublicUrl", String::new());
+                let _ = reply.send(st.public_url.clone());
+            }
+            CoreMessage::GlobalsGetDataPath { reply } => {
+                st.record("GlobalsGetDataPath", String::new());
+                let _ = reply.send(st.data_path.clone());
+            }
+            CoreMessage::GlobalsGetSettings { reply } => {
+                st.record("GlobalsGetSettings", String::new());
+                let _ = reply.send(st.settings.clone());
+            }
+            CoreMessage::GlobalsSetSettings { item } => {
+                st.record("GlobalsSetSettings", String::new());
+                st.settings = item;
+            }
+            CoreMessage::AuthCheckRole { item, role, reply } => {
+                let allowed = item
+                    .as_ref()
+                    .is_some_and(|user| st.has_role(user.id, &role));
+                st.record("AuthCheckRole", role);
+                let _ = reply.send(allowed);
+            }
+            CoreMessage::AuthGetNewSalt { reply } => {
+                st.record("AuthGetNewSalt", String::new());
+                st.salts += 1;
+                let _ = reply.send(format!("salt{}", st.salts));
+            }
+            CoreMessage::AuthGetPasswordHash {
+                password,
+                salt,
+                reply,
+            } => {
+                st.record("AuthGetPasswordHash", String::new());
+                let _ = reply.send(format!("hash:{}:{}", salt, password));
+            }
+            CoreMessage::AuthVerifyPassword {
+                password,
+                hash,
+                reply,
+            } => {
+                st.record("AuthVerifyPassword", String::new());
+                let matches = hash
+                    .strip_prefix("hash:")
+                    .and_then(|rest| rest.split_once(':'))
+                    .is_some_and(|(_, pw)| pw == password);
+                let _ = reply.send(matches);
+            }
+            CoreMessage::AuthLogin {
+                login,
+                password,
+                reply,
+            } => {
+                let ok = st.users.get(&login) == Some(&password);
+                st.record("AuthLogin", login);
+                let _ = reply.send(if ok {
+                    process_result(true, "")
+                } else {
+                    process_result(false, "invalid credentials")
+                });
+            }
+            CoreMessage::AuthLogout { login, reply } => {
+                let known = st.users.contains_key(&login);
+                st.record("AuthLogout", login);
+                let _ = reply.send(if known {
+                    process_result(true, "")
+                } else {
+                    process_result(false, "unknown user")
+                });
+            }
+            CoreMessage::AuthRegister {
+                login,
+                email,
+                reply,
+            } => {
+                st.record("AuthRegister", format!("{} <{}>", login, email));
+                let res = match st.users.entry(login) {
+                    Entry::Occupied(_) => process_result(false, "user already exists"),
+                    Entry::Vacant(e) => {
+                        e.insert(String::new());
+                        process_result(true, "")
+                    }
+                };
+                let _ = reply.send(res);
+            }
+            CoreMessage::AuthGenOtp { login, reply } => {
+                let known = st.users.contains_key(&login);
+                st.record("AuthGenOtp", login);
+                let res = if known {
+                    let mut res = process_result(true, "");
+                    res.data.insert("otp".to_string(), MOCK_OTP.to_string());
+                    res
+                } else {
+                    process_result(false, "unknown user")
+                };
+                let _ = reply.send(res);
+            }
+            CoreMessage::SendEmail { to, subject, body } => {
+                st.record("SendEmail", to.clone());
+                st.outbox.push(SentEmail { to, subject, body });
+            }
+            CoreMessage::InitGoogle { reply } => {
+                st.record("InitGoogle", String::new());
+                let _ = reply.send(String::new());
+            }
+            CoreMessage::SyncWithGoogle { name, .. } => {
+                st.record("SyncWithGoogle", name);
+            }
+            CoreMessage::SecretGet { id, reply } => {
+                st.record("SecretGet", id.to_string());
+                let _ = reply.send(st.secrets.get(&id).cloned());
+            }
+            CoreMessage::SecretGetByName { name, reply } => {
+                let secret = st
+                    .secrets
+                    .values()
+                    .find(|s| s.strs.get("name") == Some(&name))
+                    .cloned();
+                st.record("SecretGetByName", name);
+                let _ = reply.send(secret);
+            }
+            CoreMessage::SecretList { reply } => {
+                st.record("SecretList", String::new());
+                let _ = reply.send(st.secret_list());
+            }
+            CoreMessage::SecretSet { item, merge, reply } => {
+                let res = st.secret_set(item, merge);
+                let detail = match &res {
+                    Ok(id) => id.to_string(),
+                    Err(e) => e.clone(),
+                };
+                st.record("SecretSet", detail);
+                let _ = reply.send(res);
+            }
+            CoreMessage::SecretDel { id, reply } => {
+                st.record("SecretDel", id.to_string());
+                let _ = reply.send(st.secrets.remove(&id).is_some());
+            }
+        }
+        st.publish();
+    }
+
+    // --- Seeding ---
+
+    /// Store `item` in `collection` without recording a call. An id of
+    /// `u64::MAX` allocates a fresh one. Returns the id.
+    pub fn insert(&self, collection: &str, item: Item) -> u64 {
+        let mut st = self.state();
+        let id = st.set_item(collection, item, false);
+        st.publish();
+        id
+    }
+
+    pub fn set_public_url(&self, url: &str) {
+        self.state().public_url = url.to_string();
+    }
+
+    pub fn set_data_path(&self, path: &str) {
+        self.state().data_path = path.to_string();
+    }
+
+    pub fn set_settings(&self, settings: Item) {
+        self.state().settings = settings;
+    }
+
+    /// Store a secret as `SecretSet` would, without recording a call.
+    pub fn add_secret(&self, secret: Item) -> Result<u64, String> {
+        self.state().secret_set(secret, false)
+    }
+
+    /// Make `AuthLogin` succeed for `login` with `password`.
+    pub fn add_user(&self, login: &str, password: &str) {
+        self.state()
+            .users
+            .insert(login.to_string(), password.to_string());
+    }
+
+    /// Whether `grant_role` gave `user_id` the role `role`.
+    pub fn has_role(&self, user_id: u64, role: &str) -> bool {
+        self.state().has_role(user_id, role)
+    }
+
+    /// Make `AuthCheckRole` succeed for the user item with id `user_id`.
+    pub fn grant_role(&self, user_id: u64, role: &str) {
+        self.state()
+            .roles
+            .entry(user_id)
+            .or_default()
+            .insert(role.to_string());
+    }
+
+    // --- Inspection ---
+
+    /// Items of `collection`, ordered by id.
+    pub fn items(&self, collection: &str) -> Vec<Item> {
+        self.state()
+            .collections
+            .get(collection)
+            .map(|c| c.values().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn item(&self, collection: &str, id: u64) -> Option<Item> {
+        self.state()
+            .collections
+            .get(collection)
+            .and_then(|c| c.get(&id))
+            .cloned()
+    }
+
+    pub fn settings(&self) -> Item {
+        self.state().settings.clone()
+    }
+
+    /// Secrets, ordered by id.
+    pub fn secrets(&self) -> Vec<Item> {
+        self.state().secrets.values().cloned().collect()
+    }
+
+    pub fn sent_emails(&self) -> Vec<SentEmail> {
+        self.state().outbox.clone()
+    }
+
+    /// Every message received so far, in order.
+    pub fn calls(&self) -> Vec<RecordedCall> {
+        self.state().calls.clone()
+    }
+
+    /// Received messages of the `CoreMessage` variant `name`.
+    pub fn calls_named(&self, name: &str) -> Vec<RecordedCall> {
+        self.state()
+            .calls
+            .iter()
+            .filter(|c| c.name == name)
+            .cloned()
+            .collect()
+    }
+
+    pub fn call_count(&self, name: &str) -> usize {
+        self.state().calls.iter().filter(|c| c.name == name).count()
+    }
+
+    /// Forget the recorded calls and captured emails.
+    pub fn clear_calls(&self) {
+        let mut st = self.state();
+        st.calls.clear();
+        st.outbox.clear();
+    }
+
+    // --- Assertions ---
+
+    #[track_caller]
+    pub fn assert_called(&self, name: &str) {
+        assert!(
+            self.call_count(name) > 0,
+            "expected a {} call; got {:?}",
+            name,
+            self.calls()
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_not_called(&self, name: &str) {
+        let calls = self.calls_named(name);
+        assert!(
+            calls.is_empty(),
+            "expected no {} call; got {:?}",
+            name,
+            calls
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_called_times(&self, name: &str, times: usize) {
+        let calls = self.calls_named(name);
+        assert_eq!(
+            calls.len(),
+            times,
+            "expected {} {} call(s); got {:?}",
+            times,
+            name,
+            calls
+        );
+    }
+
+    /// Assert that `name` was called with exactly `detail` (see
+    /// [`RecordedCall::detail`]).
+    #[track_caller]
+    pub fn assert_called_with(&self, name: &str, detail: &str) {
+        let calls = self.calls_named(name);
+        assert!(
+            calls.iter().any(|c| c.detail == detail),
+            "expected a {} call with {:?}; got {:?}",
+            name,
+            detail,
+            calls
+        );
+    }
+
+    #[track_caller]
+    pub fn assert_email_sent_to(&self, to: &str) {
+        let outbox = self.sent_emails();
+        assert!(
+            outbox.iter().any(|m| m.to == to),
+            "expected an email to {}; outbox: {:?}",
+            to,
+            outbox
+        );
+    }
+}
+
+impl MockState {
+    fn record(&mut self, name: &'static str, detail: String) {
+        self.calls.push(RecordedCall { name, detail });
+    }
+
+    fn has_role(&self, user_id: u64, role: &str) -> bool {
+        self.roles
+            .get(&user_id)
+            .is_some_and(|roles| roles.contains(role))
+    }
+
+    fn allocate_id(&mut self) -> u64 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    fn set_item(&mut self, collection: &str, mut item: Item, merge: bool) -> u64 {
+        if item.id == u64::MAX {
+            item.id = self.allocate_id();
+        } else {
+            self.next_id = self.next_id.max(item.id);
+        }
+        let id = item.id;
+        let items = self.collections.entry(collection.to_string()).or_default();
+        let old_item = items.get(&id).cloned();
+        let revision = old_item.as_ref().map(item_revision).unwrap_or(0) + 1;
+        match items.get_mut(&id) {
+            Some(existing) if merge => {
+                existing.merge(&item);
+                existing.u64s.insert(REVISION_KEY.to_string(), revision);
+            }
+            _ => {
+                item.u64s.insert(REVISION_KEY.to_string(), revision);
+                items.insert(id, item);
+            }
+        }
+        let new_item = items.get(&id).cloned();
+        self.changes.push(ChangeEvent {
+            collection: collection.to_string(),
+            id,
+            kind: if old_item.is_some() {
+                ChangeKind::Updated
+            } else {
+                ChangeKind::Created
+            },
+            old_item,
+            new_item,
+        });
+        id
+    }
+
+    fn del_item(&mut self, collection: &str, id: u64) -> bool {
+        let Some(old_item) = self
+            .collections
+            .get_mut(collection)
+            .and_then(|c| c.remove(&id))
+        else {
+            return false;
+        };
+        self.changes.push(ChangeEvent {
+            collection: collection.to_string(),
+            id,
+            kind: ChangeKind::Deleted,
+            old_item: Some(old_item),
+            new_item: None,
+        });
+        true
+    }
+
+    /// Hand the changes made so far to matching subscribers, dropping
+    /// those that are gone or whose feed is full.
+    fn publish(&mut self) {
+        for change in std::mem::take(&mut self.changes) {
+            self.subscribers.retain(|sub| {
+                if sub.collection != change.collection {
+                    return !sub.events.is_closed();
+                }
+                let matches = [&change.old_item, &change.new_item]
+                    .into_iter()
+                    .flatten()
+                    .any(|item| matches_filter(item, &sub.filter));
+                !matches || sub.events.try_send(change.clone()).is_ok()
+            });
+        }
+    }
+
+    fn set_item_if(
+        &mut self,
+        collection: &str,
+        item: Item,
+        merge: bool,
+        expected_revision: u64,
+    ) -> Result<u64, ConditionalWriteError> {
+        if item.id == u64::MAX {
+            return Err(ConditionalWriteError::Rejected(
+                "conditional write needs an item id".to_string(),
+            ));
+        }
+        let current = self
+            .collections
+            .get(collection)
+            .and_then(|c| c.get(&item.id))
+            .map(item_revision)
+            .unwrap_or(0);
+        if current != expected_revision {
+            return Err(ConditionalWriteError::Conflict { current });
+        }
+        self.set_item(collection, item, merge);
+        Ok(current + 1)
+    }
+
+    /// Apply `ops` in order, restoring the previous collections if any
+    /// of them fails.
+    fn batch(&mut self, ops: Vec<BatchOp>) -> Result<Vec<u64>, BatchError> {
+        let collections = self.collections.clone();
+        let next_id = self.next_id;
+        let changes = self.changes.len();
+        let mut ids = Vec::with_capacity(ops.len());
+        for (index, op) in ops.into_iter().enumerate() {
+            let result = match op {
+                BatchOp::Set {
+                    collection,
+                    item,
+                    merge,
+                } => Ok(self.set_item(&collection, item, merge)),
+                BatchOp::Del { collection, id } => {
+                    if self.del_item(&collection, id) {
+                        Ok(id)
+                    } else {
+                        Err(format!("no item {} in {}", id, collection))
+                    }
+                }
+            };
+            match result {
+                Ok(id) => ids.push(id),
+                Err(reason) => {
+                    self.collections = collections;
+                    self.next_id = next_id;
+                    self.changes.truncate(changes);
+                    return Err(BatchError { index, reason });
+                }
+            }
+        }
+        Ok(ids)
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn query(
+        &self,
+        collection: &str,
+        id_min: u64,
+        id_max: u64,
+        sort_key: &str,
+        filter: &str,
+        skip: u64,
+        limit: u64,
+    ) -> ListResult {
+        let filter = parse_filter(filter).unwrap_or_else(|e| {
+            warn!("MockCore: unparseable filter {:?}: {}", filter, e);
+            Value::Bool(false)
+        });
+
+        let mut matching: Vec<&Item> = self
+            .collections
+            .get(collection)
+            .map(|c| {
+                c.range(id_min..=id_max)
+                    .map(|(_, item)| item)
+                    .filter(|item| matches_filter(item, &filter))
+                    .collect()
+            })
+            .unwrap_or_default();
+        if !sort_key.is_empty() {
+            matching.sort_by(|a, b| compare_by(a, b, sort_key));
+        }
+
+        ListResult {
+            total_count: matching.len() as u64,
+            map: matching
+                .into_iter()
+                .skip(skip.try_into().unwrap_or(usize::MAX))
+                .take(limit.try_into().unwrap_or(usize::MAX))
+                .map(|item| (item.id, item.clone()))
+                .collect(),
+        }
+    }
+
+    fn secret_list(&self) -> Vec<(u64, String)> {
+        let mut list: Vec<_> = self
+            .secrets
+            .values()
+            .map(|s| (s.id, s.strs.get("name").cloned().unwrap_or_default()))
+            .collect();
+        list.sort_by(|a, b| a.1.cmp(&b.1));
+        list
+    }
+
+    /// `PluginApi::secret_set` semantics.
+    fn secret_set(&mut self, mut item: Item, merge: bool) -> Result<u64, String> {
+        let existing = self.secrets.get(&item.id);
+        item.strs.retain(|k, v| {
+            if !k.starts_with("secret") || v != "<hidden>" {
+                return true;
+            }
+            match existing.and_then(|e| e.strs.get(k)) {
+                Some(old) => {
+                    *v = old.clone();
+                    true
+                }
+                None => false,
+            }
+        });
+        let mut secret = match existing {
+            Some(old) if merge => {
+                let mut merged = old.clone();
+                merged.merge(&item);
+                merged
+            }
+            _ => item,
+        };
+
+        let name = secret.strs.get("name").cloned().unwrap_or_default();
+        if name.is_empty() {
+            return Err("secret name must not be empty".to_string());
+        }
+        if self
+            .secrets
+            .values()
+            .any(|s| s.id != secret.id && s.strs.get("name") == Some(&name))
+        {
+            return Err(format!("secret {} already exists", name));
+        }
+
+        if secret.id == u64::MAX {
+            secret.id = self.allocate_id();
+        } else {
+            self.next_id = self.next_id.max(secret.id);
+        }
+        let id = secret.id;
+        self.secrets.insert(id, secret);
+        Ok(id)
+    }
+}
+
+/// Field of `item` as a JSON value; `_id`/`id` is the item id.
+fn field(item: &Item, key: &str) -> Option<Value> {
+    if key == "_id" || key == "id" {
+        return Some(Value::from(item.id));
+    }
+    if let Some((map, key)) = key.split_once('.') {
+        return match map {
+            "strs" => item.strs.get(key).map(|v| Value::from(v.clone())),
+            "u64s" => item.u64s.get(key).map(|v| Value::from(*v)),
+            "bools" => item.bools.get(key).map(|v| Value::from(*v)),
+            _ => None,
+        };
+    }
+    if let Some(v) = item.u64s.get(key) {
+        return Some(Value::from(*v));
+    }
+    if let Some(v) = item.strs.get(key) {
+        return Some(Value::from(v.clone()));
+    }
+    item.bools.get(key).map(|v| Value::from(*v))
+}
+
+fn compare_values(a: &Value, b: &Value) -> Option<Ordering> {
+    match (a, b) {
+        (Value::Number(a), Value::Number(b)) => a.as_f64()?.partial_cmp(&b.as_f64()?),
+        (Value::String(a), Value::String(b)) => Some(a.cmp(b)),
+        (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+        _ => None,
+    }
+}
+
+fn compare_by(a: &Item, b: &Item, key: &str) -> Ordering {
+    let by_key = match (field(a, key), field(b, key)) {
+        (Some(x), Some(y)) => compare_values(&x, &y).unwrap_or(Ordering::Equal),
+        (Some(_), None) => Ordering::Less,
+        (None, Some(_)) => Ordering::Greater,
+        (None, None) => Ordering::Equal,
+    };
+    by_key.then(a.id.cmp(&b.id))
+}
+
+fn parse_filter(filter: &str) -> Result<Value, serde_json::Error> {
+    if filter.trim().is_empty() {
+        Ok(Value::Object(Default::default()))
+    } else {
+        serde_json::from_str(filter)
+    }
+}
+
+fn matches_filter(item: &Item, filter: &Value) -> bool {
+    let Value::Object(clauses) = filter else {
+        return false;
+    };
+    clauses.iter().all(|(key, cond)| match key.as_str() {
+        "$and" => cond
+            .as_array()
+            .is_some_and(|c| c.iter().all(|f| matches_filter(item, f))),
+        "$or" => cond
+            .as_array()
+            .is_some_and(|c| c.iter().any(|f| matches_filter(item, f))),
+        "$nor" => cond
+            .as_array()
+            .is_some_and(|c| !c.iter().any(|f| matches_filter(item, f))),
+        _ => matches_condition(field(item, key).as_ref(), cond),
+    })
+}
+
+fn matches_condition(value: Option<&Value>, cond: &Value) -> bool {
+    let Value::Object(ops) = cond else {
+        return value == Some(cond);
+    };
+    ops.iter().all(|(op, arg)| {
+        let cmp = value.and_then(|v| compare_values(v, arg));
+        match op.as_str() {
+            "$eq" => value == Some(arg),
+            "$ne" => value != Some(arg),
+            "$in" => arg
+                .as_array()
+                .is_some_and(|a| value.is_some_and(|v| a.contains(v))),
+            "$nin" => arg
+                .as_array()
+                .is_some_and(|a| value.is_none_or(|v| !a.contains(v))),
+            "$gt" => cmp == Some(Ordering::Greater),
+            "$gte" => matches!(cmp, Some(Ordering::Greater | Ordering::Equal)),
+            "$lt" => cmp == Some(Ordering::Less),
+            "$lte" => matches!(cmp, Some(Ordering::Less | Ordering::Equal)),
+            "$regex" => match (value, arg) {
+                (Some(Value::String(v)), Value::String(pattern)) => Regex::new(pattern)
+                    .map(|re| re.is_match(v))
+                    .unwrap_or_else(|e| {
+                        warn!("MockCore: invalid $regex {:?}: {}", pattern, e);
+                        false
+                    }),
+                _ => false,
+            },
+            "$not" => !matches_condition(value, arg),
+            _ => false,
+        }
+    })
+}
+
+// ---------------------------------------------------------------------------
+// PluginHarness: drive a single plugin's hooks from a test
+// ---------------------------------------------------------------------------
+
+/// Registers a plugin against a [`MockCore`] and sends it hooks one at a
+/// time. Every helper awaits the plugin's reply and panics with the hook's
+/// name if the plugin is gone, drops the reply or misses the deadline.
+///
+/// Hooks go to the first plugin `register` added. Route and item hooks
+/// carry the harness' user (none by default, see
+/// [`PluginHarness::as_user`]) and handle (`"test"`).
+pub struct PluginHarness {
+    core: MockCore,
+    handle: CoreHandle,
+    registry: PluginRegistry,
+    hndl: String,
+    user: Option<Item>,
+    timeout: Duration,
+}
+
+impl PluginHarness {
+    /// Register the plugin against a fresh [`MockCore`]. `register` is the
+    /// plugin's register function (or a closure calling
+    /// `PluginRegistry::add_legacy` for a legacy plugin). Call from within
+    /// the tokio runtime.
+    pub fn new(register: impl FnOnce(&mut PluginRegistry, CoreHandle)) -> Self {
+        Self::with_core(MockCore::new(), register)
+    }
+
+    /// [`PluginHarness::new`] against a pre-seeded `core`.
+    pub fn with_core(
+        core: MockCore,
+        register: impl FnOnce(&mut PluginRegistry, CoreHandle),
+    ) -> Self {
+        let handle = core.start();
+        let mut registry = PluginRegistry::new();
+        register(&mut registry, handle.clone());
+        assert!(!registry.is_empty(), "register did not add a plugin");
+        Self {
+            core,
+            handle,
+            registry,
+            hndl: "test".to_string(),
+            user: None,
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Send hooks on behalf of `user`.
+    pub fn as_user(mut self, user: Item) -> Self {
+        self.user = Some(user);
+        self
+    }
+
+    pub fn with_hndl(mut self, hndl: impl Into<String>) -> Self {
+        self.hndl = hndl.into();
+        self
+    }
+
+    /// Deadline for each reply (default 5s).
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn core(&self) -> &MockCore {
+        &self.core
+    }
+
+    /// Handle to the mock core, as given to the plugin.
+    pub fn core_handle(&self) -> &CoreHandle {
+        &self.handle
+    }
+
+    pub fn registry(&self) -> &PluginRegistry {
+        &self.registry
+    }
+
+    fn sender(&self) -> &mpsc::Sender<PluginHookMessage> {
+        self.registry.senders().next().unwrap()
+    }
+
+    async fn deliver(&self, hook: &str, msg: PluginHookMessage) {
+        match timeout(self.timeout, self.sender().send(msg)).await {
+            Ok(Ok(())) => {}
+            Ok(Err(_)) => panic!("plugin is gone; could not deliver {}", hook),
+            Err(_) => panic!(
+                "plugin queue stayed full for {:?}; could not deliver {}",
+                self.timeout, hook
+            ),
+        }
+    }
+
+    async fn ask<T>(
+        &self,
+        hook: &str,
+        build: impl FnOnce(oneshot::Sender<T>) -> PluginHookMessage,
+    ) -> T {
+        let (tx, rx) = oneshot::channel();
+        self.deliver(hook, build(tx)).await;
+        match timeout(self.timeout, rx).await {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => panic!("plugin dropped the reply to {}", hook),
+            Err(_) => panic!("plugin did not answer {} within {:?}", hook, self.timeout),
+        }
+    }
+
+    /// `ItemPreEdit` for adding `item` to `collection`.
+    pub async fn pre_edit(&self, collection: &str, item: Item) -> PreEditReply {
+        self.pre_edit_with(collection, None, item, DataObjectAction::Add, false)
+            .await
+    }
+
+    pub async fn pre_edit_with(
+        &self,
+        collection: &str,
+        old_item: Option<Item>,
+        item: Item,
+        action: DataObjectAction,
+        merge: bool,
+    ) -> PreEditReply {
+        self.ask("ItemPreEdit", |reply| PluginHookMessage::ItemPreEdit {
+            hndl: self.hndl.clone(),
+            user: self.user.clone(),
+            collection: collection.to_string(),
+            old_item,
+            item,
+            action,
+            merge,
+            reply,
+        })
+        .await
+    }
+
+    /// `ItemPostEdit` for a write of item `id`; `new_item` is `None` for
+    /// a delete.
+    pub async fn post_edit(
+        &self,
+        collection: &str,
+        old_item: Option<Item>,
+        new_item: Option<Item>,
+        id: u64,
+        action: DataObjectAction,
+        merge: bool,
+    ) {
+        self.deliver(
+            "ItemPostEdit",
+            PluginHookMessage::ItemPostEdit {
+                hndl: self.hndl.clone(),
+                user: self.user.clone(),
+                collection: collection.to_string(),
+                old_item,
+                new_item,
+                id,
+                action,
+                merge,
+            },
+        )
+        .await
+    }
+
+    /// `ItemAuth` for reading item `id` of `collection`.
+    pub async fn auth(&self, collection: &str, id: u64) -> bool {
+        self.auth_with(collection, id, None, false).await
+    }
+
+    pub async fn auth_with(
+        &self,
+        collection: &str,
+        id: u64,
+        new_item: Option<Item>,
+        del: bool,
+    ) -> bool {
+        self.ask("ItemAuth", |reply| PluginHookMessage::ItemAuth {
+            hndl: self.hndl.clone(),
+            user: self.user.clone(),
+            collection: collection.to_string(),
+            id,
+            new_item,
+            del,
+            reply,
+        })
+        .await
+    }
+
+    pub async fn list_filter(
+        &self,
+        collection: &str,
+        context: &str,
+        items: HashMap<u64, Item>,
+    ) -> HashMap<u64, Item> {
+        self.ask("ItemListFilter", |reply| {
+            PluginHookMessage::ItemListFilter {
+                hndl: self.hndl.clone(),
+                user: self.user.clone(),
+                collection: collection.to_string(),
+                context: context.to_string(),
+                items,
+                reply,
+            }
+        })
+        .await
+        .items
+    }
+
+    pub async fn db_filter(&self, collection: &str, context: &str, filter_type: &str) -> String {
+        self.ask("ItemListDbFilter", |reply| {
+            PluginHookMessage::ItemListDbFilter {
+                hndl: self.hndl.clone(),
+                user: self.user.clone(),
+                collection: collection.to_string(),
+                context: context.to_string(),
+                filter_type: filter_type.to_string(),
+                reply,
+            }
+        })
+        .await
+    }
+
+    pub async fn collection_read(&self, collection: &str, item: Item) -> CollectionReadReply {
+        self.ask("CollectionRead", |reply| {
+            PluginHookMessage::CollectionRead {
+                hndl: self.hndl.clone(),
+                collection: collection.to_string(),
+                item,
+                reply,
+            }
+        })
+        .await
+    }
+
+    pub async fn otp(&self, item: Item) {
+        self.deliver(
+            "Otp",
+            PluginHookMessage::Otp {
+                hndl: self.hndl.clone(),
+                item,
+            },
+        )
+        .await
+    }
+
+    pub async fn periodic_job(&self, timing: &str) {
+        self.deliver(
+            "PeriodicJob",
+            PluginHookMessage::PeriodicJob {
+                timing: timing.to_string(),
+            },
+        )
+        .await
+    }
+
+    /// Context for `method` on `url` from the harness's hndl and user; add
+    /// headers or cookies and pass it to the `*_with` route helpers.
+    pub fn request(&self, method: &str, url: &str) -> RequestContext {
+        RequestContext::new(&self.hndl, &self.user, method, url)
+    }
+
+    /// `RouteUrl` (authenticated GET).
+    pub async fn get(&self, url: &str) -> WebResponse {
+        self.get_with(self.request("GET", url)).await
+    }
+
+    pub async fn get_with(&self, ctx: RequestContext) -> WebResponse {
+        self.ask("RouteUrl", |reply| PluginHookMessage::RouteUrl {
+            ctx,
+            reply,
+        })
+        .await
+    }
+
+    /// `RouteUrlPost` (authenticated POST).
+    pub async fn post(&self, url: &str, item: Item) -> WebResponse {
+        self.post_with(self.request("POST", url), item, Vec::new())
+            .await
+    }
+
+    pub async fn post_with(
+        &self,
+        ctx: RequestContext,
+        item: Item,
+        parts: Vec<UploadPart>,
+    ) -> WebResponse {
+        self.ask("RouteUrlPost", |reply| PluginHookMessage::RouteUrlPost {
+            ctx,
+            item,
+            parts: parts.into(),
+            reply,
+        })
+        .await
+    }
+
+    /// `RouteUnprotectedUrl` (public GET).
+    pub async fn get_unprotected(&self, url: &str) -> WebResponse {
+        self.get_unprotected_with(self.request("GET", url)).await
+    }
+
+    pub async fn get_unprotected_with(&self, ctx: RequestContext) -> WebResponse {
+        self.ask("RouteUnprotectedUrl", |reply| {
+            PluginHookMessage::RouteUnprotectedUrl { ctx, reply }
+        })
+        .await
+    }
+
+    /// `RouteUnprotectedUrlPost` (public POST).
+    pub async fn post_unprotected(&self, url: &str, item: Item) -> WebResponse {
+        self.post_unprotected_with(self.request("POST", url), item, Vec::new())
+            .await
+    }
+
+    pub async fn post_unprotected_with(
+        &self,
+        ctx: RequestContext,
+        item: Item,
+        parts: Vec<UploadPart>,
+    ) -> WebResponse {
+        self.ask("RouteUnprotectedUrlPost", |reply| {
+            PluginHookMessage::RouteUnprotectedUrlPost {
+                ctx,
+                item,
+                parts: parts.into(),
+                reply,
+            }
+        })
+        .await
+    }
+
+    /// `RouteRest` with `method` and raw `payload`.
+    pub async fn rest(&self, method: &str, url: &str, payload: &str) -> WebResponse {
+        self.rest_with(self.request(method, url), payload).await
+    }
+
+    pub async fn rest_with(&self, ctx: RequestContext, payload: &str) -> WebResponse {
+        self.ask("RouteRest", |reply| PluginHookMessage::RouteRest {
+            ctx,
+            payload: payload.to_string(),
+            reply,
+        })
+        .await
+    }
+
+    /// `WebSocketConnect` on `url`, routed as core does, with roles from
+    /// [`MockCore::grant_role`].
+    pub async fn websocket(&self, url: &str) -> Result<WebSocketSession, WebResponse> {
+        let check = |user: &Item, role: &str| self.core.has_role(user.id, role);
+        self.dispatcher()
+            .with_role_check(&check)
+            .websocket_connect(&self.request("GET", url), true)
+            .await
+    }
+
+    /// `SseConnect` on `url`, like [`PluginHarness::websocket`].
+    pub async fn sse(&self, url: &str) -> Result<SseSession, WebResponse> {
+        let check = |user: &Item, role: &str| self.core.has_role(user.id, role);
+        self.dispatcher()
+            .with_role_check(&check)
+            .sse_connect(&self.request("GET", url), true)
+            .await
+    }
+
+    fn dispatcher(&self) -> HookDispatcher<'_> {
+        HookDispatcher::new(&self.registry).with_timeouts(HookTimeouts {
+            route: self.timeout,
+            ..HookTimeouts::default()
+        })
+    }
+
+    pub async fn ping(&self) {
+        self.ask("Ping", |reply| PluginHookMessage::Ping { reply })
+            .await
+    }
+
+    /// Send `Shutdown` and wait until the plugin has dropped its receiver.
+    pub async fn shutdown(self) {
+        let drain = self.timeout;
+        let mut registry = self.registry;
+        let name = registry.manifests().next().unwrap().name.clone();
+        registry.unload(&name, drain).await;
+    }
+}
@@ -0,0 +1,357 @@
+/*
+ * Isabelle project
+ *
+ * Copyright 2026 Maxim Menshikov
+ *
+ * Permission is hereby granted, free of charge, to any person obtaining
+ * a copy of this software and associated documentation files (the "Software"),
+ * to deal in the Software without restriction, including without limitation
+ * the rights to use, copy, modify, merge, publish, distribute, sublicense,
+ * and/or sell copies of the Software, and to permit persons to whom the
+ * Software is furnished to do so, subject to the following conditions:
+ *
+ * The above copyright notice and this permission notice shall be included
+ * in all copies or substantial portions of the Software.
+ */
+
+//! Files uploaded with a POST route, as passed to `RouteUrlPost` and
+//! `RouteUnprotectedUrlPost` next to the parsed item.
+//!
+//! Core feeds each multipart field into an [`UploadCollector`] as it
+//! arrives. The collector enforces core's [`UploadLimits`] before anything
+//! is buffered, and spools large parts to temporary files:
+//!
+//! ```ignore
+//! let mut uploads = UploadCollector::new(limits.clone());
+//! while let Some(mut field) = multipart.try_next().await? {
+//!     let cd = field.content_disposition();
+//!     if let Err(e) = uploads.begin_part(cd.get_name().unwrap_or(""), cd.get_filename(), None) {
+//!         return Ok(WebResponse::from(e).into());
+//!     }
+//!     while let Some(chunk) = field.try_next().await? {
+//!         if let Err(e) = uploads.push(&chunk) {
+//!             return Ok(WebResponse::from(e).into());
+//!         }
+//!     }
+//! }
+//! let parts = uploads.finish()?;
+//! ```
+
+use serde_json::json;
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::api::{CustomResponse, WebResponse};
+
+/// Upload limits, set by core.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UploadLimits {
+    /// Most parts (files and fields) one request may carry.
+    pub max_parts: usize,
+    /// Largest single part, in bytes.
+    pub max_part_size: u64,
+    /// Largest sum of all parts, in bytes.
+    pub max_total_size: u64,
+    /// Parts larger than this are spooled to a file in `temp_dir`.
+    pub memory_threshold: u64,
+    pub temp_dir: PathBuf,
+}
+
+impl Default for UploadLimits {
+    /// 16 parts, 10 MiB each, 50 MiB in total; parts over 1 MiB go to the
+    /// system temp directory.
+    fn default() -> Self {
+        Self {
+            max_parts: 16,
+            max_part_size: 10 << 20,
+            max_total_size: 50 << 20,
+            memory_threshold: 1 << 20,
+            temp_dir: std::env::temp_dir(),
+        }
+    }
+}
+
+/// Why an upload was refused. Converts into a JSON [`WebResponse`]: 413
+/// for exceeded limits, 500 if a part couldn't be spooled.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UploadRejection {
+    TooManyParts { max: usize },
+    PartTooLarge { field: String, max: u64 },
+    TooLarge { max: u64 },
+    Io(String),
+}
+
+impl fmt::Display for UploadRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            UploadRejection::TooManyParts { max } => {
+                write!(f, "more than {} parts in upload", max)
+            }
+            UploadRejection::PartTooLarge { field, max } => {
+                write!(f, "part {} is larger than {} bytes", field, max)
+            }
+            UploadRejection::TooLarge { max } => write!(f, "upload is larger than {} bytes", max),
+            UploadRejection::Io(e) => write!(f, "failed to store upload: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for UploadRejection {}
+
+impl From<UploadRejection> for WebResponse {
+    fn from(rejection: UploadRejection) -> Self {
+        let (status, body) = match &rejection {
+            UploadRejection::TooManyParts { max } => {
+                (413, json!({ "error": "too_many_parts", "limit": max }))
+            }
+            UploadRejection::PartTooLarge { field, max } => (
+                413,
+                json!({ "error": "part_too_large", "field": field, "limit": max }),
+            ),
+            UploadRejection::TooLarge { max } => {
+                (413, json!({ "error": "upload_too_large", "limit": max }))
+            }
+            UploadRejection::Io(_) => (500, json!({ "error": "upload_failed" })),
+        };
+        let mut body = body;
+        body["message"] = rejection.to_string().into();
+        CustomResponse::new(status)
+            .content_type("application/json")
+            .body(body.to_string())
+            .into()
+    }
+}
+
+/// One uploaded file or form field.
+#[derive(Debug, Clone)]
+pub struct UploadPart {
+    /// Form field name.
+    pub field: String,
+    pub filename: Option<String>,
+    pub content_type: Option<String>,
+    /// Size in bytes.
+    pub size: u64,
+    pub data: UploadData,
+}
+
+/// Where an [`UploadPart`]'s bytes are.
+#[derive(Debug, Clone)]
+pub enum UploadData {
+    Memory(Vec<u8>),
+    File(Arc<TempUpload>),
+}
+
+impl UploadPart {
+    /// A part held in memory, e.g. for tests.
+    pub fn in_memory(
+        field: &str,
+        filename: Option<&str>,
+        content_type: Option<&str>,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        let data = data.into();
+        Self {
+            field: field.to_string(),
+            filename: filename.map(str::to_string),
+            content_type: content_type.map(str::to_string),
+            size: data.len() as u64,
+            data: UploadData::Memory(data),
+        }
+    }
+
+    /// The whole content; reads the temp file for spooled parts.
+    pub fn bytes(&self) -> io::Result<Cow<'_, [u8]>> {
+        match &self.data {
+            UploadData::Memory(data) => Ok(Cow::Borrowed(data)),
+            UploadData::File(file) => fs::read(file.path()).map(Cow::Owned),
+        }
+    }
+
+    /// Temp file holding a spooled part; move or copy it to keep it.
+    pub fn path(&self) -> Option<&Path> {
+        match &self.data {
+            UploadData::Memory(_) => None,
+            UploadData::File(file) => Some(file.path()),
+        }
+    }
+}
+
+/// A spooled part's temporary file, removed once the last [`UploadPart`]
+/// referring to it is dropped.
+#[derive(Debug)]
+pub struct TempUpload {
+    path: PathBuf,
+}
+
+impl TempUpload {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempUpload {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Builds [`UploadPart`]s from a streamed multipart body while enforcing
+/// [`UploadLimits`]. File writes are blocking.
+pub struct UploadCollector {
+    limits: UploadLimits,
+    parts: Vec<UploadPart>,
+    total: u64,
+    current: Option<PartInProgress>,
+}
+
+struct PartInProgress {
+    field: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    size: u64,
+    buffer: Vec<u8>,
+    spool: Option<(File, TempUpload)>,
+}
+
+impl UploadCollector {
+    pub fn new(limits: UploadLimits) -> Self {
+        Self {
+            limits,
+            parts: Vec::new(),
+            total: 0,
+            current: None,
+        }
+    }
+
+    /// Start the next part, completing the previous one.
+    pub fn begin_part(
+        &mut self,
+        field: &str,
+        filename: Option<&str>,
+        content_type: Option<&str>,
+    ) -> Result<(), UploadRejection> {
+        self.end_part()?;
+        if self.parts.len() >= self.limits.max_parts {
+            return Err(UploadRejection::TooManyParts {
+                max: self.limits.max_parts,
+            });
+        }
+        self.current = Some(PartInProgress {
+            field: field.to_string(),
+            filename: filename.map(str::to_string),
+            content_type: content_type.map(str::to_string),
+            size: 0,
+            buffer: Vec::new(),
+            spool: None,
+        });
+        Ok(())
+    }
+
+    /// Append `chunk` to the part started last. Bytes pushed before any
+    /// [`UploadCollector::begin_part`] go to an unnamed part.
+    pub fn push(&mut self, chunk: &[u8]) -> Result<(), UploadRejection> {
+        if self.current.is_none() {
+            self.begin_part("", None, None)?;
+        }
+        let Some(current) = self.current.as_mut() else {
+            unreachable!("begin_part always starts a part");
+        };
+        let len = chunk.len() as u64;
+        if current.size + len > self.limits.max_part_size {
+            return Err(UploadRejection::PartTooLarge {
+                field: current.field.clone(),
+                max: self.limits.max_part_size,
+            });
+        }
+        if self.total + len > self.limits.max_total_size {
+            return Err(UploadRejection::TooLarge {
+                max: self.limits.max_total_size,
+            });
+        }
+        current.size += len;
+        self.total += len;
+
+        if current.spool.is_none() && current.size > self.limits.memory_threshold {
+            let (mut file, temp) = spool_file(&self.limits.temp_dir)?;
+            write_spool(&mut file, &current.buffer)?;
+            current.buffer = Vec::new();
+            current.spool = Some((file, temp));
+        }
+        match &mut current.spool {
+            Some((file, _)) => write_spool(file, chunk),
+            None => {
+                current.buffer.extend_from_slice(chunk);
+                Ok(())
+            }
+        }
+    }
+
+    /// All parts, in the order they were sent.
+    pub fn finish(mut self) -> Result<Vec<UploadPart>, UploadRejection> {
+        self.end_part()?;
+        Ok(self.parts)
+    }
+
+    fn end_part(&mut self) -> Result<(), UploadRejection> {
+        let Some(current) = self.current.take() else {
+            return Ok(());
+        };
+        let data = match current.spool {
+            Some((mut file, temp)) => {
+                file.flush()
+                    .map_err(|e| UploadRejection::Io(e.to_string()))?;
+                UploadData::File(Arc::new(temp))
+            }
+            None => UploadData::Memory(current.buffer),
+        };
+        self.parts.push(UploadPart {
+            field: current.field,
+            filename: current.filename,
+            content_type: current.content_type,
+            size: current.size,
+            data,
+        });
+        Ok(())
+    }
+}
+
+/// Names tried by [`spool_file`] before giving up.
+const SPOOL_ATTEMPTS: usize = 64;
+
+fn spool_file(dir: &Path) -> Result<(File, TempUpload), UploadRejection> {
+    static NEXT_SPOOL: AtomicUsize = AtomicUsize::new(0);
+
+    // `create_new` refuses existing paths and symlinks, so a name planted
+    // in a shared temp dir is skipped rather than written through.
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    for _ in 0..SPOOL_ATTEMPTS {
+        let path = dir.join(format!(
+            "isabelle-upload-{}-{}",
+            std::process::id(),
+            NEXT_SPOOL.fetch_add(1, Ordering::Relaxed)
+        ));
+        match options.open(&path) {
+            Ok(file) => return Ok((file, TempUpload { path })),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(UploadRejection::Io(e.to_string())),
+        }
+    }
+    Err(UploadRejection::Io(format!(
+        "no free spool file name in {}",
+        dir.display()
+    )))
+}
+
+fn write_spool(file: &mut File, data: &[u8]) -> Result<(), UploadRejection> {
+    file.write_all(data)
+        .map_err(|e| UploadRejection::Io(e.to_string()))
+}
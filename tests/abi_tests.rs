@@ -0,0 +1,70 @@
+use isabelle_plugin_api::abi::*;
+use std::ffi::CStr;
+
+isabelle_plugin_api::export_plugin_abi!();
+
+#[test]
+fn current_abi_matches_itself() {
+    let abi = PluginAbi::current();
+    assert_eq!(abi.descriptor_version, PLUGIN_ABI_DESCRIPTOR_VERSION);
+    let version = unsafe { CStr::from_ptr(abi.crate_version) };
+    assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+    assert!(unsafe { abi.check_against(&PluginAbi::current()) }.is_ok());
+}
+
+#[test]
+fn exported_descriptor_matches_host() {
+    let abi = isabelle_plugin_abi();
+    assert!(unsafe { abi.check_against(&PluginAbi::current()) }.is_ok());
+}
+
+#[test]
+fn mismatching_fields_are_all_reported() {
+    let plugin = PluginAbi {
+        crate_version: c"0.0.1".as_ptr(),
+        api_hash: 0,
+        ..PluginAbi::current()
+    };
+    let err = unsafe { plugin.check_against(&PluginAbi::current()) }.unwrap_err();
+    assert!(
+        err.contains("isabelle-plugin-api 0.0.1"),
+        "unexpected error: {}",
+        err
+    );
+    assert!(err.contains("API revision"), "unexpected error: {}", err);
+    assert!(!err.contains("rustc"), "unexpected error: {}", err);
+}
+
+#[test]
+fn descriptor_version_mismatch_is_reported_first() {
+    let plugin = PluginAbi {
+        descriptor_version: PLUGIN_ABI_DESCRIPTOR_VERSION + 1,
+        ..PluginAbi::current()
+    };
+    let err = unsafe { plugin.check_against(&PluginAbi::current()) }.unwrap_err();
+    assert!(
+        err.contains("descriptor version"),
+        "unexpected error: {}",
+        err
+    );
+}
+
+#[test]
+fn dependency_versions_and_type_layouts_are_checked() {
+    let abi = PluginAbi::current();
+    let dependencies = unsafe { CStr::from_ptr(abi.dependencies) };
+    assert!(dependencies.to_str().unwrap().contains("tokio "));
+
+    let plugin = PluginAbi {
+        dependencies: c"tokio 1.0.0".as_ptr(),
+        layout_hash: abi.layout_hash ^ 1,
+        ..abi
+    };
+    let err = unsafe { plugin.check_against(&abi) }.unwrap_err();
+    assert!(
+        err.contains("dependencies tokio 1.0.0 (host: "),
+        "unexpected error: {}",
+        err
+    );
+    assert!(err.contains("type layouts"), "unexpected error: {}", err);
+}
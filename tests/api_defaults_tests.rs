@@ -48,7 +48,8 @@ fn plugin_default_route_url_post_hook_returns_not_implemented() {
     let api = boxed_api();
     let mut plugin = CountingPlugin::new();
     let item = Item::new();
-    let resp = plugin.route_url_post_hook(&api, "h", &None, "q", &item);
+    let ctx = RequestContext::new("h", &None, "POST", "q");
+    let resp = plugin.route_url_post_hook(&api, &ctx, &item, &[]);
     assert!(matches!(resp, WebResponse::NotImplemented));
 }
 
@@ -56,7 +57,8 @@ fn plugin_default_route_url_post_hook_returns_not_implemented() {
 fn plugin_default_route_rest_hook_returns_not_implemented() {
     let api = boxed_api();
     let mut plugin = CountingPlugin::new();
-    let resp = plugin.route_rest_hook(&api, "h", "GET", &None, "/foo", "");
+    let ctx = RequestContext::new("h", &None, "GET", "/foo");
+    let resp = plugin.route_rest_hook(&api, &ctx, "");
     assert!(matches!(resp, WebResponse::NotImplemented));
 }
 
@@ -77,6 +79,15 @@ fn plugin_default_call_periodic_job_hook_is_noop() {
     plugin.call_periodic_job_hook(&api, "");
 }
 
+#[test]
+fn plugin_default_manifest_is_anonymous_and_subscribes_to_everything() {
+    let plugin = CountingPlugin::new();
+    let manifest = plugin.manifest();
+    assert!(manifest.name.is_empty());
+    assert!(manifest.hooks.is_none());
+    assert!(manifest.collections.is_empty());
+}
+
 #[test]
 fn web_response_variants_are_distinct() {
     let cases: Vec<WebResponse> = vec![
@@ -89,8 +100,15 @@ fn web_response_variants_are_distinct() {
         WebResponse::BadRequest,
         WebResponse::Forbidden,
         WebResponse::NotImplemented,
+        WebResponse::ServiceUnavailable,
         WebResponse::Login("user".to_string()),
         WebResponse::Logout,
+        WebResponse::Json(serde_json::json!({})),
+        WebResponse::Redirect("/".to_string()),
+        WebResponse::Conflict,
+        WebResponse::TooManyRequests,
+        WebResponse::Custom(CustomResponse::new(204)),
+        WebResponse::Stream(StreamResponse::channel("text/plain", 1).1),
     ];
 
     // Smoke check: each variant is constructable and matches its own arm.
@@ -105,8 +123,15 @@ fn web_response_variants_are_distinct() {
             | WebResponse::BadRequest
             | WebResponse::Forbidden
             | WebResponse::NotImplemented
+            | WebResponse::ServiceUnavailable
             | WebResponse::Login(_)
-            | WebResponse::Logout => {}
+            | WebResponse::Logout
+            | WebResponse::Json(_)
+            | WebResponse::Redirect(_)
+            | WebResponse::Conflict
+            | WebResponse::TooManyRequests
+            | WebResponse::Custom(_)
+            | WebResponse::Stream(_) => {}
         }
     }
 }
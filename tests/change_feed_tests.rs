@@ -0,0 +1,130 @@
+#![cfg(feature = "testing")]
+
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::query::{Field, Filter};
+use isabelle_plugin_api::testing::MockCore;
+use tokio::sync::mpsc;
+
+fn task(id: u64, status: &str) -> Item {
+    let mut item = Item::new();
+    item.id = id;
+    item.strs.insert("status".to_string(), status.to_string());
+    item
+}
+
+#[tokio::test]
+async fn feed_reports_creates_updates_and_deletes() {
+    let core = MockCore::new();
+    let handle = core.start();
+    let mut feed = handle.subscribe("tasks", &Filter::all()).await;
+
+    // A write by "another plugin" and one made straight on the mock.
+    let other = core.start();
+    other.db_set_item("tasks", &task(1, "open"), false).await;
+    core.insert("tasks", task(2, "open"));
+    other.db_set_item("tasks", &task(1, "done"), true).await;
+    other.db_del_item("tasks", 2).await;
+    other.db_set_item("notes", &task(3, "open"), false).await;
+
+    let created = feed.recv().await.unwrap();
+    assert_eq!((created.id, created.kind), (1, ChangeKind::Created));
+    assert!(created.old_item.is_none());
+    assert_eq!(created.new_item.unwrap().strs["status"], "open");
+
+    assert_eq!(feed.recv().await.unwrap().id, 2);
+
+    let updated = feed.recv().await.unwrap();
+    assert_eq!((updated.id, updated.kind), (1, ChangeKind::Updated));
+    assert_eq!(updated.old_item.unwrap().strs["status"], "open");
+    assert_eq!(updated.new_item.unwrap().strs["status"], "done");
+
+    let deleted = feed.recv().await.unwrap();
+    assert_eq!((deleted.id, deleted.kind), (2, ChangeKind::Deleted));
+    assert!(deleted.new_item.is_none());
+
+    // Nothing from "notes".
+    assert!(feed.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn feed_filters_on_old_or_new_item() {
+    let core = MockCore::new();
+    core.insert("tasks", task(1, "open"));
+    core.insert("tasks", task(2, "done"));
+    let handle = core.start();
+    let mut open = handle
+        .subscribe("tasks", &Filter::eq(Field::str("status"), "open"))
+        .await;
+
+    handle.db_set_item("tasks", &task(2, "done"), false).await;
+    // Leaving the filter is still reported.
+    handle.db_set_item("tasks", &task(1, "done"), false).await;
+    handle.db_set_item("tasks", &task(5, "open"), false).await;
+
+    assert_eq!(open.recv().await.unwrap().id, 1);
+    assert_eq!(open.recv().await.unwrap().id, 5);
+    assert!(open.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn feed_skips_rolled_back_batches() {
+    let core = MockCore::new();
+    let handle = core.start();
+    let mut feed = handle.subscribe("tasks", &Filter::all()).await;
+
+    let failed = handle
+        .db_batch(vec![
+            BatchOp::set("tasks", &task(1, "open"), false),
+            BatchOp::del("tasks", 9),
+        ])
+        .await;
+    assert!(failed.is_none());
+    handle
+        .db_batch(vec![
+            BatchOp::set("tasks", &task(1, "open"), false),
+            BatchOp::del("tasks", 1),
+        ])
+        .await
+        .unwrap();
+
+    assert_eq!(feed.recv().await.unwrap().kind, ChangeKind::Created);
+    assert_eq!(feed.recv().await.unwrap().kind, ChangeKind::Deleted);
+    assert!(feed.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn feed_ends_when_subscriber_falls_behind() {
+    let core = MockCore::new();
+    let handle = core.start();
+    let mut feed = handle.subscribe("tasks", &Filter::all()).await;
+
+    for id in 1..=CHANGE_FEED_CAPACITY as u64 + 1 {
+        core.insert("tasks", task(id, "open"));
+    }
+    let mut seen = 0;
+    while feed.recv().await.is_some() {
+        seen += 1;
+    }
+    assert_eq!(seen, CHANGE_FEED_CAPACITY);
+}
+
+#[tokio::test]
+async fn refused_subscription_gives_closed_receiver() {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let CoreMessage::DbSubscribe { reply, .. } = msg {
+                let _ = reply.send(Err("no feeds here".to_string()));
+            }
+        }
+    });
+    let handle = CoreHandle::new(tx);
+
+    assert_eq!(
+        handle.try_subscribe("tasks", &Filter::all()).await.err(),
+        Some(CoreError::Denied("no feeds here".to_string()))
+    );
+    let mut feed = handle.subscribe("tasks", &Filter::all()).await;
+    assert!(feed.recv().await.is_none());
+}
@@ -4,13 +4,21 @@ use isabelle_dm::data_model::data_object_action::DataObjectAction;
 use isabelle_dm::data_model::item::Item;
 use isabelle_dm::data_model::list_result::ListResult;
 use isabelle_dm::data_model::process_result::ProcessResult;
+use isabelle_plugin_api::actor::*;
 use isabelle_plugin_api::api::*;
 use std::any::Any;
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
 
 /// Test plugin that records how often `ping_test` was called and exercises
 /// only the default trait impls for the rest. Using the defaults lets us
 /// assert their return values without re-implementing every hook.
+///
+/// This is a legacy `Plugin` fixture for the default-impl and `PluginPool`
+/// tests, not a hook driver; actor plugins are driven with `PluginHarness`.
 pub struct CountingPlugin {
     pub pings: u64,
 }
@@ -48,10 +56,13 @@ impl Plugin for CountingPlugin {
         &mut self,
         _api: &Box<dyn PluginApi>,
         _hndl: &str,
+        _user: &Option<Item>,
         _collection: &str,
         _old_itm: Option<Item>,
+        _new_itm: Option<Item>,
         _id: u64,
         _action: DataObjectAction,
+        _merge: bool,
     ) {
     }
 
@@ -79,22 +90,14 @@ impl Plugin for CountingPlugin {
     ) {
     }
 
-    fn route_url_hook(
-        &mut self,
-        _api: &Box<dyn PluginApi>,
-        _hndl: &str,
-        _user: &Option<Item>,
-        _query: &str,
-    ) -> WebResponse {
+    fn route_url_hook(&mut self, _api: &Box<dyn PluginApi>, _ctx: &RequestContext) -> WebResponse {
         WebResponse::Ok
     }
 
     fn route_unprotected_url_hook(
         &mut self,
         _api: &Box<dyn PluginApi>,
-        _hndl: &str,
-        _user: &Option<Item>,
-        _query: &str,
+        _ctx: &RequestContext,
     ) -> WebResponse {
         WebResponse::Ok
     }
@@ -102,10 +105,9 @@ impl Plugin for CountingPlugin {
     fn route_unprotected_url_post_hook(
         &mut self,
         _api: &Box<dyn PluginApi>,
-        _hndl: &str,
-        _user: &Option<Item>,
-        _query: &str,
+        _ctx: &RequestContext,
         _itm: &Item,
+        _parts: &[UploadPart],
     ) -> WebResponse {
         WebResponse::Ok
     }
@@ -224,3 +226,116 @@ impl PluginApi for StubApi {
     }
     fn fn_set_state(&self, _hndl: &str, _value: Option<Box<dyn Any + Send>>) {}
 }
+
+/// Path of the plugin library built from `examples/<name>.rs`. `cargo test`
+/// builds examples next to the test binaries.
+pub fn example_library(name: &str) -> std::path::PathBuf {
+    let exe = std::env::current_exe().unwrap();
+    let path = exe
+        .parent()
+        .and_then(|deps| deps.parent())
+        .unwrap()
+        .join("examples")
+        .join(format!(
+            "{}{}{}",
+            std::env::consts::DLL_PREFIX,
+            name,
+            std::env::consts::DLL_SUFFIX
+        ));
+    assert!(
+        path.exists(),
+        "{} is missing; build it with `cargo build --example {}`",
+        path.display(),
+        name
+    );
+    path
+}
+
+/// Install the example plugin library `name` in `dir` as `file`, replacing
+/// any previous file by renaming over it.
+pub fn install_example_library(name: &str, dir: &std::path::Path, file: &str) -> String {
+    let staged = dir.join(format!(".{}.tmp", file));
+    std::fs::copy(example_library(name), &staged).unwrap();
+    let path = dir.join(file);
+    std::fs::rename(&staged, &path).unwrap();
+    path.to_string_lossy().to_string()
+}
+
+/// Fresh, empty temporary directory for the test `name`.
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "isabelle-plugin-api-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+/// Spawn a plugin actor that answers hooks with fixed behaviour and records
+/// every hook it sees in `log` as "<name>:<hook>". Pings are answered
+/// slowly, so a test can catch one still queued.
+pub fn spawn_plugin(
+    reg: &mut PluginRegistry,
+    name: &'static str,
+    allow: bool,
+    db_filter: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+) {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                PluginHookMessage::ItemPreEdit {
+                    mut item, reply, ..
+                } => {
+                    log.lock().unwrap().push(format!("{}:pre_edit", name));
+                    if !allow {
+                        let _ = reply.send(PreEditReply::rejected(name));
+                        continue;
+                    }
+                    let mut seen = item.strs.get("seen").cloned().unwrap_or_default();
+                    seen.push_str(name);
+                    item.strs.insert("seen".to_string(), seen);
+                    let _ = reply.send(PreEditReply {
+                        modified_item: Some(item),
+                        ..PreEditReply::ok_unchanged()
+                    });
+                }
+                PluginHookMessage::ItemAuth { reply, .. } => {
+                    log.lock().unwrap().push(format!("{}:auth", name));
+                    let _ = reply.send(allow);
+                }
+                PluginHookMessage::ItemListFilter {
+                    mut items, reply, ..
+                } => {
+                    items.remove(&items.keys().copied().min().unwrap_or(0));
+                    let _ = reply.send(ListFilterReply { items });
+                }
+                PluginHookMessage::ItemListDbFilter { reply, .. } => {
+                    let _ = reply.send(db_filter.to_string());
+                }
+                PluginHookMessage::RouteUrl { reply, .. } => {
+                    let resp = if allow {
+                        WebResponse::OkData(name.to_string())
+                    } else {
+                        WebResponse::NotImplemented
+                    };
+                    let _ = reply.send(resp);
+                }
+                PluginHookMessage::Ping { reply } => {
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                    log.lock().unwrap().push(format!("{}:ping", name));
+                    let _ = reply.send(());
+                }
+                PluginHookMessage::Shutdown => {
+                    log.lock().unwrap().push(format!("{}:shutdown", name));
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+    reg.add(name, tx);
+}
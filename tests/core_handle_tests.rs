@@ -0,0 +1,143 @@
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Spawn a core task that only knows about the secret store: a single
+/// secret (id 1, name "smtp") and nothing else.
+fn spawn_secret_core() -> CoreHandle {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                CoreMessage::SecretGetByName { name, reply } => {
+                    let found = (name == "smtp").then(|| {
+                        let mut itm = Item::new();
+                        itm.id = 1;
+                        itm.strs.insert("name".to_string(), name);
+                        itm
+                    });
+                    let _ = reply.send(found);
+                }
+                CoreMessage::SecretList { reply } => {
+                    let _ = reply.send(vec![(1, "smtp".to_string())]);
+                }
+                CoreMessage::SecretSet { item, reply, .. } => {
+                    let res = match item.strs.get("name") {
+                        Some(n) if !n.is_empty() => Ok(2),
+                        _ => Err("name is required".to_string()),
+                    };
+                    let _ = reply.send(res);
+                }
+                CoreMessage::SecretDel { id, reply } => {
+                    let _ = reply.send(id == 1);
+                }
+                _ => {}
+            }
+        }
+    });
+    CoreHandle::new(tx)
+}
+
+#[tokio::test]
+async fn secret_messages_round_trip() {
+    let core = spawn_secret_core();
+
+    assert_eq!(core.secret_get_by_name("smtp").await.unwrap().id, 1);
+    assert!(core.secret_get_by_name("other").await.is_none());
+    assert_eq!(core.secret_list().await, vec![(1, "smtp".to_string())]);
+
+    let mut itm = Item::new();
+    assert_eq!(
+        core.secret_set(&itm, false).await,
+        Err("name is required".to_string())
+    );
+    itm.strs.insert("name".to_string(), "api".to_string());
+    assert_eq!(core.secret_set(&itm, false).await, Ok(2));
+
+    assert!(core.secret_del(1).await);
+    assert!(!core.secret_del(5).await);
+}
+
+#[tokio::test]
+async fn secret_methods_fall_back_when_core_is_gone() {
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    let core = CoreHandle::new(tx);
+
+    assert!(core.secret_get_by_name("smtp").await.is_none());
+    assert!(core.secret_list().await.is_empty());
+    assert!(core.secret_set(&Item::new(), true).await.is_err());
+    assert!(!core.secret_del(1).await);
+}
+
+#[tokio::test]
+async fn try_methods_report_core_shutdown() {
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    let core = CoreHandle::new(tx);
+
+    assert_eq!(
+        core.try_db_del_item("col", 1).await,
+        Err(CoreError::CoreShutdown)
+    );
+    assert_eq!(
+        core.try_send_email("a@b.c", "s", "b").await,
+        Err(CoreError::CoreShutdown)
+    );
+}
+
+#[tokio::test]
+async fn try_methods_report_dropped_reply() {
+    // `spawn_secret_core` drops the reply of every non-secret request.
+    let core = spawn_secret_core();
+    assert!(matches!(
+        core.try_db_get_item("col", 1).await,
+        Err(CoreError::ReplyDropped)
+    ));
+    // The sentinel API can't tell the difference.
+    assert!(core.db_get_item("col", 1).await.is_none());
+}
+
+#[tokio::test(start_paused = true)]
+async fn try_methods_report_timeout() {
+    let (tx, _rx) = mpsc::channel(1);
+    let core = CoreHandle::new(tx).with_timeout(Duration::from_millis(50));
+    assert_eq!(
+        core.try_auth_check_role(&None, "admin").await,
+        Err(CoreError::Timeout)
+    );
+}
+
+#[tokio::test]
+async fn rejected_writes_are_reported_as_denied() {
+    let (tx, mut rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                CoreMessage::DbSetItem { reply, .. } => {
+                    let _ = reply.send(u64::MAX);
+                }
+                CoreMessage::SecretSet { reply, .. } => {
+                    let _ = reply.send(Err("duplicate name".to_string()));
+                }
+                _ => {}
+            }
+        }
+    });
+    let core = CoreHandle::new(tx);
+
+    assert!(matches!(
+        core.try_db_set_item("col", &Item::new(), false).await,
+        Err(CoreError::Denied(_))
+    ));
+    assert_eq!(core.db_set_item("col", &Item::new(), false).await, u64::MAX);
+    assert_eq!(
+        core.try_secret_set(&Item::new(), false).await,
+        Err(CoreError::Denied("duplicate name".to_string()))
+    );
+    assert_eq!(
+        core.secret_set(&Item::new(), false).await,
+        Err("duplicate name".to_string())
+    );
+}
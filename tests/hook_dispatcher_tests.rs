@@ -0,0 +1,354 @@
+mod common;
+
+use common::spawn_plugin;
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::WebResponse;
+use isabelle_plugin_api::manifest::{HookKind, PluginManifest};
+use isabelle_plugin_api::request::RequestContext;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+fn get(url: &str) -> RequestContext {
+    RequestContext::new("h", &None, "GET", url)
+}
+
+fn new_log() -> Arc<Mutex<Vec<String>>> {
+    Arc::new(Mutex::new(Vec::new()))
+}
+
+#[tokio::test]
+async fn pre_edit_threads_modified_item_through_plugins() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    let mut item = Item::new();
+    let res = HookDispatcher::new(&reg)
+        .item_pre_edit(
+            "h",
+            &None,
+            "col",
+            &None,
+            &mut item,
+            DataObjectAction::Add,
+            false,
+        )
+        .await;
+    assert!(res.succeeded);
+    assert_eq!(item.strs.get("seen").unwrap(), "ab");
+}
+
+#[tokio::test]
+async fn pre_edit_rejection_short_circuits() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", false, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    let mut item = Item::new();
+    let res = HookDispatcher::new(&reg)
+        .item_pre_edit(
+            "h",
+            &None,
+            "col",
+            &None,
+            &mut item,
+            DataObjectAction::Add,
+            false,
+        )
+        .await;
+    assert!(!res.succeeded);
+    assert_eq!(res.error, "a");
+    assert_eq!(*log.lock().unwrap(), vec!["a:pre_edit".to_string()]);
+}
+
+#[tokio::test]
+async fn auth_denial_short_circuits() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    spawn_plugin(&mut reg, "b", false, "", log.clone());
+    spawn_plugin(&mut reg, "c", true, "", log.clone());
+
+    let allowed = HookDispatcher::new(&reg)
+        .item_auth("h", &None, "col", 1, &None, false)
+        .await;
+    assert!(!allowed);
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["a:auth".to_string(), "b:auth".to_string()]
+    );
+}
+
+#[tokio::test]
+async fn auth_with_no_plugins_allows() {
+    let reg = PluginRegistry::new();
+    assert!(
+        HookDispatcher::new(&reg)
+            .item_auth("h", &None, "col", 1, &None, false)
+            .await
+    );
+}
+
+#[tokio::test]
+async fn list_filter_chains_pages() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    let mut items: HashMap<u64, Item> = (1..=3).map(|id| (id, Item::new())).collect();
+    HookDispatcher::new(&reg)
+        .item_list_filter("h", &None, "col", "ctx", &mut items)
+        .await;
+    // Each plugin drops the lowest id it was given.
+    assert_eq!(items.keys().copied().collect::<Vec<_>>(), vec![3]);
+}
+
+#[tokio::test]
+async fn db_filters_are_and_combined() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, r#"{"a": 1}"#, log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+    spawn_plugin(&mut reg, "c", true, r#"{"c": 2}"#, log.clone());
+
+    let filter = HookDispatcher::new(&reg)
+        .item_list_db_filter("h", &None, "col", "ctx", "list")
+        .await;
+    let v: serde_json::Value = serde_json::from_str(&filter).unwrap();
+    assert_eq!(v, serde_json::json!({ "$and": [{ "a": 1 }, { "c": 2 }] }));
+}
+
+#[tokio::test]
+async fn single_db_filter_is_passed_through() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, r#"{"b": 1}"#, log.clone());
+
+    let filter = HookDispatcher::new(&reg)
+        .item_list_db_filter("h", &None, "col", "ctx", "list")
+        .await;
+    let v: serde_json::Value = serde_json::from_str(&filter).unwrap();
+    assert_eq!(v, serde_json::json!({ "b": 1 }));
+}
+
+#[tokio::test]
+async fn invalid_db_filter_denies_everything() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, r#"{"a": 1}"#, log.clone());
+    spawn_plugin(&mut reg, "b", true, "not json", log.clone());
+
+    let filter = HookDispatcher::new(&reg)
+        .item_list_db_filter("h", &None, "col", "ctx", "list")
+        .await;
+    let v: serde_json::Value = serde_json::from_str(&filter).unwrap();
+    let deny: serde_json::Value = serde_json::from_str(DENY_ALL_DB_FILTER).unwrap();
+    assert_eq!(v, serde_json::json!({ "$and": [{ "a": 1 }, deny] }));
+}
+
+#[tokio::test]
+async fn route_uses_first_implemented_response() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", false, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    let resp = HookDispatcher::new(&reg).route_url(&get("/x")).await;
+    assert!(matches!(resp, WebResponse::OkData(ref s) if s == "b"));
+}
+
+#[tokio::test]
+async fn stopped_plugin_fails_closed_and_is_marked_dead() {
+    let mut reg = PluginRegistry::new();
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    reg.add("dead", tx);
+
+    let dispatcher = HookDispatcher::new(&reg);
+    assert!(
+        !dispatcher
+            .item_auth("h", &None, "col", 1, &None, false)
+            .await
+    );
+    assert_eq!(reg.health("dead"), Some(PluginHealth::Dead));
+    assert_eq!(dispatcher.ping().await, 0);
+    assert!(matches!(
+        dispatcher.route_url(&get("/x")).await,
+        WebResponse::ServiceUnavailable
+    ));
+}
+
+/// Register a plugin that accepts hooks but never answers them.
+fn add_silent_plugin(reg: &mut PluginRegistry, name: &str) {
+    let (tx, mut rx) = mpsc::channel::<PluginHookMessage>(8);
+    tokio::spawn(async move {
+        let mut pending = Vec::new();
+        while let Some(msg) = rx.recv().await {
+            pending.push(msg);
+        }
+    });
+    reg.add(name, tx);
+}
+
+#[tokio::test(start_paused = true)]
+async fn silent_plugin_times_out_with_fallbacks() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    add_silent_plugin(&mut reg, "silent");
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    let timeouts = HookTimeouts {
+        auth: Duration::from_millis(100),
+        pre_edit: Duration::from_millis(100),
+        route: Duration::from_millis(100),
+        ..HookTimeouts::default()
+    };
+    let dispatcher = HookDispatcher::new(&reg).with_timeouts(timeouts);
+
+    assert!(
+        !dispatcher
+            .item_auth("h", &None, "col", 1, &None, false)
+            .await
+    );
+    assert_eq!(reg.health("silent"), Some(PluginHealth::Unresponsive));
+    assert_eq!(reg.health("b"), Some(PluginHealth::Healthy));
+
+    let mut item = Item::new();
+    let res = dispatcher
+        .item_pre_edit(
+            "h",
+            &None,
+            "col",
+            &None,
+            &mut item,
+            DataObjectAction::Add,
+            false,
+        )
+        .await;
+    assert!(!res.succeeded);
+    assert!(log.lock().unwrap().is_empty());
+
+    // Another plugin handling the route wins over the unresponsive one.
+    let resp = dispatcher.route_url(&get("/x")).await;
+    assert!(matches!(resp, WebResponse::OkData(ref s) if s == "b"));
+}
+
+#[tokio::test]
+async fn health_report_lists_plugins_in_order() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    reg.add("dead", tx);
+
+    HookDispatcher::new(&reg).ping().await;
+    assert_eq!(
+        reg.health_report(),
+        vec![
+            ("a".to_string(), PluginHealth::Healthy),
+            ("dead".to_string(), PluginHealth::Dead),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn post_edit_carries_persisted_item_user_and_merge() {
+    let (seen_tx, mut seen) = mpsc::channel(1);
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let PluginHookMessage::ItemPostEdit {
+                user,
+                old_item,
+                new_item,
+                id,
+                merge,
+                ..
+            } = msg
+            {
+                let _ = seen_tx.send((user, old_item, new_item, id, merge)).await;
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add("audit", tx);
+    let dispatcher = HookDispatcher::new(&reg);
+
+    let mut user = Item::new();
+    user.id = 1;
+    let mut old = Item::new();
+    old.id = 5;
+    let mut new = old.clone();
+    new.strs.insert("title".to_string(), "after".to_string());
+    dispatcher
+        .item_post_edit(
+            "h",
+            &Some(user),
+            "notes",
+            &Some(old),
+            &Some(new),
+            5,
+            DataObjectAction::Add,
+            true,
+        )
+        .await;
+
+    let (user, old_item, new_item, id, merge) = seen.recv().await.unwrap();
+    assert_eq!(user.map(|u| u.id), Some(1));
+    assert!(old_item.unwrap().strs.is_empty());
+    assert_eq!(new_item.unwrap().strs["title"], "after");
+    assert_eq!(id, 5);
+    assert!(merge);
+}
+
+#[tokio::test]
+async fn hooks_are_only_sent_to_subscribed_plugins() {
+    let log = new_log();
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "all", true, "", log.clone());
+
+    let mut scoped = PluginRegistry::new();
+    spawn_plugin(&mut scoped, "pre-edit-only", true, "", log.clone());
+    spawn_plugin(&mut scoped, "invoice-only", true, "", log.clone());
+    // Re-register the scoped plugins with manifests.
+    let mut senders = scoped.senders().cloned();
+    reg.add_with_manifest(
+        PluginManifest::new("pre-edit-only", "1.0.0").hooks([HookKind::ItemPreEdit]),
+        senders.next().unwrap(),
+    );
+    reg.add_with_manifest(
+        PluginManifest::new("invoice-only", "1.0.0").collections(["invoice"]),
+        senders.next().unwrap(),
+    );
+
+    let dispatcher = HookDispatcher::new(&reg);
+    assert!(
+        dispatcher
+            .item_auth("h", &None, "user", 1, &None, false)
+            .await
+    );
+    assert_eq!(*log.lock().unwrap(), vec!["all:auth".to_string()]);
+
+    log.lock().unwrap().clear();
+    assert!(
+        dispatcher
+            .item_auth("h", &None, "invoice", 1, &None, false)
+            .await
+    );
+    assert_eq!(
+        *log.lock().unwrap(),
+        vec!["all:auth".to_string(), "invoice-only:auth".to_string()]
+    );
+
+    let names: Vec<_> = reg.manifests().map(|m| m.name.clone()).collect();
+    assert_eq!(names, vec!["all", "pre-edit-only", "invoice-only"]);
+}
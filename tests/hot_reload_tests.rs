@@ -0,0 +1,249 @@
+mod common;
+
+use common::{install_example_library, spawn_plugin, test_dir};
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::plugin_pool::{
+    PluginDirWatcher, PluginFileChange, PluginLibraries, PluginPool,
+};
+use std::fs;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+
+#[tokio::test]
+async fn unload_drains_queued_hooks_then_stops_plugin() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+    spawn_plugin(&mut reg, "b", true, "", log.clone());
+
+    // Queue a ping whose reply is still pending when unload is called.
+    let sender = reg.senders().next().unwrap().clone();
+    let (reply, pending) = tokio::sync::oneshot::channel();
+    sender
+        .send(PluginHookMessage::Ping { reply })
+        .await
+        .unwrap();
+
+    assert_eq!(reg.unload("a", Duration::from_secs(1)).await, 1);
+    assert!(pending.await.is_ok());
+    assert!(sender.is_closed());
+    assert_eq!(*log.lock().unwrap(), vec!["a:ping", "a:shutdown"]);
+    assert_eq!(
+        reg.health_report(),
+        vec![("b".to_string(), PluginHealth::Healthy)]
+    );
+    assert_eq!(HookDispatcher::new(&reg).ping().await, 1);
+}
+
+#[tokio::test(start_paused = true)]
+async fn unload_gives_up_on_plugin_that_ignores_shutdown() {
+    let mut reg = PluginRegistry::new();
+    let (tx, rx) = mpsc::channel(1);
+    reg.add("stuck", tx);
+
+    assert_eq!(reg.unload("stuck", Duration::from_millis(50)).await, 1);
+    assert!(reg.is_empty());
+    drop(rx);
+}
+
+#[tokio::test]
+async fn unload_of_unknown_name_or_library_removes_nothing() {
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "a", true, "", log.clone());
+
+    assert_eq!(reg.unload("nope", Duration::from_secs(1)).await, 0);
+    assert_eq!(
+        reg.unload_library("/nonexistent/lib.so", Duration::from_secs(1))
+            .await,
+        0
+    );
+    assert_eq!(reg.len(), 1);
+    assert_eq!(reg.libraries().count(), 0);
+    assert!(log.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn registry_reload_reports_invalid_library() {
+    let dir = test_dir("registry");
+    let file = dir.join(format!("{}bogus.so", ACTOR_PLUGIN_PREFIX));
+    fs::write(&file, b"not a real shared library").unwrap();
+    let (tx, _rx) = mpsc::channel(1);
+
+    let mut reg = PluginRegistry::new();
+    let result = reg
+        .reload_library(
+            file.to_str().unwrap(),
+            &CoreHandle::new(tx),
+            Duration::from_secs(1),
+        )
+        .await;
+    assert!(reg.is_empty());
+    assert_eq!(result.considered, 1);
+    assert_eq!(result.loaded, 0);
+    assert_eq!(result.failed(), 1);
+    assert_eq!(result.failures[0].path, file.to_str().unwrap());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pool_load_and_unload_library_report_failures() {
+    let dir = test_dir("pool");
+    let file = dir.join("libisabelle_plugin_bogus.so");
+    fs::write(&file, b"not a real shared library").unwrap();
+
+    let mut pool = PluginPool::new();
+    let mut libraries = PluginLibraries::new();
+    let result = libraries.load_library(&mut pool, file.to_str().unwrap());
+    assert_eq!(result.considered, 1);
+    assert_eq!(result.failed(), 1);
+    assert!(result.failures[0]
+        .error
+        .contains("Failed to load plugin library"));
+    assert_eq!(libraries.libraries().count(), 0);
+    assert_eq!(
+        libraries.unload_library(&mut pool, file.to_str().unwrap()),
+        0
+    );
+
+    let removed = libraries.apply_change(
+        &mut pool,
+        &PluginFileChange::Removed(file.to_string_lossy().to_string()),
+    );
+    assert_eq!(removed.considered, 0);
+    assert!(removed.is_ok());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn registry_reload_hands_over_to_the_new_library() {
+    let dir = test_dir("registry-example");
+    let file = format!("{}example.so", ACTOR_PLUGIN_PREFIX);
+    let path = install_example_library("actor_plugin", &dir, &file);
+    let (tx, _rx) = mpsc::channel(1);
+    let core = CoreHandle::new(tx);
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_library(&path, &core);
+    assert_eq!(result.loaded, 1);
+    assert!(result.is_ok());
+    let old = reg.senders().next().unwrap().clone();
+
+    // The old plugin is stopped before the replaced file registers anew.
+    install_example_library("actor_plugin", &dir, &file);
+    let result = reg
+        .apply_change(
+            &PluginFileChange::Modified(path.clone()),
+            &core,
+            Duration::from_secs(1),
+        )
+        .await;
+    assert_eq!(result.loaded, 1);
+    assert!(result.is_ok());
+    assert!(old.is_closed());
+    assert_eq!(reg.len(), 1);
+    assert_eq!(reg.libraries().collect::<Vec<_>>(), vec![path.as_str()]);
+    assert_eq!(HookDispatcher::new(&reg).ping().await, 1);
+
+    let result = reg
+        .apply_change(
+            &PluginFileChange::Removed(path.clone()),
+            &core,
+            Duration::from_secs(1),
+        )
+        .await;
+    assert!(result.is_ok());
+    assert!(reg.is_empty());
+    assert_eq!(reg.libraries().count(), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn pool_loads_reloads_and_unloads_a_library() {
+    let dir = test_dir("pool-example");
+    let path = install_example_library("legacy_plugin", &dir, "libisabelle_plugin_example.so");
+
+    let mut pool = PluginPool::new();
+    let mut libraries = PluginLibraries::new();
+    let result = libraries.load_plugins(&mut pool, dir.to_str().unwrap());
+    assert_eq!(result.considered, 1);
+    assert_eq!(result.loaded, 1);
+    assert!(result.is_ok());
+    assert_eq!(pool.manifests()[0].name, "legacy-example");
+    assert_eq!(
+        libraries.libraries().collect::<Vec<_>>(),
+        vec![path.as_str()]
+    );
+
+    // Rescanning skips the library that is already loaded.
+    let again = libraries.load_plugins(&mut pool, dir.to_str().unwrap());
+    assert_eq!(again.considered, 0);
+    assert_eq!(again.loaded, 0);
+    assert_eq!(pool.plugins.len(), 1);
+
+    // The replaced file's plugin takes the place of the old one.
+    install_example_library("legacy_plugin", &dir, "libisabelle_plugin_example.so");
+    let result = libraries.apply_change(&mut pool, &PluginFileChange::Modified(path.clone()));
+    assert_eq!(result.loaded, 1);
+    assert!(result.is_ok());
+    assert_eq!(pool.plugins.len(), 1);
+    pool.ping_plugins();
+    assert_eq!(pool.manifests()[0].name, "legacy-example");
+
+    assert_eq!(libraries.unload_library(&mut pool, &path), 1);
+    assert!(pool.plugins.is_empty());
+    assert_eq!(libraries.libraries().count(), 0);
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn watcher_reports_added_modified_and_removed_files() {
+    let dir = test_dir("watch");
+    let existing = dir.join("libisabelle_plugin_old.so");
+    fs::write(&existing, b"old").unwrap();
+    fs::write(dir.join("README.txt"), b"ignored").unwrap();
+
+    let mut watcher = PluginDirWatcher::new(dir.to_str().unwrap(), "libisabelle_plugin_");
+    assert!(watcher.poll().is_empty());
+
+    let added = dir.join("libisabelle_plugin_new.so");
+    fs::write(&added, b"new").unwrap();
+    fs::write(dir.join("notes.txt"), b"ignored").unwrap();
+    assert_eq!(
+        watcher.poll(),
+        vec![PluginFileChange::Added(added.to_string_lossy().to_string())]
+    );
+
+    fs::File::options()
+        .write(true)
+        .open(&existing)
+        .unwrap()
+        .set_modified(SystemTime::now() + Duration::from_secs(60))
+        .unwrap();
+    fs::remove_file(&added).unwrap();
+    assert_eq!(
+        watcher.poll(),
+        vec![
+            PluginFileChange::Removed(added.to_string_lossy().to_string()),
+            PluginFileChange::Modified(existing.to_string_lossy().to_string()),
+        ]
+    );
+    assert!(watcher.poll().is_empty());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn watcher_ignores_unreadable_directory() {
+    let dir = test_dir("watch-gone");
+    fs::write(dir.join("libisabelle_plugin_a.so"), b"a").unwrap();
+    let mut watcher = PluginDirWatcher::new(dir.to_str().unwrap(), "libisabelle_plugin_");
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert!(watcher.poll().is_empty());
+}
@@ -0,0 +1,116 @@
+#![cfg(feature = "testing")]
+
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::list_result::ListResult;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::query::{Field, Filter};
+use isabelle_plugin_api::testing::MockCore;
+use tokio::sync::mpsc;
+
+fn seeded(count: u64) -> MockCore {
+    let core = MockCore::new();
+    for id in 1..=count {
+        let mut item = Item::new();
+        item.id = id * 2;
+        item.bools.insert("even".to_string(), id % 2 == 0);
+        core.insert("things", item);
+    }
+    core
+}
+
+#[tokio::test]
+async fn stream_pages_through_collection_in_id_order() {
+    let core = seeded(25);
+    let handle = core.start();
+
+    let mut stream = handle.db_stream_items("things", &Filter::all(), 10);
+    let mut ids = Vec::new();
+    while let Some(item) = stream.next().await {
+        ids.push(item.unwrap().id);
+    }
+    assert_eq!(ids, (1..=25).map(|i| i * 2).collect::<Vec<_>>());
+    assert!(stream.next().await.is_none());
+
+    // 10 + 10 + 5 items; the empty fourth page ends the stream.
+    core.assert_called_times("DbGetItems", 4);
+}
+
+#[tokio::test]
+async fn stream_applies_filter_and_survives_concurrent_deletes() {
+    let core = seeded(30);
+    let handle = core.start();
+
+    let mut stream = handle.db_stream_items("things", &Filter::eq(Field::bool("even"), true), 4);
+    let mut ids = Vec::new();
+    while let Some(item) = stream.next().await {
+        let item = item.unwrap();
+        if ids.is_empty() {
+            // Page one (4..=16) is buffered; deleting a seen item and one
+            // from page two must not make the cursor skip anything.
+            handle.db_del_item("things", 4).await;
+            handle.db_del_item("things", 20).await;
+        }
+        ids.push(item.id);
+    }
+    let expected: Vec<u64> = (1..=30)
+        .filter(|i| i % 2 == 0)
+        .map(|i| i * 2)
+        .filter(|id| *id != 20)
+        .collect();
+    assert_eq!(ids, expected);
+}
+
+#[tokio::test]
+async fn stream_of_empty_collection_ends_immediately() {
+    let core = MockCore::new();
+    let handle = core.start();
+
+    let mut stream = handle.db_stream_items("nothing", &Filter::all(), 0);
+    assert!(stream.next().await.is_none());
+    core.assert_called_times("DbGetItems", 1);
+}
+
+#[tokio::test]
+async fn stream_stops_and_reports_core_failure() {
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    let handle = CoreHandle::new(tx);
+
+    let mut stream = handle.db_stream_items("things", &Filter::all(), 10);
+    assert!(matches!(
+        stream.next().await,
+        Some(Err(CoreError::CoreShutdown))
+    ));
+    assert!(stream.next().await.is_none());
+}
+
+#[tokio::test]
+async fn stream_continues_when_core_caps_page_size() {
+    // Core that returns at most 3 items per page, whatever the limit.
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let CoreMessage::DbGetItems { id_min, reply, .. } = msg {
+                let map = (id_min.max(1)..=10)
+                    .take(3)
+                    .map(|id| {
+                        let mut item = Item::new();
+                        item.id = id;
+                        (id, item)
+                    })
+                    .collect();
+                let _ = reply.send(ListResult {
+                    map,
+                    total_count: 10,
+                });
+            }
+        }
+    });
+
+    let mut stream = CoreHandle::new(tx).db_stream_items("things", &Filter::all(), 10);
+    let mut ids = Vec::new();
+    while let Some(item) = stream.next().await {
+        ids.push(item.unwrap().id);
+    }
+    assert_eq!(ids, (1..=10).collect::<Vec<_>>());
+}
@@ -0,0 +1,364 @@
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_dm::data_model::process_result::ProcessResult;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::*;
+use std::collections::HashMap;
+use tokio::sync::mpsc;
+
+/// Core that knows a single item (id 7 in "col") and treats every user as
+/// holding the "admin" role.
+fn spawn_core() -> CoreHandle {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                CoreMessage::DbGetItem { id, reply, .. } => {
+                    let found = (id == 7).then(|| {
+                        let mut itm = Item::new();
+                        itm.id = 7;
+                        itm
+                    });
+                    let _ = reply.send(found);
+                }
+                CoreMessage::AuthCheckRole { role, reply, .. } => {
+                    let _ = reply.send(role == "admin");
+                }
+                _ => {}
+            }
+        }
+    });
+    CoreHandle::new(tx)
+}
+
+/// Legacy plugin that talks to core through `PluginApi` and counts
+/// pre-edits in its per-hndl state.
+struct ApiPlugin;
+
+impl Plugin for ApiPlugin {
+    fn ping_test(&mut self) {}
+
+    fn item_pre_edit_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        hndl: &str,
+        _user: &Option<Item>,
+        collection: &str,
+        _old_itm: Option<Item>,
+        itm: &mut Item,
+        _action: DataObjectAction,
+        _merge: bool,
+    ) -> ProcessResult {
+        let state = api.fn_get_state(hndl);
+        let count = state
+            .as_ref()
+            .and_then(|s| s.downcast_ref::<u64>())
+            .copied()
+            .unwrap_or(0)
+            + 1;
+        *state = Some(Box::new(count));
+        itm.u64s.insert("edits".to_string(), count);
+
+        let exists = api.db_get_item(collection, itm.id).is_some();
+        ProcessResult {
+            succeeded: exists,
+            data: HashMap::new(),
+            error: if exists { "" } else { "no such item" }.to_string(),
+        }
+    }
+
+    /// Mails what it was told to "audit".
+    fn item_post_edit_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        user: &Option<Item>,
+        collection: &str,
+        old_itm: Option<Item>,
+        new_itm: Option<Item>,
+        id: u64,
+        _action: DataObjectAction,
+        merge: bool,
+    ) {
+        let name = new_itm
+            .and_then(|itm| itm.strs.get("name").cloned())
+            .unwrap_or_default();
+        let body = format!(
+            "user={} old={} name={} merge={}",
+            user.as_ref().map(|u| u.id).unwrap_or(0),
+            old_itm.is_some(),
+            name,
+            merge
+        );
+        api.fn_send_email("audit", &format!("{}/{}", collection, id), &body);
+    }
+
+    fn item_auth_hook(
+        &mut self,
+        api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        user: &Option<Item>,
+        _collection: &str,
+        _id: u64,
+        _new_item: Option<Item>,
+        _del: bool,
+    ) -> bool {
+        api.auth_check_role(user, "admin")
+    }
+
+    fn item_list_filter_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _user: &Option<Item>,
+        _collection: &str,
+        _context: &str,
+        map: &mut HashMap<u64, Item>,
+    ) {
+        map.clear();
+    }
+
+    fn route_url_hook(&mut self, _api: &Box<dyn PluginApi>, ctx: &RequestContext) -> WebResponse {
+        let page = ctx.query_param("page").unwrap_or("1");
+        WebResponse::OkData(format!("{} page {}", ctx.path, page))
+    }
+
+    fn route_unprotected_url_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _ctx: &RequestContext,
+    ) -> WebResponse {
+        WebResponse::NotImplemented
+    }
+
+    fn route_unprotected_url_post_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _ctx: &RequestContext,
+        _itm: &Item,
+        _parts: &[UploadPart],
+    ) -> WebResponse {
+        WebResponse::NotImplemented
+    }
+
+    fn collection_read_hook(
+        &mut self,
+        _api: &Box<dyn PluginApi>,
+        _hndl: &str,
+        _collection: &str,
+        _itm: &mut Item,
+    ) -> bool {
+        false
+    }
+
+    fn call_otp_hook(&mut self, _api: &Box<dyn PluginApi>, _hndl: &str, _itm: &Item) {}
+}
+
+#[tokio::test]
+async fn legacy_plugin_answers_hooks_through_core_handle() {
+    let core = spawn_core();
+    let mut reg = PluginRegistry::new();
+    reg.add_legacy("api", Box::new(ApiPlugin), core.clone());
+    let dispatcher = HookDispatcher::new(&reg);
+
+    assert!(
+        dispatcher
+            .item_auth("h", &None, "col", 7, &None, false)
+            .await
+    );
+
+    let mut item = Item::new();
+    item.id = 7;
+    for expected in 1..=2 {
+        let res = dispatcher
+            .item_pre_edit(
+                "h",
+                &None,
+                "col",
+                &None,
+                &mut item,
+                DataObjectAction::Add,
+                true,
+            )
+            .await;
+        assert!(res.succeeded);
+        assert_eq!(item.u64s.get("edits"), Some(&expected));
+    }
+
+    item.id = 8;
+    let res = dispatcher
+        .item_pre_edit(
+            "h",
+            &None,
+            "col",
+            &None,
+            &mut item,
+            DataObjectAction::Add,
+            true,
+        )
+        .await;
+    assert!(!res.succeeded);
+    assert_eq!(res.error, "no such item");
+
+    let ctx = RequestContext::new("h", &None, "GET", "/q?page=2");
+    let resp = dispatcher.route_url(&ctx).await;
+    assert!(matches!(resp, WebResponse::OkData(ref s) if s == "/q page 2"));
+    // Legacy plugins can't hold connections open.
+    assert!(matches!(
+        dispatcher.websocket_connect(&ctx, true).await,
+        Err(WebResponse::NotImplemented)
+    ));
+
+    reg.shutdown_all().await;
+}
+
+#[tokio::test]
+async fn legacy_post_edit_gets_new_item_user_and_merge() {
+    let (tx, mut rx) = mpsc::channel(8);
+    let mut reg = PluginRegistry::new();
+    reg.add_legacy("api", Box::new(ApiPlugin), CoreHandle::new(tx));
+    let dispatcher = HookDispatcher::new(&reg);
+
+    let mut user = Item::new();
+    user.id = 3;
+    let mut item = Item::new();
+    item.id = 7;
+    item.strs.insert("name".to_string(), "report".to_string());
+    dispatcher
+        .item_post_edit(
+            "h",
+            &Some(user),
+            "col",
+            &None,
+            &Some(item),
+            7,
+            DataObjectAction::Add,
+            true,
+        )
+        .await;
+
+    match rx.recv().await {
+        Some(CoreMessage::SendEmail { to, subject, body }) => {
+            assert_eq!(to, "audit");
+            assert_eq!(subject, "col/7");
+            assert_eq!(body, "user=3 old=false name=report merge=true");
+        }
+        _ => panic!("expected the plugin's email"),
+    }
+}
+
+#[tokio::test]
+async fn legacy_and_actor_plugins_share_a_registry() {
+    let core = spawn_core();
+    let mut reg = PluginRegistry::new();
+    reg.add_legacy("api", Box::new(ApiPlugin), core.clone());
+    reg.add_legacy("api-2", Box::new(ApiPlugin), core);
+
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let PluginHookMessage::Ping { reply } = msg {
+                let _ = reply.send(());
+            }
+        }
+    });
+    reg.add("actor", tx);
+
+    let dispatcher = HookDispatcher::new(&reg);
+    assert_eq!(dispatcher.ping().await, 3);
+
+    let mut items: HashMap<u64, Item> = (1..=3).map(|id| (id, Item::new())).collect();
+    dispatcher
+        .item_list_filter("h", &None, "col", "ctx", &mut items)
+        .await;
+    assert!(items.is_empty());
+}
+
+#[tokio::test]
+async fn core_handle_api_serves_plugin_pool() {
+    use isabelle_plugin_api::legacy::CoreHandleApi;
+    use isabelle_plugin_api::plugin_pool::PluginPool;
+
+    let core = spawn_core();
+    let mut pool = PluginPool {
+        plugins: Vec::new(),
+    };
+    pool.register(Box::new(ApiPlugin));
+
+    let (allowed, found, missing) = tokio::task::spawn_blocking(move || {
+        let api: Box<dyn PluginApi> = Box::new(CoreHandleApi::new(core));
+        let allowed = pool.plugins[0].item_auth_hook(&api, "h", &None, "col", 7, None, false);
+        (
+            allowed,
+            api.db_get_item("col", 7).is_some(),
+            api.db_get_item("col", 8).is_none(),
+        )
+    })
+    .await
+    .unwrap();
+    assert!(allowed);
+    assert!(found);
+    assert!(missing);
+}
+
+#[test]
+fn core_handle_api_gives_up_on_stalled_core() {
+    use isabelle_plugin_api::legacy::CoreHandleApi;
+    use std::time::{Duration, Instant};
+
+    // Core receives requests but never answers.
+    let (tx, _rx) = mpsc::channel(1);
+    let api = CoreHandleApi::new(CoreHandle::new(tx).with_timeout(Duration::from_millis(50)));
+    let start = Instant::now();
+    assert!(api.db_get_item("col", 1).is_none());
+    // The queue is full now, so the send itself times out.
+    assert!(!api.auth_check_role(&None, "admin"));
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn core_handle_api_falls_back_when_core_is_gone() {
+    use isabelle_plugin_api::legacy::CoreHandleApi;
+
+    let (tx, rx) = mpsc::channel(1);
+    drop(rx);
+    let api = CoreHandleApi::new(CoreHandle::new(tx));
+    assert!(api.db_get_item("col", 1).is_none());
+    assert_eq!(api.db_set_item("col", &Item::new(), false), u64::MAX);
+    assert!(!api.auth_check_role(&None, "admin"));
+    assert!(!api.auth_login("u", "p").succeeded);
+    assert_eq!(
+        api.secret_set(&Item::new(), false),
+        Err("core unavailable".to_string())
+    );
+
+    api.fn_set_state("h", Some(Box::new(5u64)));
+    let state = api.fn_get_state("h");
+    assert_eq!(state.as_ref().unwrap().downcast_ref::<u64>(), Some(&5));
+}
+
+#[test]
+fn core_handle_api_refuses_overlapping_state_borrows() {
+    use isabelle_plugin_api::legacy::CoreHandleApi;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    let (tx, _rx) = mpsc::channel(1);
+    let api = CoreHandleApi::new(CoreHandle::new(tx));
+    let borrows = api.state_borrows();
+    *api.fn_get_state("a") = Some(Box::new(1u64));
+    *api.fn_get_state("b") = Some(Box::new(2u64));
+
+    let again = catch_unwind(AssertUnwindSafe(|| {
+        api.fn_get_state("a");
+    }));
+    assert!(again.is_err());
+    let set = catch_unwind(AssertUnwindSafe(|| api.fn_set_state("b", None)));
+    assert!(set.is_err());
+
+    // SAFETY: the references handed out above are gone.
+    unsafe { borrows.release() };
+    api.fn_set_state("a", None);
+    assert!(api.fn_get_state("a").is_none());
+    let b = api.fn_get_state("b");
+    assert_eq!(b.as_ref().unwrap().downcast_ref::<u64>(), Some(&2));
+}
@@ -0,0 +1,275 @@
+use actix_web::body::to_bytes;
+use actix_web::HttpResponse;
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::WebResponse;
+use isabelle_plugin_api::live::*;
+use isabelle_plugin_api::manifest::PluginManifest;
+use isabelle_plugin_api::request::RequestContext;
+use isabelle_plugin_api::route::RouteSpec;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::timeout;
+
+#[test]
+fn sse_events_render_as_frames() {
+    assert_eq!(SseEvent::new("hi").to_frame(), "data: hi\n\n");
+    assert_eq!(
+        SseEvent::new("a\nb").event("tick").id("7").to_frame(),
+        "event: tick\nid: 7\ndata: a\ndata: b\n\n"
+    );
+    assert_eq!(
+        SseEvent::new("a\r\nb\rc\n")
+            .event("tick\ndata: x")
+            .id("7\r\nevent: y")
+            .to_frame(),
+        "event: tickdata: x\nid: 7event: y\ndata: a\ndata: b\ndata: c\ndata: \n\n"
+    );
+}
+
+/// Dashboard plugin: echoes WebSocket text prefixed with the user id,
+/// streams two events to SSE clients, refuses "/private" and reports
+/// closed connections on `closed`.
+fn spawn_dashboard(reg: &mut PluginRegistry, closed: mpsc::UnboundedSender<ConnectionId>) {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                PluginHookMessage::WebSocketConnect { ctx, conn, reply } => {
+                    if ctx.path == "/private" {
+                        let _ = reply.send(WebResponse::Forbidden);
+                        continue;
+                    }
+                    let _ = reply.send(WebResponse::Ok);
+                    let user = ctx.user.map_or(0, |u| u.id);
+                    let WebSocketConn {
+                        id,
+                        outgoing,
+                        mut incoming,
+                    } = conn;
+                    tokio::spawn(async move {
+                        while let Some(WsMessage::Text(text)) = incoming.recv().await {
+                            let reply = format!("{}/{}: {}", id, user, text);
+                            let _ = outgoing.send(WsMessage::Text(reply)).await;
+                        }
+                    });
+                }
+                PluginHookMessage::SseConnect { conn, reply, .. } => {
+                    let _ = reply.send(WebResponse::Ok);
+                    let _ = conn.events.send(SseEvent::new("one")).await;
+                    let _ = conn.events.send(SseEvent::new("two").event("tick")).await;
+                }
+                PluginHookMessage::ConnectionClosed { id } => {
+                    let _ = closed.send(id);
+                }
+                _ => {}
+            }
+        }
+    });
+    reg.add_with_manifest(
+        PluginManifest::new("dashboard", "1.0.0")
+            .route(RouteSpec::get("/live"))
+            .route(RouteSpec::get("/private")),
+        tx,
+    );
+}
+
+async fn next_closed(closed: &mut mpsc::UnboundedReceiver<ConnectionId>) -> ConnectionId {
+    timeout(Duration::from_secs(1), closed.recv())
+        .await
+        .unwrap()
+        .unwrap()
+}
+
+#[tokio::test]
+async fn websocket_messages_flow_both_ways_and_close_is_reported() {
+    let (closed_tx, mut closed) = mpsc::unbounded_channel();
+    let mut reg = PluginRegistry::new();
+    spawn_dashboard(&mut reg, closed_tx);
+    let d = HookDispatcher::new(&reg);
+
+    let mut user = Item::new();
+    user.id = 9;
+    let ctx = RequestContext::new("h", &Some(user), "GET", "/live");
+    let Ok(mut first) = d.websocket_connect(&ctx, true).await else {
+        panic!("connection refused");
+    };
+    let Ok(second) = d.websocket_connect(&ctx, true).await else {
+        panic!("connection refused");
+    };
+    assert_ne!(first.id(), second.id());
+    assert_eq!(first.plugin(), "dashboard");
+
+    first
+        .to_plugin
+        .send(WsMessage::Text("hello".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(
+        first.from_plugin.recv().await,
+        Some(WsMessage::Text(format!("{}/9: hello", first.id())))
+    );
+
+    let id = second.id();
+    drop(second);
+    assert_eq!(next_closed(&mut closed).await, id);
+    let id = first.id();
+    drop(first);
+    assert_eq!(next_closed(&mut closed).await, id);
+}
+
+#[tokio::test]
+async fn sse_sessions_stream_events_until_dropped() {
+    let (closed_tx, mut closed) = mpsc::unbounded_channel();
+    let mut reg = PluginRegistry::new();
+    spawn_dashboard(&mut reg, closed_tx);
+
+    let ctx = RequestContext::new("h", &None, "GET", "/live");
+    let Ok(session) = HookDispatcher::new(&reg).sse_connect(&ctx, true).await else {
+        panic!("connection refused");
+    };
+    let id = session.id();
+
+    let resp = HttpResponse::from(session.into_response());
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/event-stream"
+    );
+    // The plugin dropped its sender after two events, ending the body.
+    let body = to_bytes(resp.into_body()).await.unwrap();
+    assert_eq!(&body[..], b"data: one\n\nevent: tick\ndata: two\n\n");
+    assert_eq!(next_closed(&mut closed).await, id);
+}
+
+#[tokio::test]
+async fn close_is_reported_when_the_plugin_queue_is_full() {
+    let (closed_tx, mut closed) = mpsc::unbounded_channel();
+    let (go_tx, go_rx) = tokio::sync::oneshot::channel::<()>();
+    let (tx, mut rx) = mpsc::channel(1);
+    tokio::spawn(async move {
+        let mut go = Some(go_rx);
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                PluginHookMessage::SseConnect { reply, .. } => {
+                    let _ = reply.send(WebResponse::Ok);
+                    // Stay busy until the test has filled the queue.
+                    if let Some(go) = go.take() {
+                        let _ = go.await;
+                    }
+                }
+                PluginHookMessage::ConnectionClosed { id } => {
+                    let _ = closed_tx.send(id);
+                }
+                _ => {}
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add_with_manifest(
+        PluginManifest::new("busy", "1.0.0").route(RouteSpec::get("/live")),
+        tx.clone(),
+    );
+
+    let ctx = RequestContext::new("h", &None, "GET", "/live");
+    let Ok(session) = HookDispatcher::new(&reg).sse_connect(&ctx, true).await else {
+        panic!("connection refused");
+    };
+    tx.try_send(PluginHookMessage::PeriodicJob {
+        timing: "minute".to_string(),
+    })
+    .unwrap();
+    let id = session.id();
+    drop(session);
+    go_tx.send(()).unwrap();
+    assert_eq!(next_closed(&mut closed).await, id);
+}
+
+#[tokio::test]
+async fn refused_and_unrouted_connections_return_the_response() {
+    let (closed_tx, mut closed) = mpsc::unbounded_channel();
+    let mut reg = PluginRegistry::new();
+    spawn_dashboard(&mut reg, closed_tx);
+    let d = HookDispatcher::new(&reg);
+
+    let private = RequestContext::new("h", &None, "GET", "/private");
+    assert!(matches!(
+        d.websocket_connect(&private, true).await,
+        Err(WebResponse::Forbidden)
+    ));
+    let elsewhere = RequestContext::new("h", &None, "GET", "/elsewhere");
+    assert!(matches!(
+        d.sse_connect(&elsewhere, true).await,
+        Err(WebResponse::NotImplemented)
+    ));
+    // Nothing was accepted, so nothing is closed.
+    assert!(closed.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn unprotected_routes_only_match_unprotected_connections() {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                PluginHookMessage::WebSocketConnect { reply, .. }
+                | PluginHookMessage::SseConnect { reply, .. } => {
+                    let _ = reply.send(WebResponse::Ok);
+                }
+                _ => {}
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add_with_manifest(
+        PluginManifest::new("public", "1.0.0").route(RouteSpec::get("/status").unprotected()),
+        tx,
+    );
+    let d = HookDispatcher::new(&reg);
+
+    let ctx = RequestContext::new("h", &None, "GET", "/status");
+    assert_eq!(
+        d.websocket_connect(&ctx, false)
+            .await
+            .ok()
+            .unwrap()
+            .plugin(),
+        "public"
+    );
+    assert_eq!(
+        d.sse_connect(&ctx, false).await.ok().unwrap().plugin(),
+        "public"
+    );
+    // Declared unprotected, so it doesn't claim protected requests.
+    assert!(d.sse_connect(&ctx, true).await.is_err());
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn harness_connects_with_granted_roles() {
+    use isabelle_plugin_api::testing::PluginHarness;
+
+    let harness = PluginHarness::new(|reg, _core| {
+        let (tx, mut rx) = mpsc::channel(8);
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let PluginHookMessage::SseConnect { reply, .. } = msg {
+                    let _ = reply.send(WebResponse::Ok);
+                }
+            }
+        });
+        reg.add_with_manifest(
+            PluginManifest::new("admin", "1.0.0").route(RouteSpec::get("/feed").role("admin")),
+            tx,
+        );
+    });
+    let mut admin = Item::new();
+    admin.id = 3;
+    let harness = harness.as_user(admin);
+
+    assert!(matches!(
+        harness.sse("/feed").await,
+        Err(WebResponse::Forbidden)
+    ));
+    harness.core().grant_role(3, "admin");
+    assert_eq!(harness.sse("/feed").await.ok().unwrap().plugin(), "admin");
+}
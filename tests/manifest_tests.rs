@@ -0,0 +1,75 @@
+use isabelle_plugin_api::actor::PluginRegistry;
+use isabelle_plugin_api::manifest::*;
+use tokio::sync::mpsc;
+
+#[test]
+fn default_manifest_accepts_everything() {
+    let m = PluginManifest::new("p", "1.0.0");
+    assert!(m.handles(HookKind::ItemAuth));
+    assert!(m.handles(HookKind::RouteRest));
+    assert!(m.interested_in("anything"));
+    assert!(m.accepts(HookKind::ItemPreEdit, Some("user")));
+}
+
+#[test]
+fn hooks_and_collections_restrict_delivery() {
+    let m = PluginManifest::new("invoices", "0.3.1")
+        .author("Jane")
+        .hooks([HookKind::ItemPreEdit, HookKind::RouteUrl])
+        .collections(["invoice", "invoice_line"]);
+
+    assert_eq!(m.author, "Jane");
+    assert!(m.accepts(HookKind::ItemPreEdit, Some("invoice")));
+    assert!(!m.accepts(HookKind::ItemPreEdit, Some("user")));
+    assert!(!m.accepts(HookKind::ItemAuth, Some("invoice")));
+    // Collection interest doesn't apply to non-item hooks.
+    assert!(m.accepts(HookKind::RouteUrl, None));
+}
+
+#[test]
+fn missing_capabilities_lists_unavailable_ones() {
+    let m = PluginManifest::new("mailer", "1.0.0").requires(["email", "secrets"]);
+    assert_eq!(m.missing_capabilities(&["email"]), vec!["secrets"]);
+    assert!(m.missing_capabilities(&["secrets", "email"]).is_empty());
+}
+
+#[test]
+fn validate_checks_version_and_capabilities() {
+    let m = PluginManifest::new("mailer", "1.2.0-rc.1+build5").requires(["email"]);
+    assert!(m.validate(None).is_ok());
+    assert!(m.validate(Some(&["email", "secrets"])).is_ok());
+    let err = m.validate(Some(&["secrets"])).unwrap_err();
+    assert!(err.contains("email"), "{}", err);
+
+    // An unset version is allowed; a malformed one is not.
+    assert!(PluginManifest::new("p", "").validate(None).is_ok());
+    for version in ["1.0", "v1.0.0", "1.0.x", "1.0.0-"] {
+        assert!(
+            PluginManifest::new("p", version).validate(None).is_err(),
+            "{} accepted",
+            version
+        );
+    }
+}
+
+#[tokio::test]
+async fn registry_refuses_plugins_missing_capabilities() {
+    let mut reg = PluginRegistry::new().with_capabilities(["email"]);
+    let (tx, _rx) = mpsc::channel(1);
+    reg.add_with_manifest(
+        PluginManifest::new("mailer", "1.0.0").requires(["email"]),
+        tx,
+    );
+    let (tx, mut rx) = mpsc::channel(1);
+    reg.add_with_manifest(
+        PluginManifest::new("vault", "1.0.0").requires(["secrets"]),
+        tx,
+    );
+    let (tx, _rx) = mpsc::channel(1);
+    reg.add_with_manifest(PluginManifest::new("odd", "one"), tx);
+
+    let names: Vec<_> = reg.manifests().map(|m| m.name.as_str()).collect();
+    assert_eq!(names, vec!["mailer"]);
+    // The refused plugin's sender is dropped, ending its loop.
+    assert!(rx.recv().await.is_none());
+}
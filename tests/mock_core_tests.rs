@@ -0,0 +1,312 @@
+#![cfg(feature = "testing")]
+
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::{item_revision, BatchError, BatchOp, CoreError};
+use isabelle_plugin_api::testing::*;
+
+fn item(id: u64, name: &str, age: u64) -> Item {
+    let mut item = Item::new();
+    item.id = id;
+    item.strs.insert("name".to_string(), name.to_string());
+    item.u64s.insert("age".to_string(), age);
+    item
+}
+
+fn seeded() -> MockCore {
+    let core = MockCore::new();
+    core.insert("users", item(1, "carol", 40));
+    core.insert("users", item(2, "alice", 30));
+    core.insert("users", item(3, "bob", 20));
+    core.insert("users", item(4, "dave", 30));
+    core
+}
+
+#[tokio::test]
+async fn db_queries_filter_sort_skip_and_limit() {
+    let core = seeded();
+    let handle = core.start();
+
+    let all = handle.db_get_all_items("users", "", "").await;
+    assert_eq!(all.total_count, 4);
+
+    let thirty = handle.db_get_all_items("users", "", r#"{"age": 30}"#).await;
+    let mut ids: Vec<_> = thirty.map.keys().copied().collect();
+    ids.sort();
+    assert_eq!(ids, vec![2, 4]);
+
+    // Sorted by name: alice(2), bob(3), carol(1), dave(4); skip 1, take 2.
+    let page = handle
+        .db_get_items("users", 0, u64::MAX, "name", "", 1, 2)
+        .await;
+    assert_eq!(page.total_count, 4);
+    let mut ids: Vec<_> = page.map.keys().copied().collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 3]);
+
+    let ranged = handle
+        .db_get_items(
+            "users",
+            2,
+            3,
+            "",
+            r#"{"$or": [{"name": "bob"}, {"age": {"$gte": 30}}]}"#,
+            0,
+            u64::MAX,
+        )
+        .await;
+    assert_eq!(ranged.total_count, 2);
+
+    let denied = handle
+        .db_get_all_items("users", "", r#"{"_id": {"$in": []}}"#)
+        .await;
+    assert_eq!(denied.total_count, 0);
+    let garbage = handle.db_get_all_items("users", "", "{not json").await;
+    assert_eq!(garbage.total_count, 0);
+    assert_eq!(
+        handle.db_get_all_items("missing", "", "").await.total_count,
+        0
+    );
+
+    core.assert_called_times("DbGetAllItems", 5);
+    core.assert_called_with("DbGetItems", "users");
+}
+
+#[tokio::test]
+async fn db_writes_allocate_merge_and_delete() {
+    let core = seeded();
+    let handle = core.start();
+
+    let id = handle
+        .db_set_item("users", &item(u64::MAX, "erin", 25), false)
+        .await;
+    assert_eq!(id, 5);
+
+    let mut patch = Item::new();
+    patch.id = 2;
+    patch.bools.insert("active".to_string(), true);
+    assert_eq!(handle.db_set_item("users", &patch, true).await, 2);
+    let merged = handle.db_get_item("users", 2).await.unwrap();
+    assert_eq!(merged.strs["name"], "alice");
+    assert!(merged.bools["active"]);
+
+    assert!(handle.db_del_item("users", 3).await);
+    assert!(!handle.db_del_item("users", 3).await);
+    assert!(core.item("users", 3).is_none());
+    assert_eq!(core.items("users").len(), 4);
+
+    core.assert_called_with("DbSetItem", "users/5");
+    core.assert_called_times("DbDelItem", 2);
+    core.assert_not_called("DbGetAllItems");
+}
+
+#[tokio::test]
+async fn db_batch_applies_all_or_nothing() {
+    let core = seeded();
+    let handle = core.start();
+
+    let ids = handle
+        .try_db_batch(vec![
+            BatchOp::set("invoices", &item(u64::MAX, "inv", 0), false),
+            BatchOp::set("lines", &item(u64::MAX, "line", 1), false),
+            BatchOp::del("users", 3),
+        ])
+        .await
+        .unwrap();
+    assert_eq!(ids, vec![5, 6, 3]);
+    assert!(core.item("invoices", 5).is_some());
+    assert!(core.item("users", 3).is_none());
+
+    // The delete of a missing item fails the batch; the writes before it
+    // are rolled back and their ids are handed out again.
+    let err = handle
+        .try_db_batch(vec![
+            BatchOp::set("invoices", &item(u64::MAX, "inv2", 0), false),
+            BatchOp::del("users", 2),
+            BatchOp::del("users", 42),
+        ])
+        .await
+        .unwrap_err();
+    assert_eq!(
+        err,
+        CoreError::BatchFailed(BatchError {
+            index: 2,
+            reason: "no item 42 in users".to_string(),
+        })
+    );
+    assert_eq!(core.items("invoices").len(), 1);
+    assert!(core.item("users", 2).is_some());
+    assert_eq!(
+        handle
+            .db_batch(vec![BatchOp::set("lines", &item(u64::MAX, "x", 0), false)])
+            .await,
+        Some(vec![7])
+    );
+
+    core.assert_called_with("DbBatch", "invoices, users, users");
+    core.assert_not_called("DbSetItem");
+}
+
+#[tokio::test]
+async fn conditional_writes_detect_concurrent_edits() {
+    let core = seeded();
+    let handle = core.start();
+
+    let first = handle.db_get_item("users", 2).await.unwrap();
+    let second = handle.db_get_item("users", 2).await.unwrap();
+    assert_eq!(item_revision(&first), 1);
+
+    let mut edit = first.clone();
+    edit.u64s.insert("age".to_string(), 31);
+    assert_eq!(
+        handle
+            .try_db_set_item_if("users", &edit, true, item_revision(&first))
+            .await,
+        Ok(2)
+    );
+
+    // The second editor still holds revision 1.
+    let mut stale = second.clone();
+    stale.strs.insert("name".to_string(), "alicia".to_string());
+    assert_eq!(
+        handle
+            .try_db_set_item_if("users", &stale, true, item_revision(&second))
+            .await,
+        Err(CoreError::Conflict { current: 2 })
+    );
+    let stored = handle.db_get_item("users", 2).await.unwrap();
+    assert_eq!(stored.strs["name"], "alice");
+    assert_eq!(stored.u64s["age"], 31);
+    assert_eq!(item_revision(&stored), 2);
+
+    // Plain writes bump the revision too.
+    handle.db_set_item("users", &stored, true).await;
+    assert_eq!(item_revision(&core.item("users", 2).unwrap()), 3);
+
+    // Revision 0: create only if absent.
+    assert_eq!(
+        handle
+            .db_set_item_if("users", &item(9, "ivan", 50), false, 0)
+            .await,
+        Some(1)
+    );
+    assert_eq!(
+        handle
+            .db_set_item_if("users", &item(9, "ivan", 50), false, 0)
+            .await,
+        None
+    );
+    assert!(matches!(
+        handle
+            .try_db_set_item_if("users", &item(u64::MAX, "x", 0), false, 0)
+            .await,
+        Err(CoreError::Denied(_))
+    ));
+
+    core.assert_called_times("DbSetItemIf", 5);
+}
+
+#[tokio::test]
+async fn auth_roles_and_users_are_configurable() {
+    let core = MockCore::new();
+    core.grant_role(7, "admin");
+    core.add_user("alice", "pw");
+    let handle = core.start();
+
+    assert!(
+        handle
+            .auth_check_role(&Some(item(7, "x", 0)), "admin")
+            .await
+    );
+    assert!(
+        !handle
+            .auth_check_role(&Some(item(7, "x", 0)), "owner")
+            .await
+    );
+    assert!(
+        !handle
+            .auth_check_role(&Some(item(8, "y", 0)), "admin")
+            .await
+    );
+    assert!(!handle.auth_check_role(&None, "admin").await);
+
+    assert!(handle.auth_login("alice", "pw").await.succeeded);
+    assert!(!handle.auth_login("alice", "nope").await.succeeded);
+    assert!(
+        !handle
+            .auth_register("alice", "a@example.com")
+            .await
+            .succeeded
+    );
+    assert!(handle.auth_register("bob", "b@example.com").await.succeeded);
+    let otp = handle.auth_gen_otp("bob").await;
+    assert_eq!(otp.data["otp"], MOCK_OTP);
+
+    let salt = handle.auth_get_new_salt().await;
+    let hash = handle.auth_get_password_hash("secret", &salt).await;
+    assert!(handle.auth_verify_password("secret", &hash).await);
+    assert!(!handle.auth_verify_password("other", &hash).await);
+
+    core.assert_called_with("AuthRegister", "bob <b@example.com>");
+}
+
+#[tokio::test]
+async fn emails_settings_and_secrets_are_captured() {
+    let core = MockCore::new();
+    core.set_public_url("https://example.com");
+    let mut api_key = Item::new();
+    api_key.id = u64::MAX;
+    api_key.strs.insert("name".to_string(), "api".to_string());
+    api_key
+        .strs
+        .insert("secret_value".to_string(), "s3cr3t".to_string());
+    let secret_id = core.add_secret(api_key).unwrap();
+    let handle = core.start();
+
+    handle.send_email("ops@example.com", "Hi", "Body").await;
+    let mut settings = Item::new();
+    settings.strs.insert("site".to_string(), "demo".to_string());
+    handle.globals_set_settings(&settings).await;
+    assert_eq!(handle.globals_get_public_url().await, "https://example.com");
+    assert_eq!(handle.globals_get_settings().await.strs["site"], "demo");
+
+    // "<hidden>" keeps the stored secret value.
+    let mut update = Item::new();
+    update.id = secret_id;
+    update.strs.insert("name".to_string(), "api".to_string());
+    update
+        .strs
+        .insert("secret_value".to_string(), "<hidden>".to_string());
+    assert_eq!(handle.secret_set(&update, false).await, Ok(secret_id));
+    let stored = handle.secret_get_by_name("api").await.unwrap();
+    assert_eq!(stored.strs["secret_value"], "s3cr3t");
+
+    let mut duplicate = Item::new();
+    duplicate.id = u64::MAX;
+    duplicate.strs.insert("name".to_string(), "api".to_string());
+    assert!(handle.secret_set(&duplicate, false).await.is_err());
+    assert_eq!(
+        handle.secret_list().await,
+        vec![(secret_id, "api".to_string())]
+    );
+
+    core.assert_email_sent_to("ops@example.com");
+    assert_eq!(
+        core.sent_emails(),
+        vec![SentEmail {
+            to: "ops@example.com".to_string(),
+            subject: "Hi".to_string(),
+            body: "Body".to_string(),
+        }]
+    );
+    assert_eq!(core.settings().strs["site"], "demo");
+
+    core.clear_calls();
+    assert!(core.calls().is_empty());
+    assert!(core.sent_emails().is_empty());
+}
+
+#[test]
+#[should_panic(expected = "expected a SendEmail call")]
+fn assert_called_reports_missing_call() {
+    MockCore::new().assert_called("SendEmail");
+}
@@ -0,0 +1,142 @@
+#![cfg(feature = "testing")]
+
+mod common;
+
+use common::CountingPlugin;
+use isabelle_dm::data_model::data_object_action::DataObjectAction;
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::WebResponse;
+use isabelle_plugin_api::testing::*;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Example actor plugin: requires a name on new items, lets "editor"s
+/// through auth, hides odd ids from lists and serves the public URL.
+fn register(reg: &mut PluginRegistry, core: CoreHandle) {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            match msg {
+                PluginHookMessage::ItemPreEdit { item, reply, .. } => {
+                    let _ = reply.send(if item.strs.contains_key("name") {
+                        PreEditReply::ok_unchanged()
+                    } else {
+                        PreEditReply::rejected("name is required")
+                    });
+                }
+                PluginHookMessage::ItemAuth { user, reply, .. } => {
+                    let _ = reply.send(core.auth_check_role(&user, "editor").await);
+                }
+                PluginHookMessage::ItemListFilter {
+                    mut items, reply, ..
+                } => {
+                    items.retain(|id, _| id % 2 == 0);
+                    let _ = reply.send(ListFilterReply { items });
+                }
+                PluginHookMessage::ItemPostEdit { collection, id, .. } => {
+                    core.send_email("audit@example.com", &collection, &id.to_string())
+                        .await;
+                }
+                PluginHookMessage::RouteUrl { reply, .. } => {
+                    let _ = reply.send(WebResponse::OkData(core.globals_get_public_url().await));
+                }
+                PluginHookMessage::RouteRest { reply, .. } => {
+                    // Dropped on purpose.
+                    drop(reply);
+                }
+                PluginHookMessage::Ping { reply } => {
+                    let _ = reply.send(());
+                }
+                PluginHookMessage::Shutdown => break,
+                _ => {}
+            }
+        }
+    });
+    reg.add("example", tx);
+}
+
+fn user(id: u64) -> Item {
+    let mut user = Item::new();
+    user.id = id;
+    user
+}
+
+#[tokio::test]
+async fn harness_drives_item_hooks() {
+    let core = MockCore::new();
+    core.grant_role(1, "editor");
+    let harness = PluginHarness::with_core(core, register).as_user(user(1));
+
+    let mut named = Item::new();
+    named.strs.insert("name".to_string(), "x".to_string());
+    assert!(harness.pre_edit("notes", named).await.result.succeeded);
+    let rejected = harness.pre_edit("notes", Item::new()).await;
+    assert_eq!(rejected.result.error, "name is required");
+
+    assert!(harness.auth("notes", 5).await);
+    let stranger = PluginHarness::new(register).as_user(user(2));
+    assert!(!stranger.auth("notes", 5).await);
+
+    let items: HashMap<u64, Item> = (1..=4).map(|id| (id, user(id))).collect();
+    let mut kept: Vec<_> = harness
+        .list_filter("notes", "list", items)
+        .await
+        .into_keys()
+        .collect();
+    kept.sort();
+    assert_eq!(kept, vec![2, 4]);
+
+    harness
+        .post_edit("notes", None, None, 9, DataObjectAction::Delete, false)
+        .await;
+    harness.ping().await;
+    harness.core().assert_email_sent_to("audit@example.com");
+    harness.core().assert_called_with("AuthCheckRole", "editor");
+}
+
+#[tokio::test]
+async fn harness_drives_routes_and_shutdown() {
+    let core = MockCore::new();
+    core.set_public_url("https://example.com");
+    let harness = PluginHarness::with_core(core, register);
+
+    match harness.get("/info").await {
+        WebResponse::OkData(url) => assert_eq!(url, "https://example.com"),
+        _ => panic!("unexpected response"),
+    }
+    harness.shutdown().await;
+}
+
+#[tokio::test]
+async fn harness_runs_legacy_plugins() {
+    let harness = PluginHarness::new(|reg, core| {
+        reg.add_legacy("counting", Box::new(CountingPlugin::new()), core)
+    });
+    assert!(harness.auth("notes", 1).await);
+    assert!(matches!(
+        harness.get_unprotected("/").await,
+        WebResponse::Ok
+    ));
+}
+
+#[tokio::test]
+#[should_panic(expected = "plugin dropped the reply to RouteRest")]
+async fn harness_fails_on_dropped_reply() {
+    let harness = PluginHarness::new(register);
+    harness.rest("GET", "/", "").await;
+}
+
+#[tokio::test(start_paused = true)]
+#[should_panic(expected = "plugin did not answer CollectionRead within 100ms")]
+async fn harness_fails_on_timeout() {
+    // Keeps its receiver but never reads from it.
+    let harness = PluginHarness::new(|reg, _core| {
+        let (tx, rx) = mpsc::channel(8);
+        std::mem::forget(rx);
+        reg.add("silent", tx);
+    })
+    .with_timeout(Duration::from_millis(100));
+    harness.collection_read("notes", Item::new()).await;
+}
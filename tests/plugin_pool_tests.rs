@@ -53,6 +53,19 @@ fn ping_plugins_invokes_each_plugin_once_per_call() {
     }
 }
 
+#[test]
+fn manifests_follow_registration_order() {
+    let mut pool = PluginPool {
+        plugins: Vec::new(),
+    };
+    pool.register(Box::new(CountingPlugin::new()));
+    pool.register(Box::new(CountingPlugin::new()));
+
+    let manifests = pool.manifests();
+    assert_eq!(manifests.len(), 2);
+    assert!(manifests.iter().all(|m| m.hooks.is_none()));
+}
+
 #[test]
 fn load_plugins_with_empty_directory_is_noop() {
     let dir = std::env::temp_dir().join(format!(
@@ -0,0 +1,140 @@
+use isabelle_plugin_api::actor::DENY_ALL_DB_FILTER;
+use isabelle_plugin_api::query::*;
+use serde_json::{json, Value};
+
+fn doc(filter: &Filter) -> Value {
+    serde_json::from_str(&filter.to_string()).unwrap()
+}
+
+#[test]
+fn fields_map_to_item_paths() {
+    assert_eq!(Field::id().path(), "_id");
+    assert_eq!(Field::str("name").path(), "strs.name");
+    assert_eq!(Field::u64("time").path(), "u64s.time");
+    assert_eq!(Field::bool("done").path(), "bools.done");
+}
+
+#[test]
+fn comparisons_serialise_to_mongo_operators() {
+    assert_eq!(
+        doc(&Filter::eq(Field::str("status"), "open")),
+        json!({"strs.status": {"$eq": "open"}})
+    );
+    assert_eq!(
+        doc(&Filter::ne(Field::bool("done"), true)),
+        json!({"bools.done": {"$ne": true}})
+    );
+    assert_eq!(
+        doc(&Filter::is_in(Field::id(), [1u64, 2, 3])),
+        json!({"_id": {"$in": [1, 2, 3]}})
+    );
+    assert_eq!(
+        doc(&Filter::not_in(Field::str("role"), ["admin"])),
+        json!({"strs.role": {"$nin": ["admin"]}})
+    );
+    assert_eq!(
+        doc(&Filter::regex(Field::str("title"), "^a\"b")),
+        json!({"strs.title": {"$regex": "^a\"b"}})
+    );
+}
+
+#[test]
+fn ranges_use_matching_bounds() {
+    assert_eq!(
+        doc(&Filter::range(Field::u64("time"), 10u64..20)),
+        json!({"u64s.time": {"$gte": 10, "$lt": 20}})
+    );
+    assert_eq!(
+        doc(&Filter::range(Field::u64("time"), ..=5u64)),
+        json!({"u64s.time": {"$lte": 5}})
+    );
+    assert_eq!(
+        doc(&Filter::range(Field::str("name"), "m"..)),
+        json!({"strs.name": {"$gte": "m"}})
+    );
+    assert!(Filter::range::<u64>(Field::u64("time"), ..).is_all());
+}
+
+#[test]
+fn combinators_flatten_and_skip_match_all() {
+    let a = Filter::eq(Field::u64("a"), 1u64);
+    let b = Filter::eq(Field::u64("b"), 2u64);
+    let c = Filter::eq(Field::u64("c"), 3u64);
+
+    assert_eq!(
+        doc(&a.clone().and(b.clone()).and(c.clone())),
+        json!({"$and": [doc(&a), doc(&b), doc(&c)]})
+    );
+    assert_eq!(
+        doc(&a.clone().or(b.clone()).or(c.clone())),
+        json!({"$or": [doc(&a), doc(&b), doc(&c)]})
+    );
+    assert_eq!(Filter::all().and(a.clone()), a);
+    assert_eq!(doc(&!a.clone()), json!({"$nor": [doc(&a)]}));
+
+    assert!(Filter::all_of([]).is_all());
+    assert_eq!(Filter::any_of([]), Filter::none());
+    assert_eq!(
+        doc(&Filter::none()),
+        serde_json::from_str::<Value>(DENY_ALL_DB_FILTER).unwrap()
+    );
+    assert_eq!(Filter::default().to_string(), "{}");
+}
+
+#[test]
+fn sort_renders_its_key() {
+    assert_eq!(Sort::by("time").to_string(), "time");
+    assert_eq!(Sort::none().as_str(), "");
+    assert_eq!(Sort::default(), Sort::none());
+}
+
+#[cfg(feature = "testing")]
+#[tokio::test]
+async fn core_handle_accepts_typed_queries() {
+    use isabelle_dm::data_model::item::Item;
+    use isabelle_plugin_api::testing::MockCore;
+
+    let core = MockCore::new();
+    for (id, name, time, done) in [
+        (1, "alpha", 30u64, false),
+        (2, "beta", 10, true),
+        (3, "gamma", 20, false),
+        (4, "delta", 40, false),
+    ] {
+        let mut item = Item::new();
+        item.id = id;
+        item.strs.insert("name".to_string(), name.to_string());
+        item.u64s.insert("time".to_string(), time);
+        item.bools.insert("done".to_string(), done);
+        core.insert("tasks", item);
+    }
+    let handle = core.start();
+
+    let open = Filter::eq(Field::bool("done"), false)
+        .and(Filter::range(Field::u64("time"), 15u64..))
+        .and(!Filter::regex(Field::str("name"), "^d"));
+    let found = handle
+        .db_find_all_items("tasks", &Sort::by("time"), &open)
+        .await;
+    let mut ids: Vec<_> = found.map.keys().copied().collect();
+    ids.sort();
+    assert_eq!(ids, vec![1, 3]);
+
+    let page = handle
+        .try_db_find_items(
+            "tasks",
+            0,
+            u64::MAX,
+            &Sort::by("time"),
+            &Filter::all(),
+            1,
+            2,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.total_count, 4);
+    let mut ids: Vec<_> = page.map.keys().copied().collect();
+    ids.sort();
+    // By time: beta(2), gamma(3), alpha(1), delta(4).
+    assert_eq!(ids, vec![1, 3]);
+}
@@ -0,0 +1,116 @@
+mod common;
+
+use common::{install_example_library, test_dir};
+use isabelle_plugin_api::actor::*;
+use std::fs;
+use tokio::sync::mpsc;
+
+fn core() -> CoreHandle {
+    let (tx, _rx) = mpsc::channel(1);
+    CoreHandle::new(tx)
+}
+
+#[test]
+fn load_dir_with_empty_directory_is_noop() {
+    let dir = test_dir("empty");
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert!(reg.is_empty());
+    assert_eq!(result.considered, 0);
+    assert_eq!(result.loaded, 0);
+    assert!(result.is_ok());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn load_dir_skips_legacy_and_unrelated_files() {
+    let dir = test_dir("skip");
+    fs::write(dir.join("README.txt"), b"not a plugin").unwrap();
+    // Legacy plugins are loaded by `PluginPool::load_plugins`, not here.
+    fs::write(dir.join("libisabelle_plugin_old.so"), b"not a plugin").unwrap();
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert!(reg.is_empty());
+    assert_eq!(result.considered, 0);
+    assert!(result.is_ok());
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn load_dir_reports_missing_directory() {
+    let dir = test_dir("missing").join("absent");
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert_eq!(result.considered, 0);
+    assert_eq!(result.failed(), 1);
+    assert_eq!(result.failures[0].path, dir.to_str().unwrap());
+    assert!(result.failures[0].error.contains("Failed to read"));
+
+    let _ = fs::remove_dir_all(dir.parent().unwrap());
+}
+
+#[test]
+fn load_dir_reports_failure_for_invalid_library() {
+    let dir = test_dir("bad-lib");
+    fs::write(
+        dir.join(format!("{}bogus.so", ACTOR_PLUGIN_PREFIX)),
+        b"not a real shared library",
+    )
+    .unwrap();
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert!(reg.is_empty());
+    assert_eq!(result.considered, 1);
+    assert_eq!(result.loaded, 0);
+    assert_eq!(result.failed(), 1);
+    let failure = &result.failures[0];
+    assert!(
+        failure.path.ends_with("libisabelle_actor_plugin_bogus.so"),
+        "unexpected failure path: {}",
+        failure.path
+    );
+    assert!(
+        failure.error.contains("Failed to load plugin library"),
+        "unexpected failure message: {}",
+        failure.error
+    );
+
+    let _ = fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn load_dir_registers_library_plugins_once() {
+    let dir = test_dir("example");
+    let path = install_example_library(
+        "actor_plugin",
+        &dir,
+        &format!("{}example.so", ACTOR_PLUGIN_PREFIX),
+    );
+
+    let mut reg = PluginRegistry::new();
+    let result = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert_eq!(result.considered, 1);
+    assert_eq!(result.loaded, 1);
+    assert!(result.is_ok());
+    assert_eq!(reg.manifests().next().unwrap().name, "actor-example");
+    assert_eq!(
+        reg.libraries().collect::<Vec<_>>(),
+        vec![fs::canonicalize(&path).unwrap().to_str().unwrap()]
+    );
+    assert_eq!(HookDispatcher::new(&reg).ping().await, 1);
+
+    // Rescanning skips the library that is already loaded.
+    let again = reg.load_dir(dir.to_str().unwrap(), &core());
+    assert_eq!(again.considered, 0);
+    assert_eq!(again.loaded, 0);
+    assert_eq!(reg.len(), 1);
+
+    reg.shutdown_all().await;
+    let _ = fs::remove_dir_all(&dir);
+}
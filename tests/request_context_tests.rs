@@ -0,0 +1,59 @@
+use actix_web::cookie::Cookie;
+use actix_web::test::TestRequest;
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::request::*;
+
+#[test]
+fn new_splits_and_decodes_the_url() {
+    let ctx = RequestContext::new("h", &None, "get", "/search?q=a%20b&tag=x&tag=y&empty=");
+    assert_eq!(ctx.method, "GET");
+    assert_eq!(ctx.path, "/search");
+    assert_eq!(ctx.query_string, "q=a%20b&tag=x&tag=y&empty=");
+    assert_eq!(ctx.query_param("q"), Some("a b"));
+    assert_eq!(ctx.query_param("tag"), Some("x"));
+    assert_eq!(ctx.query_params.len(), 4);
+    assert_eq!(ctx.query_param("empty"), Some(""));
+    assert_eq!(ctx.query_param("missing"), None);
+    assert_eq!(ctx.url(), "/search?q=a%20b&tag=x&tag=y&empty=");
+
+    let plain = RequestContext::new("h", &None, "GET", "/");
+    assert!(plain.query_params.is_empty());
+    assert_eq!(plain.url(), "/");
+}
+
+#[test]
+fn request_ids_are_unique_unless_the_client_sends_one() {
+    let a = RequestContext::new("h", &None, "GET", "/");
+    let b = RequestContext::new("h", &None, "GET", "/");
+    assert!(!a.request_id.is_empty());
+    assert_ne!(a.request_id, b.request_id);
+
+    let traced = a.with_header("X-Request-Id", "abc-123");
+    assert_eq!(traced.request_id, "abc-123");
+    assert_eq!(traced.header("x-request-id"), Some("abc-123"));
+}
+
+#[test]
+fn from_http_request_collects_headers_cookies_and_peer() {
+    let req = TestRequest::post()
+        .uri("/upload?dir=docs")
+        .insert_header(("User-Agent", "test"))
+        .insert_header(("X-Request-Id", "req-7"))
+        .cookie(Cookie::new("session", "s3"))
+        .peer_addr("10.0.0.1:4000".parse().unwrap())
+        .to_http_request();
+    let mut user = Item::new();
+    user.id = 5;
+
+    let ctx = RequestContext::from_http_request("h", &Some(user), &req);
+    assert_eq!(ctx.hndl, "h");
+    assert_eq!(ctx.user.as_ref().map(|u| u.id), Some(5));
+    assert_eq!(ctx.method, "POST");
+    assert_eq!(ctx.path, "/upload");
+    assert_eq!(ctx.query_param("dir"), Some("docs"));
+    assert_eq!(ctx.header("user-agent"), Some("test"));
+    assert_eq!(ctx.header("USER-AGENT"), Some("test"));
+    assert_eq!(ctx.cookie("session"), Some("s3"));
+    assert_eq!(ctx.remote_addr, Some("10.0.0.1:4000".parse().unwrap()));
+    assert_eq!(ctx.request_id, "req-7");
+}
@@ -0,0 +1,240 @@
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::WebResponse;
+use isabelle_plugin_api::manifest::PluginManifest;
+use isabelle_plugin_api::request::RequestContext;
+use isabelle_plugin_api::rest::*;
+use isabelle_plugin_api::route::RouteSpec;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+#[derive(Deserialize)]
+struct NewInvoice {
+    customer: String,
+    amount: u64,
+}
+
+#[derive(Serialize)]
+struct Invoice {
+    id: u64,
+    customer: String,
+    amount: u64,
+}
+
+fn create(req: NewInvoice) -> Result<Invoice, ApiError> {
+    if req.amount == 0 {
+        return Err(
+            ApiError::bad_request("amount must be positive").details(json!({ "field": "amount" }))
+        );
+    }
+    Ok(Invoice {
+        id: 1,
+        customer: req.customer,
+        amount: req.amount,
+    })
+}
+
+/// Status and JSON body of a response.
+fn json(resp: WebResponse) -> (u16, Value) {
+    match resp {
+        WebResponse::Json(value) => (200, value),
+        WebResponse::Custom(custom) => {
+            assert!(custom
+                .headers
+                .contains(&("Content-Type".to_string(), "application/json".to_string())));
+            (custom.status, serde_json::from_slice(&custom.body).unwrap())
+        }
+        _ => panic!("unexpected response"),
+    }
+}
+
+#[test]
+fn handle_json_parses_and_serializes() {
+    let resp = handle_json(r#"{"customer": "acme", "amount": 5}"#, create);
+    assert_eq!(
+        json(resp),
+        (200, json!({ "id": 1, "customer": "acme", "amount": 5 }))
+    );
+}
+
+#[test]
+fn malformed_bodies_get_400() {
+    for payload in ["", "{", r#"{"customer": "acme"}"#, r#"{"amount": "5"}"#] {
+        let (status, body) = json(handle_json(payload, create));
+        assert_eq!(status, 400, "{}", payload);
+        assert_eq!(body["error"], "invalid_body");
+        assert!(body["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("invalid request body: "));
+    }
+}
+
+#[test]
+fn handler_errors_use_the_envelope() {
+    let (status, body) = json(handle_json(r#"{"customer": "acme", "amount": 0}"#, create));
+    assert_eq!(status, 400);
+    assert_eq!(
+        body,
+        json!({
+            "error": "bad_request",
+            "message": "amount must be positive",
+            "details": { "field": "amount" }
+        })
+    );
+
+    let (status, body) = json(handle_json("", |(): ()| {
+        Err::<(), _>(ApiError::new(418, "teapot", "short and stout"))
+    }));
+    assert_eq!(status, 418);
+    assert_eq!(
+        body,
+        json!({ "error": "teapot", "message": "short and stout" })
+    );
+}
+
+fn router() -> RestRouter {
+    RestRouter::new()
+        .route(RouteSpec::post("/invoice"), |req: NewInvoice| async move {
+            create(req)
+        })
+        .route_with_context(
+            RouteSpec::get("/invoice/{id}"),
+            |ctx: RequestContext, (): ()| async move {
+                let id: u64 = ctx
+                    .path_param("id")
+                    .unwrap_or("")
+                    .parse()
+                    .map_err(|_| ApiError::bad_request("invalid id"))?;
+                match id {
+                    1 => Ok(json!({ "id": 1 })),
+                    _ => Err(ApiError::not_found("no such invoice")),
+                }
+            },
+        )
+        .route(RouteSpec::get("/invoice/latest"), |(): ()| async {
+            Ok::<_, ApiError>("latest")
+        })
+}
+
+fn request(method: &str, url: &str) -> RequestContext {
+    RequestContext::new("h", &None, method, url)
+}
+
+#[tokio::test]
+async fn router_dispatches_by_method_and_path() {
+    let router = router();
+
+    let (status, body) = json(
+        router
+            .handle(
+                &request("POST", "/invoice"),
+                r#"{"customer": "a", "amount": 2}"#,
+            )
+            .await,
+    );
+    assert_eq!((status, &body["amount"]), (200, &json!(2)));
+
+    assert_eq!(
+        json(router.handle(&request("GET", "/invoice/1"), "").await),
+        (200, json!({ "id": 1 }))
+    );
+    assert_eq!(
+        json(router.handle(&request("GET", "/invoice/2"), "").await).0,
+        404
+    );
+    assert_eq!(
+        json(router.handle(&request("GET", "/invoice/x"), "").await).0,
+        400
+    );
+    // The literal route beats `{id}`.
+    assert_eq!(
+        json(router.handle(&request("GET", "/invoice/latest"), "").await),
+        (200, json!("latest"))
+    );
+
+    match router.handle(&request("DELETE", "/invoice/1"), "").await {
+        WebResponse::Custom(custom) => {
+            assert_eq!(custom.status, 405);
+            assert_eq!(
+                custom.headers,
+                vec![("Allow".to_string(), "GET".to_string())]
+            );
+        }
+        _ => panic!("unexpected response"),
+    }
+    assert!(matches!(
+        router.handle(&request("GET", "/other"), "").await,
+        WebResponse::NotImplemented
+    ));
+}
+
+#[tokio::test]
+async fn router_picks_the_first_of_equally_specific_routes() {
+    let router = RestRouter::new()
+        .route(RouteSpec::get("/report/{year}"), |(): ()| async {
+            Ok::<_, ApiError>("first")
+        })
+        .route(RouteSpec::any("/report/{name}"), |(): ()| async {
+            Ok::<_, ApiError>("second")
+        });
+
+    // Same as the dispatcher: declaration order breaks ties.
+    assert_eq!(
+        json(router.handle(&request("GET", "/report/2024"), "").await),
+        (200, json!("first"))
+    );
+    assert_eq!(
+        json(router.handle(&request("POST", "/report/2024"), "").await),
+        (200, json!("second"))
+    );
+}
+
+#[test]
+#[should_panic(expected = "REST route GET /status must be protected")]
+fn router_refuses_unprotected_routes() {
+    RestRouter::new().route(RouteSpec::get("/status").unprotected(), |(): ()| async {
+        Ok::<_, ApiError>("up")
+    });
+}
+
+#[test]
+#[should_panic(expected = "REST route GET /admin can't be restricted to a role")]
+fn router_refuses_role_restricted_routes() {
+    RestRouter::new().route(RouteSpec::get("/admin").role("admin"), |(): ()| async {
+        Ok::<_, ApiError>("hi")
+    });
+}
+
+#[tokio::test]
+async fn router_routes_go_into_the_manifest() {
+    let router = Arc::new(router());
+    let (tx, mut rx) = mpsc::channel(8);
+    let plugin_router = router.clone();
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let PluginHookMessage::RouteRest {
+                ctx,
+                payload,
+                reply,
+            } = msg
+            {
+                let _ = reply.send(plugin_router.handle(&ctx, &payload).await);
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add_with_manifest(
+        PluginManifest::new("billing", "1.0.0").routes(router.specs()),
+        tx,
+    );
+    let d = HookDispatcher::new(&reg);
+
+    assert_eq!(
+        json(d.route_rest(&request("GET", "/invoice/1"), "").await),
+        (200, json!({ "id": 1 }))
+    );
+    let (status, body) = json(d.route_rest(&request("POST", "/invoice"), "[]").await);
+    assert_eq!((status, &body["error"]), (400, &json!("invalid_body")));
+}
@@ -0,0 +1,278 @@
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::WebResponse;
+use isabelle_plugin_api::manifest::{HookKind, PluginManifest};
+use isabelle_plugin_api::request::RequestContext;
+use isabelle_plugin_api::route::*;
+use tokio::sync::mpsc;
+
+fn params(pairs: &[(&str, &str)]) -> Option<Vec<(String, String)>> {
+    Some(
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn patterns_match_literals_params_and_rest() {
+    let pdf = RoutePattern::new("/invoice/{id}/pdf");
+    assert_eq!(pdf.matches("/invoice/42/pdf"), params(&[("id", "42")]));
+    assert_eq!(pdf.matches("/invoice/a%20b/pdf/"), params(&[("id", "a b")]));
+    assert_eq!(pdf.matches("/invoice/42"), None);
+    assert_eq!(pdf.matches("/invoice/42/pdf/extra"), None);
+    assert_eq!(pdf.matches("/invoices/42/pdf"), None);
+
+    let files = RoutePattern::new("/files/{*path}");
+    assert_eq!(
+        files.matches("/files/docs/a%2Bb.txt"),
+        params(&[("path", "docs/a+b.txt")])
+    );
+    assert_eq!(files.matches("/files"), params(&[("path", "")]));
+
+    assert_eq!(RoutePattern::new("/").matches(""), params(&[]));
+}
+
+#[test]
+fn specs_default_to_protected_and_match_methods() {
+    let spec = RouteSpec::new("get", "/report");
+    assert_eq!(spec.method, "GET");
+    assert!(spec.protected);
+    assert!(spec.allows("GET"));
+    assert!(!spec.allows("POST"));
+    assert!(RouteSpec::any("/report").allows("PATCH"));
+
+    let hook = RouteSpec::post("/hook").unprotected().role("admin");
+    assert!(!hook.protected);
+    assert_eq!(hook.role.as_deref(), Some("admin"));
+}
+
+/// Register a plugin with `routes` that answers route hooks with
+/// "<name> <method> <path params>".
+fn spawn_plugin(reg: &mut PluginRegistry, name: &'static str, routes: Vec<RouteSpec>) {
+    spawn_plugin_with_manifest(reg, PluginManifest::new(name, "1.0.0").routes(routes));
+}
+
+/// [`spawn_plugin`] with a full manifest.
+fn spawn_plugin_with_manifest(reg: &mut PluginRegistry, manifest: PluginManifest) {
+    let name = manifest.name.clone();
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let (ctx, reply) = match msg {
+                PluginHookMessage::RouteUrl { ctx, reply }
+                | PluginHookMessage::RouteUnprotectedUrl { ctx, reply }
+                | PluginHookMessage::RouteRest { ctx, reply, .. } => (ctx, reply),
+                _ => continue,
+            };
+            let params: Vec<_> = ctx
+                .path_params
+                .iter()
+                .map(|(n, v)| format!("{}={}", n, v))
+                .collect();
+            let _ = reply.send(WebResponse::OkData(format!(
+                "{} {} {}",
+                name,
+                ctx.method,
+                params.join(",")
+            )));
+        }
+    });
+    reg.add_with_manifest(manifest, tx);
+}
+
+fn request(method: &str, url: &str) -> RequestContext {
+    RequestContext::new("h", &None, method, url)
+}
+
+fn data(resp: WebResponse) -> String {
+    match resp {
+        WebResponse::OkData(s) => s,
+        _ => panic!("unexpected response"),
+    }
+}
+
+#[tokio::test]
+async fn declared_routes_go_to_their_plugin_only() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "legacy", vec![]);
+    spawn_plugin(
+        &mut reg,
+        "billing",
+        vec![
+            RouteSpec::get("/invoice/{id}/pdf"),
+            RouteSpec::delete("/invoice/{id}"),
+        ],
+    );
+    spawn_plugin(
+        &mut reg,
+        "drafts",
+        vec![RouteSpec::get("/invoice/draft/pdf")],
+    );
+    let d = HookDispatcher::new(&reg);
+
+    assert_eq!(
+        data(d.route_url(&request("GET", "/invoice/7/pdf")).await),
+        "billing GET id=7"
+    );
+    // The literal pattern beats `{id}`.
+    assert_eq!(
+        data(d.route_url(&request("GET", "/invoice/draft/pdf")).await),
+        "drafts GET "
+    );
+    assert_eq!(
+        data(d.route_rest(&request("DELETE", "/invoice/7"), "").await),
+        "billing DELETE id=7"
+    );
+    // Undeclared paths still reach plugins without routes.
+    assert_eq!(
+        data(d.route_url(&request("GET", "/other")).await),
+        "legacy GET "
+    );
+}
+
+#[tokio::test]
+async fn wrong_method_gets_405_and_unclaimed_path_404() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(
+        &mut reg,
+        "billing",
+        vec![
+            RouteSpec::get("/invoice/{id}"),
+            RouteSpec::delete("/invoice/{id}"),
+        ],
+    );
+    let d = HookDispatcher::new(&reg);
+
+    let resp = d.route_rest(&request("PUT", "/invoice/7"), "{}").await;
+    match resp {
+        WebResponse::Custom(custom) => {
+            assert_eq!(custom.status, 405);
+            assert_eq!(
+                custom.headers,
+                vec![("Allow".to_string(), "DELETE, GET".to_string())]
+            );
+        }
+        _ => panic!("unexpected response"),
+    }
+
+    // Nobody is left to offer an undeclared path to.
+    assert!(matches!(
+        d.route_url(&request("GET", "/unknown")).await,
+        WebResponse::NotImplemented
+    ));
+    // Protected routes aren't served through unprotected hooks.
+    assert!(matches!(
+        d.route_unprotected_url(&request("GET", "/invoice/7")).await,
+        WebResponse::NotImplemented
+    ));
+}
+
+#[tokio::test]
+async fn unprotected_routes_are_separate() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(
+        &mut reg,
+        "hooks",
+        vec![RouteSpec::get("/status").unprotected()],
+    );
+    let d = HookDispatcher::new(&reg);
+
+    assert_eq!(
+        data(d.route_unprotected_url(&request("GET", "/status")).await),
+        "hooks GET "
+    );
+    assert!(matches!(
+        d.route_url(&request("GET", "/status")).await,
+        WebResponse::NotImplemented
+    ));
+}
+
+#[tokio::test]
+async fn roles_are_checked_before_the_plugin_is_asked() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(
+        &mut reg,
+        "admin",
+        vec![RouteSpec::get("/admin/{page}").role("admin")],
+    );
+    let check = |user: &Item, role: &str| user.id == 1 && role == "admin";
+
+    let mut admin = Item::new();
+    admin.id = 1;
+    let mut guest = Item::new();
+    guest.id = 2;
+    let as_user = |user: Item| RequestContext::new("h", &Some(user), "GET", "/admin/users");
+
+    let d = HookDispatcher::new(&reg).with_role_check(&check);
+    assert_eq!(
+        data(d.route_url(&as_user(admin.clone())).await),
+        "admin GET page=users"
+    );
+    assert!(matches!(
+        d.route_url(&as_user(guest)).await,
+        WebResponse::Forbidden
+    ));
+    assert!(matches!(
+        d.route_url(&request("GET", "/admin/users")).await,
+        WebResponse::Unauthorized
+    ));
+
+    // Without a role check, role-restricted routes are refused.
+    let d = HookDispatcher::new(&reg);
+    assert!(matches!(
+        d.route_url(&as_user(admin)).await,
+        WebResponse::Forbidden
+    ));
+}
+
+#[tokio::test]
+async fn role_refusals_do_not_fall_through_to_catch_alls() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin(&mut reg, "pages", vec![RouteSpec::get("/{*path}")]);
+    spawn_plugin(
+        &mut reg,
+        "admin",
+        vec![RouteSpec::get("/admin/{*rest}").role("admin")],
+    );
+    let mut guest = Item::new();
+    guest.id = 2;
+    let check = |user: &Item, role: &str| user.id == 1 && role == "admin";
+    let d = HookDispatcher::new(&reg).with_role_check(&check);
+
+    assert!(matches!(
+        d.route_url(&RequestContext::new("h", &Some(guest), "GET", "/admin/x"))
+            .await,
+        WebResponse::Forbidden
+    ));
+    assert!(matches!(
+        d.route_url(&request("GET", "/admin/x")).await,
+        WebResponse::Unauthorized
+    ));
+    assert_eq!(
+        data(d.route_url(&request("GET", "/about")).await),
+        "pages GET path=about"
+    );
+}
+
+#[tokio::test]
+async fn routes_are_only_offered_through_subscribed_hooks() {
+    let mut reg = PluginRegistry::new();
+    spawn_plugin_with_manifest(
+        &mut reg,
+        PluginManifest::new("api", "1.0.0")
+            .hooks([HookKind::RouteRest])
+            .route(RouteSpec::get("/api/status")),
+    );
+    let d = HookDispatcher::new(&reg);
+
+    assert_eq!(
+        data(d.route_rest(&request("GET", "/api/status"), "").await),
+        "api GET "
+    );
+    assert!(matches!(
+        d.route_url(&request("GET", "/api/status")).await,
+        WebResponse::NotImplemented
+    ));
+}
@@ -0,0 +1,191 @@
+use actix_web::body::to_bytes;
+use actix_web::http::StatusCode;
+use actix_web::HttpResponse;
+use isabelle_dm::data_model::item::Item;
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::*;
+use isabelle_plugin_api::upload::*;
+use serde_json::Value;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+fn limits(memory_threshold: u64) -> UploadLimits {
+    UploadLimits {
+        max_parts: 3,
+        max_part_size: 16,
+        max_total_size: 24,
+        memory_threshold,
+        ..UploadLimits::default()
+    }
+}
+
+#[test]
+fn collector_keeps_small_parts_in_memory() {
+    let mut uploads = UploadCollector::new(limits(64));
+    uploads.begin_part("title", None, None).unwrap();
+    uploads.push(b"notes").unwrap();
+    uploads
+        .begin_part("file", Some("a.txt"), Some("text/plain"))
+        .unwrap();
+    uploads.push(b"hello ").unwrap();
+    uploads.push(b"world").unwrap();
+    let parts = uploads.finish().unwrap();
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].field, "title");
+    assert_eq!(parts[0].filename, None);
+    assert_eq!(&*parts[0].bytes().unwrap(), b"notes");
+    assert_eq!(parts[1].filename.as_deref(), Some("a.txt"));
+    assert_eq!(parts[1].content_type.as_deref(), Some("text/plain"));
+    assert_eq!(parts[1].size, 11);
+    assert!(parts[1].path().is_none());
+    assert_eq!(&*parts[1].bytes().unwrap(), b"hello world");
+}
+
+#[test]
+fn collector_spools_large_parts_and_removes_them_on_drop() {
+    let mut uploads = UploadCollector::new(limits(4));
+    uploads.begin_part("file", Some("big.bin"), None).unwrap();
+    uploads.push(b"0123").unwrap();
+    uploads.push(b"456789").unwrap();
+    let parts = uploads.finish().unwrap();
+
+    let path = parts[0].path().unwrap().to_path_buf();
+    assert!(path.exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    assert_eq!(parts[0].size, 10);
+    assert_eq!(&*parts[0].bytes().unwrap(), b"0123456789");
+
+    // Clones share the file; it goes with the last one.
+    let copy = parts[0].clone();
+    drop(parts);
+    assert!(path.exists());
+    drop(copy);
+    assert!(!path.exists());
+}
+
+#[test]
+fn collector_starts_an_unnamed_part_for_leading_bytes() {
+    let mut uploads = UploadCollector::new(limits(1024));
+    uploads.push(b"stray").unwrap();
+    uploads.begin_part("file", None, None).unwrap();
+    uploads.push(b"data").unwrap();
+    let parts = uploads.finish().unwrap();
+
+    assert_eq!(parts.len(), 2);
+    assert_eq!(parts[0].field, "");
+    assert_eq!(&*parts[0].bytes().unwrap(), b"stray");
+    assert_eq!(parts[1].field, "file");
+}
+
+#[test]
+fn collector_enforces_limits() {
+    let mut uploads = UploadCollector::new(limits(64));
+    for field in ["a", "b", "c"] {
+        uploads.begin_part(field, None, None).unwrap();
+    }
+    assert_eq!(
+        uploads.begin_part("d", None, None),
+        Err(UploadRejection::TooManyParts { max: 3 })
+    );
+
+    let mut uploads = UploadCollector::new(limits(64));
+    uploads.begin_part("file", None, None).unwrap();
+    uploads.push(&[0; 10]).unwrap();
+    assert_eq!(
+        uploads.push(&[0; 7]),
+        Err(UploadRejection::PartTooLarge {
+            field: "file".to_string(),
+            max: 16
+        })
+    );
+
+    let mut uploads = UploadCollector::new(limits(4));
+    uploads.begin_part("a", None, None).unwrap();
+    uploads.push(&[0; 16]).unwrap();
+    uploads.begin_part("b", None, None).unwrap();
+    assert_eq!(
+        uploads.push(&[0; 9]),
+        Err(UploadRejection::TooLarge { max: 24 })
+    );
+}
+
+#[tokio::test]
+async fn rejections_become_json_responses() {
+    let resp = HttpResponse::from(WebResponse::from(UploadRejection::PartTooLarge {
+        field: "file".to_string(),
+        max: 16,
+    }));
+    assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body: Value = serde_json::from_slice(&to_bytes(resp.into_body()).await.unwrap()).unwrap();
+    assert_eq!(body["error"], "part_too_large");
+    assert_eq!(body["field"], "file");
+    assert_eq!(body["limit"], 16);
+    assert_eq!(body["message"], "part file is larger than 16 bytes");
+
+    let resp = HttpResponse::from(WebResponse::from(UploadRejection::Io(
+        "disk full".to_string(),
+    )));
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn parts_reach_post_route_hooks() {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let PluginHookMessage::RouteUrlPost { parts, reply, .. } = msg {
+                let names: Vec<_> = parts
+                    .iter()
+                    .map(|p| format!("{}={}", p.field, p.size))
+                    .collect();
+                let _ = reply.send(WebResponse::OkData(names.join(",")));
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add("uploads", tx);
+
+    let ctx = RequestContext::new("h", &None, "POST", "/upload");
+    let parts = vec![
+        UploadPart::in_memory("doc", Some("a.pdf"), Some("application/pdf"), vec![1; 5]),
+        UploadPart::in_memory("note", None, None, "hi"),
+    ];
+    let resp = HookDispatcher::new(&reg)
+        .route_url_post(&ctx, &Item::new(), parts)
+        .await;
+    assert!(matches!(resp, WebResponse::OkData(s) if s == "doc=5,note=2"));
+}
+
+#[tokio::test]
+async fn parts_are_shared_between_plugins() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let mut reg = PluginRegistry::new();
+    for name in ["first", "second"] {
+        let (tx, mut rx) = mpsc::channel(8);
+        let seen = seen.clone();
+        tokio::spawn(async move {
+            while let Some(msg) = rx.recv().await {
+                if let PluginHookMessage::RouteUrlPost { parts, reply, .. } = msg {
+                    seen.lock().unwrap().push(parts);
+                    let _ = reply.send(WebResponse::NotImplemented);
+                }
+            }
+        });
+        reg.add(name, tx);
+    }
+
+    let ctx = RequestContext::new("h", &None, "POST", "/upload");
+    let parts = vec![UploadPart::in_memory("doc", None, None, vec![0; 1024])];
+    HookDispatcher::new(&reg)
+        .route_url_post(&ctx, &Item::new(), parts)
+        .await;
+    let seen = seen.lock().unwrap();
+    assert_eq!(seen.len(), 2);
+    assert!(Arc::ptr_eq(&seen[0], &seen[1]));
+}
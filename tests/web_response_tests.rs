@@ -0,0 +1,226 @@
+use actix_web::body::{to_bytes, BodySize, MessageBody};
+use actix_web::cookie::Cookie;
+use actix_web::http::StatusCode;
+use actix_web::test::TestRequest;
+use actix_web::{HttpResponse, Responder};
+use isabelle_plugin_api::actor::*;
+use isabelle_plugin_api::api::*;
+use isabelle_plugin_api::request::RequestContext;
+use serde_json::json;
+use std::path::PathBuf;
+use tokio::sync::mpsc;
+
+async fn body(resp: HttpResponse) -> Vec<u8> {
+    to_bytes(resp.into_body()).await.unwrap().to_vec()
+}
+
+fn header<'a>(resp: &'a HttpResponse, name: &str) -> Option<&'a str> {
+    resp.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+#[test]
+fn fixed_variants_map_to_status_codes() {
+    for (resp, status) in [
+        (WebResponse::Ok, StatusCode::OK),
+        (WebResponse::Logout, StatusCode::OK),
+        (WebResponse::NotFound, StatusCode::NOT_FOUND),
+        (WebResponse::NotImplemented, StatusCode::NOT_FOUND),
+        (WebResponse::Unauthorized, StatusCode::UNAUTHORIZED),
+        (WebResponse::BadRequest, StatusCode::BAD_REQUEST),
+        (WebResponse::Forbidden, StatusCode::FORBIDDEN),
+        (WebResponse::Conflict, StatusCode::CONFLICT),
+        (WebResponse::TooManyRequests, StatusCode::TOO_MANY_REQUESTS),
+        (
+            WebResponse::ServiceUnavailable,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ),
+    ] {
+        assert_eq!(HttpResponse::from(resp).status(), status);
+    }
+}
+
+#[tokio::test]
+async fn data_json_and_files_carry_bodies() {
+    let resp = HttpResponse::from(WebResponse::OkData("hello".to_string()));
+    assert_eq!(body(resp).await, b"hello");
+
+    let resp = HttpResponse::from(WebResponse::Json(json!({"ok": true})));
+    assert_eq!(header(&resp, "content-type"), Some("application/json"));
+    assert_eq!(body(resp).await, br#"{"ok":true}"#);
+
+    let resp = HttpResponse::from(WebResponse::OkFile("a.bin".to_string(), vec![1, 2, 3]));
+    assert_eq!(
+        header(&resp, "content-disposition"),
+        Some("attachment; filename=\"a.bin\"")
+    );
+    assert_eq!(body(resp).await, vec![1, 2, 3]);
+
+    let path = std::env::temp_dir().join(format!("web-response-{}.txt", std::process::id()));
+    std::fs::write(&path, "on disk").unwrap();
+    let resp = HttpResponse::from(WebResponse::OkFilePath(
+        "b.txt".to_string(),
+        path.to_string_lossy().into_owned(),
+    ));
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(resp.status(), StatusCode::OK);
+    // Streamed with its size, not read into memory.
+    assert_eq!(resp.body().size(), BodySize::Sized(7));
+    assert_eq!(
+        header(&resp, "content-disposition"),
+        Some("attachment; filename=\"b.txt\"")
+    );
+    assert_eq!(body(resp).await, b"on disk");
+
+    let resp = HttpResponse::from(WebResponse::OkFilePath(
+        "c.txt".to_string(),
+        "/nonexistent/c.txt".to_string(),
+    ));
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+}
+
+#[test]
+fn redirect_sets_location() {
+    let resp = HttpResponse::from(WebResponse::Redirect("/login?next=%2F".to_string()));
+    assert_eq!(resp.status(), StatusCode::FOUND);
+    assert_eq!(header(&resp, "location"), Some("/login?next=%2F"));
+}
+
+#[tokio::test]
+async fn custom_sets_status_headers_cookies_and_body() {
+    let resp: WebResponse = CustomResponse::new(201)
+        .content_type("text/csv")
+        .header("X-Total", "2")
+        .header("X-Total", "3")
+        .cookie(Cookie::build("seen", "1").path("/").finish())
+        .body("a,b\n")
+        .into();
+    let resp = HttpResponse::from(resp);
+
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    assert_eq!(header(&resp, "content-type"), Some("text/csv"));
+    let totals: Vec<_> = resp.headers().get_all("x-total").collect();
+    assert_eq!(totals.len(), 2);
+    let cookie = resp.cookies().next().unwrap();
+    assert_eq!((cookie.name(), cookie.value()), ("seen", "1"));
+    assert_eq!(body(resp).await, b"a,b\n");
+}
+
+#[tokio::test]
+async fn invalid_custom_responses_become_server_errors() {
+    let resp = HttpResponse::from(WebResponse::Custom(CustomResponse::new(42)));
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+
+    let resp = HttpResponse::from(WebResponse::Custom(
+        CustomResponse::new(200).header("Bad Header", "x"),
+    ));
+    assert_eq!(resp.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[tokio::test]
+async fn streams_are_sent_as_produced() {
+    let (tx, stream) = StreamResponse::channel("text/plain", 2);
+    tokio::spawn(async move {
+        for part in ["one ", "two ", "three"] {
+            tx.send(Ok(part.as_bytes().to_vec())).await.unwrap();
+        }
+    });
+    let resp = HttpResponse::from(WebResponse::Stream(stream.length(13).attachment("n.txt")));
+    assert_eq!(header(&resp, "content-type"), Some("text/plain"));
+    assert_eq!(
+        header(&resp, "content-disposition"),
+        Some("attachment; filename=\"n.txt\"")
+    );
+    assert_eq!(body(resp).await, b"one two three");
+
+    let parts = chunk_stream(vec![Ok(b"a".to_vec()), Err("disk failed".to_string())]);
+    let resp = HttpResponse::from(WebResponse::Stream(StreamResponse::from_stream(
+        "application/octet-stream",
+        parts,
+    )));
+    assert!(to_bytes(resp.into_body()).await.is_err());
+}
+
+/// A stream yielding `chunks`, fed through a channel.
+fn chunk_stream(chunks: Vec<Chunk>) -> impl futures_core::Stream<Item = Chunk> {
+    let (tx, stream) = StreamResponse::channel("", chunks.len());
+    for chunk in chunks {
+        tx.try_send(chunk).unwrap();
+    }
+    stream.body
+}
+
+#[tokio::test]
+async fn streams_travel_through_route_hooks() {
+    let (tx, mut rx) = mpsc::channel(8);
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let PluginHookMessage::RouteUrl { reply, .. } = msg {
+                let (chunks, stream) = StreamResponse::channel("text/plain", 1);
+                let _ = reply.send(WebResponse::Stream(stream));
+                for i in 0..3 {
+                    let _ = chunks.send(Ok(i.to_string().into_bytes())).await;
+                }
+            }
+        }
+    });
+    let mut reg = PluginRegistry::new();
+    reg.add("archive", tx);
+
+    let ctx = RequestContext::new("h", &None, "GET", "/doc");
+    let resp = HookDispatcher::new(&reg).route_url(&ctx).await;
+    assert_eq!(body(HttpResponse::from(resp)).await, b"012");
+}
+
+fn archive_file(contents: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!(
+        "web-response-range-{}-{}.txt",
+        std::process::id(),
+        contents.len()
+    ));
+    std::fs::write(&path, contents).unwrap();
+    path
+}
+
+#[tokio::test]
+async fn file_paths_support_ranges_and_validators() {
+    let path = archive_file("0123456789");
+    let file = || {
+        WebResponse::OkFilePath(
+            "digits.txt".to_string(),
+            path.to_string_lossy().into_owned(),
+        )
+    };
+
+    let req = TestRequest::get().to_http_request();
+    let full = file().respond_to(&req);
+    assert_eq!(full.status(), StatusCode::OK);
+    assert_eq!(header(&full, "accept-ranges"), Some("bytes"));
+    let etag = header(&full, "etag").unwrap().to_string();
+    assert!(header(&full, "last-modified").is_some());
+    assert_eq!(
+        header(&full, "content-disposition"),
+        Some("attachment; filename=\"digits.txt\"")
+    );
+    assert_eq!(body(full).await, b"0123456789");
+
+    let req = TestRequest::get()
+        .insert_header(("Range", "bytes=2-5"))
+        .to_http_request();
+    let part = file().respond_to(&req);
+    assert_eq!(part.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(header(&part, "content-range"), Some("bytes 2-5/10"));
+    assert_eq!(body(part).await, b"2345");
+
+    let req = TestRequest::get()
+        .insert_header(("If-None-Match", etag.as_str()))
+        .to_http_request();
+    assert_eq!(file().respond_to(&req).status(), StatusCode::NOT_MODIFIED);
+
+    std::fs::remove_file(&path).unwrap();
+    let req = TestRequest::get().to_http_request();
+    assert_eq!(file().respond_to(&req).status(), StatusCode::NOT_FOUND);
+
+    // Everything else converts as without a request.
+    let resp = WebResponse::Conflict.respond_to(&req);
+    assert_eq!(resp.status(), StatusCode::CONFLICT);
+}